@@ -0,0 +1,393 @@
+#![forbid(unsafe_code)]
+#![warn(
+    clippy::dbg_macro,
+    clippy::decimal_literal_representation,
+    clippy::panic,
+    clippy::panic_in_result_fn,
+    clippy::print_stderr,
+    clippy::print_stdout,
+    clippy::todo,
+    clippy::unimplemented,
+    clippy::unwrap_in_result,
+    clippy::unwrap_used,
+    clippy::use_debug
+)]
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use cryo_cooler_controller_lib::{
+    MonitoringData, Parameter, Severity, Tec, TecStatus, VerifyConfig,
+};
+use serde_json::json;
+
+/// Command succeeded and no flag is in an abnormal state.
+const EXIT_OK: u8 = 0;
+/// Command failed, e.g. because the cooler could not be reached.
+const EXIT_ERROR: u8 = 1;
+/// At least one flag with [`Severity::Warning`] is in its abnormal state.
+const EXIT_WARNING: u8 = 2;
+/// At least one flag with [`Severity::Fault`] is in its abnormal state.
+const EXIT_FAULT: u8 = 3;
+
+/// Command line interface for the cryo cooler.
+///
+/// Commands that talk to the cooler exit with 0 if the cooler is healthy,
+/// 2 if a warning flag and 3 if a fault flag is in its abnormal state afterwards.
+/// Errors exit with 1.
+#[derive(Parser)]
+#[command(name = "cryo-cli", version)]
+struct Cli {
+    /// Serial port the cooler is connected to.
+    #[arg(short, long, global = true)]
+    port: Option<PathBuf>,
+    /// Print JSON instead of text.
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the available serial ports.
+    Ports,
+    /// Show the status flags of the cooler.
+    Status,
+    /// Print the measurements periodically, one line per sample.
+    Monitor {
+        /// Time between two samples in milliseconds.
+        #[arg(long, default_value_t = 1000)]
+        interval_ms: u64,
+        /// Stop after this many samples instead of running until interrupted.
+        #[arg(long)]
+        count: Option<u64>,
+    },
+    /// Write the PID values and offset and enable the TEC.
+    Enable {
+        /// P coefficient.
+        #[arg(long, default_value_t = 100.0)]
+        p: f32,
+        /// I coefficient.
+        #[arg(long, default_value_t = 1.0)]
+        i: f32,
+        /// D coefficient.
+        #[arg(long, default_value_t = 1.0)]
+        d: f32,
+        /// Offset to the dew point in C.
+        #[arg(long, default_value_t = 2.0, allow_negative_numbers = true)]
+        offset: f32,
+        /// Maximum TEC power in %.
+        #[arg(long, default_value_t = 100)]
+        max_power: u8,
+        /// Read back the written values.
+        #[arg(long)]
+        verify: bool,
+    },
+    /// Disable the TEC.
+    Disable,
+    /// Reset the cooler.
+    Reset,
+    /// Read a single value.
+    Get { value: Value },
+    /// Write a single parameter without changing whether the TEC is enabled.
+    Set {
+        parameter: SettableParameter,
+        #[arg(allow_negative_numbers = true)]
+        value: f32,
+        /// Read back the written value.
+        #[arg(long)]
+        verify: bool,
+    },
+    /// Show the version of this tool and, if a port is given, of the cooler.
+    Version,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Value {
+    P,
+    I,
+    D,
+    Offset,
+    Humidity,
+    TecTemperature,
+    BoardTemperature,
+    DewPoint,
+    Voltage,
+    Current,
+    PowerLevel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SettableParameter {
+    P,
+    I,
+    D,
+    Offset,
+}
+
+impl From<SettableParameter> for Parameter {
+    fn from(value: SettableParameter) -> Self {
+        match value {
+            SettableParameter::P => Parameter::P,
+            SettableParameter::I => Parameter::I,
+            SettableParameter::D => Parameter::D,
+            SettableParameter::Offset => Parameter::SetpointOffset,
+        }
+    }
+}
+
+fn value_name<T: ValueEnum>(value: T) -> String {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_owned())
+        .unwrap_or_default()
+}
+
+fn exit_code(status: TecStatus) -> u8 {
+    match status.worst_severity() {
+        None | Some(Severity::Info) => EXIT_OK,
+        Some(Severity::Warning) => EXIT_WARNING,
+        Some(Severity::Fault) => EXIT_FAULT,
+    }
+}
+
+fn read_value(tec: &mut Tec, value: Value) -> std::io::Result<f32> {
+    match value {
+        Value::P => tec.p_coefficient(),
+        Value::I => tec.i_coefficient(),
+        Value::D => tec.d_coefficient(),
+        Value::Offset => tec.setpoint_offset(),
+        Value::Humidity => tec.humidity(),
+        Value::TecTemperature => tec.tec_temperature(),
+        Value::BoardTemperature => tec.board_temperature(),
+        Value::DewPoint => tec.dew_point_temperature(),
+        Value::Voltage => tec.tec_voltage(),
+        Value::Current => tec.tec_current(),
+        Value::PowerLevel => Ok(tec.tec_power_level()? as f32),
+    }
+}
+
+fn print_ports(out: &mut impl Write, json: bool) -> std::io::Result<()> {
+    let ports = serial2::SerialPort::available_ports()?;
+    if json {
+        let ports: Vec<_> = ports
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        writeln!(out, "{}", json!(ports))
+    } else {
+        for port in ports {
+            writeln!(out, "{}", port.display())?;
+        }
+        Ok(())
+    }
+}
+
+fn print_status(out: &mut impl Write, status: TecStatus, json: bool) -> std::io::Result<()> {
+    if json {
+        let flags: Vec<_> = status.iter_names().map(|(name, _)| name).collect();
+        let severity = status.worst_severity().map(|severity| severity.to_string());
+        return writeln!(
+            out,
+            "{}",
+            json!({ "status": status.bits(), "flags": flags, "severity": severity })
+        );
+    }
+    for (name, flag) in TecStatus::all().iter_names() {
+        let set = status.contains(flag);
+        let marker = if set != flag.is_healthy_when_set() && flag.severity() > Severity::Info {
+            flag.severity().to_string()
+        } else {
+            String::new()
+        };
+        writeln!(
+            out,
+            "{name:<22} {:<7} {marker}",
+            if set { "set" } else { "cleared" }
+        )?;
+    }
+    Ok(())
+}
+
+fn print_sample(
+    out: &mut impl Write,
+    data: &MonitoringData,
+    status: TecStatus,
+    json: bool,
+) -> std::io::Result<()> {
+    if json {
+        return writeln!(
+            out,
+            "{}",
+            json!({
+                "timestamp": data.timestamp.to_rfc3339(),
+                "tec_temperature": data.tec_temperature,
+                "board_temperature": data.pcb_temperature,
+                "humidity": data.humidity,
+                "dew_point": data.dew_point_temperature,
+                "voltage": data.tec_voltage,
+                "current": data.tec_current,
+                "power_level": data.tec_power_level,
+                "status": status.bits(),
+            })
+        );
+    }
+    writeln!(
+        out,
+        "{:<10} {:>8.2} {:>8.2} {:>8.1} {:>8.2} {:>8.2} {:>8.2} {:>6} {:>#8x}",
+        data.timestamp.format("%H:%M:%S"),
+        data.tec_temperature,
+        data.pcb_temperature,
+        data.humidity,
+        data.dew_point_temperature,
+        data.tec_voltage,
+        data.tec_current,
+        data.tec_power_level,
+        status.bits(),
+    )
+}
+
+fn monitor(
+    out: &mut impl Write,
+    tec: &mut Tec,
+    interval: Duration,
+    count: Option<u64>,
+    json: bool,
+) -> std::io::Result<u8> {
+    if !json {
+        writeln!(
+            out,
+            "{:<10} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>6} {:>8}",
+            "time", "tec [C]", "pcb [C]", "hum [%]", "dew [C]", "U [V]", "I [A]", "power", "status"
+        )?;
+    }
+    let mut sample = 0;
+    loop {
+        let status = tec.heart_beat()?;
+        let data = tec.monitor()?;
+        print_sample(out, &data, status, json)?;
+        out.flush()?;
+        sample += 1;
+        if count.is_some_and(|count| sample >= count) {
+            return Ok(exit_code(status));
+        }
+        std::thread::sleep(interval);
+    }
+}
+
+fn run(cli: &Cli) -> std::io::Result<u8> {
+    let mut out = std::io::stdout().lock();
+    match (&cli.command, &cli.port) {
+        (Command::Ports, _) => {
+            print_ports(&mut out, cli.json)?;
+            return Ok(EXIT_OK);
+        }
+        (Command::Version, None) => {
+            if cli.json {
+                writeln!(out, "{}", json!({ "cli": env!("CARGO_PKG_VERSION") }))?;
+            } else {
+                writeln!(out, "cryo-cli {}", env!("CARGO_PKG_VERSION"))?;
+            }
+            return Ok(EXIT_OK);
+        }
+        _ => {}
+    }
+
+    let port = cli.port.as_ref().ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "No serial port given, use --port",
+        )
+    })?;
+    let mut tec = Tec::new(&port.as_os_str())?;
+
+    match &cli.command {
+        Command::Ports | Command::Status => {}
+        Command::Monitor { interval_ms, count } => {
+            return monitor(
+                &mut out,
+                &mut tec,
+                Duration::from_millis(*interval_ms),
+                *count,
+                cli.json,
+            );
+        }
+        Command::Enable {
+            p,
+            i,
+            d,
+            offset,
+            max_power,
+            verify,
+        } => {
+            if *verify {
+                tec.set_verify_config(Some(VerifyConfig::default()));
+            }
+            tec.enable(*p, *i, *d, *max_power, *offset)?;
+        }
+        Command::Disable => tec.disable()?,
+        Command::Reset => tec.reset()?,
+        Command::Get { value } => {
+            let read = read_value(&mut tec, *value)?;
+            if cli.json {
+                writeln!(out, "{}", json!({ value_name(*value): read }))?;
+            } else {
+                writeln!(out, "{read}")?;
+            }
+        }
+        Command::Set {
+            parameter,
+            value,
+            verify,
+        } => {
+            if *verify {
+                tec.set_verify_config(Some(VerifyConfig::default()));
+            }
+            tec.set_parameter((*parameter).into(), *value)?;
+        }
+        Command::Version => {
+            let firmware = tec.fw_version()?;
+            let firmware = format!(
+                "{:X}.{:X}.{:X}.{:X}",
+                firmware.0, firmware.1, firmware.2, firmware.3
+            );
+            let hardware = tec.hw_version()?;
+            if cli.json {
+                writeln!(
+                    out,
+                    "{}",
+                    json!({
+                        "cli": env!("CARGO_PKG_VERSION"),
+                        "firmware": firmware,
+                        "hardware": hardware,
+                    })
+                )?;
+            } else {
+                writeln!(out, "cryo-cli {}", env!("CARGO_PKG_VERSION"))?;
+                writeln!(out, "Firmware Version: {firmware}")?;
+                writeln!(out, "Hardware Version: {hardware}")?;
+            }
+        }
+    }
+
+    let status = tec.heart_beat()?;
+    if matches!(cli.command, Command::Status) {
+        print_status(&mut out, status, cli.json)?;
+    }
+    Ok(exit_code(status))
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(&cli) {
+        Ok(code) => ExitCode::from(code),
+        Err(e) => {
+            let _ = writeln!(std::io::stderr(), "Error: {e}");
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Requests from other programs, shared by the remote control interfaces.
+
+use cryo_cooler_controller_lib::{MonitoringData, TecStatus};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::controller::Controller;
+use crate::settings::SetpointMode;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiError {
+    MethodNotFound(String),
+    InvalidParams(String),
+    /// The request was valid, but the controller failed to execute it.
+    Failed(String),
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ApiError::MethodNotFound(method) => write!(f, "Unknown method {method}"),
+            ApiError::InvalidParams(reason) => write!(f, "Invalid parameters ({reason})"),
+            ApiError::Failed(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct EnableParams {
+    p: Option<f32>,
+    i: Option<f32>,
+    d: Option<f32>,
+    offset: Option<f32>,
+    max_power: Option<u8>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OffsetParams {
+    offset: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfileParams {
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PidParams {
+    p: f32,
+    i: f32,
+    d: f32,
+}
+
+pub fn monitoring_json(data: &MonitoringData) -> Value {
+    json!({
+        "timestamp": data.timestamp.to_rfc3339(),
+        "tec_temperature": data.tec_temperature,
+        "board_temperature": data.pcb_temperature,
+        "humidity": data.humidity,
+        "dew_point": data.dew_point_temperature,
+        "voltage": data.tec_voltage,
+        "current": data.tec_current,
+        "power_level": data.tec_power_level,
+    })
+}
+
+pub fn status_json(controller: &Controller) -> Value {
+    let status = controller.tec_status();
+    let flags: Vec<_> = status.iter_names().map(|(name, _)| name).collect();
+    json!({
+        "status": status.bits(),
+        "flags": flags,
+        "severity": status.worst_severity().map(|severity| severity.to_string()),
+        "enabled": !status.contains(TecStatus::LOW_POWER_MODE_ACTIVE),
+        "heartbeat_missed": controller.watchdog().is_heartbeat_missed(),
+    })
+}
+
+fn parse<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, ApiError> {
+    serde_json::from_value(params).map_err(|e| ApiError::InvalidParams(e.to_string()))
+}
+
+/// Executes `method` and returns its result.
+///
+/// Setpoint and PID changes are stored in the settings, like changes made in the GUI.
+pub fn call(controller: &mut Controller, method: &str, params: Value) -> Result<Value, ApiError> {
+    let params = if params.is_null() { json!({}) } else { params };
+    let result = match method {
+        "status" => return Ok(status_json(controller)),
+        "monitor" => return Ok(controller.last_data().map_or(Value::Null, monitoring_json)),
+        "enable" => {
+            let params: EnableParams = parse(params)?;
+            let saved = controller.update_settings(|settings| {
+                if let Some(p) = params.p {
+                    settings.set_p_coef(p)?;
+                }
+                if let Some(i) = params.i {
+                    settings.set_i_coef(i)?;
+                }
+                if let Some(d) = params.d {
+                    settings.set_d_coef(d)?;
+                }
+                if let Some(offset) = params.offset {
+                    settings.set_setpoint_mode(SetpointMode::Offset)?;
+                    settings.set_set_point(offset)?;
+                }
+                if let Some(max_power) = params.max_power {
+                    settings.set_max_power(max_power)?;
+                }
+                Ok(())
+            });
+            controller.enable().and(saved)
+        }
+        "disable" => controller.disable(),
+        "set_offset" => {
+            let params: OffsetParams = parse(params)?;
+            let saved = controller.update_settings(|settings| {
+                settings.set_setpoint_mode(SetpointMode::Offset)?;
+                settings.set_set_point(params.offset)
+            });
+            controller.apply_parameters().and(saved)
+        }
+        "set_pid" => {
+            let params: PidParams = parse(params)?;
+            let saved = controller.update_settings(|settings| {
+                settings.set_p_coef(params.p)?;
+                settings.set_i_coef(params.i)?;
+                settings.set_d_coef(params.d)
+            });
+            controller.apply_parameters().and(saved)
+        }
+        "save_profile" => {
+            let params: ProfileParams = parse(params)?;
+            controller.update_settings(|settings| settings.save_profile(&params.name))
+        }
+        "switch_profile" => {
+            let params: ProfileParams = parse(params)?;
+            if !controller.settings().has_profile(&params.name) {
+                return Err(ApiError::InvalidParams(format!(
+                    "no profile named {}",
+                    params.name
+                )));
+            }
+            let saved =
+                controller.update_settings(|settings| settings.switch_profile(&params.name));
+            controller.apply_parameters().and(saved)
+        }
+        _ => return Err(ApiError::MethodNotFound(method.to_owned())),
+    };
+    result.map(|()| Value::Bool(true)).map_err(ApiError::Failed)
+}
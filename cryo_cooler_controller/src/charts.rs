@@ -1,27 +1,226 @@
 use std::collections::VecDeque;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 use chrono::{DateTime, Utc};
 use iced::{
     alignment::{Horizontal, Vertical},
     widget::{
         canvas::{Cache, Frame, Geometry},
-        Column, Container, Row,
+        Column, Container, Row, Text,
     },
     Alignment, Element, Length, Size,
 };
 use plotters::{
-    prelude::ChartBuilder,
+    coord::Shift,
+    prelude::{BitMapBackend, ChartBuilder, DrawingArea, IntoDrawingArea, SVGBackend},
     series::AreaSeries,
     style::{Color, IntoFont, RGBAColor, RGBColor, ShapeStyle},
 };
 use plotters_backend::{DrawingBackend, FontTransform};
 use plotters_iced::{Chart, ChartWidget, Renderer};
 
+use crate::history::{HistoryDb, HistoryPoint, Quantity};
 use crate::Message;
 
 const PLOT_LINE_COLOR: RGBColor = RGBColor(0, 175, 255);
 const GRID_BOLD_COLOR: RGBAColor = RGBAColor(100, 100, 100, 0.5);
+/// Points loaded from the history database per chart.
+const HISTORY_POINTS: usize = 2000;
+/// How often a history range is reloaded to include new data.
+const HISTORY_REFRESH: Duration = Duration::from_secs(30);
+/// Time without zooming or panning before the shown points are reloaded.
+const RELOAD_DELAY: Duration = Duration::from_millis(300);
+/// Same as the background of the application theme.
+const EXPORT_BACKGROUND_COLOR: RGBColor = RGBColor(0x20, 0x22, 0x25);
+const EXPORT_WIDTH: u32 = 1400;
+const EXPORT_CHART_HEIGHT: u32 = 250;
+const EXPORT_HEADER_LINE_HEIGHT: u32 = 24;
+const CHART_MARGIN: u32 = 10;
+const Y_LABEL_AREA_SIZE: u32 = 28;
+/// Distance of the plotting area from the left and right edge of a chart.
+const PLOT_LEFT: f32 = (CHART_MARGIN + Y_LABEL_AREA_SIZE) as f32;
+const PLOT_RIGHT: f32 = CHART_MARGIN as f32;
+/// More points in the shown range are thinned out before drawing.
+const MAX_DRAWN_POINTS: usize = 2000;
+/// Change of the shown range per step of the mouse wheel.
+const ZOOM_STEP: f32 = 0.8;
+const MIN_WINDOW_SECS: i64 = 60;
+const MAX_LIVE_WINDOW_SECS: i64 = 24 * 3600;
+const MAX_HISTORY_WINDOW_SECS: i64 = 30 * 24 * 3600;
+
+/// Presets for the time range of the live charts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartWindow {
+    OneMinute,
+    FiveMinutes,
+    FifteenMinutes,
+    OneHour,
+    SixHours,
+    Day,
+}
+
+impl ChartWindow {
+    pub const ALL: &[ChartWindow] = &[
+        ChartWindow::OneMinute,
+        ChartWindow::FiveMinutes,
+        ChartWindow::FifteenMinutes,
+        ChartWindow::OneHour,
+        ChartWindow::SixHours,
+        ChartWindow::Day,
+    ];
+
+    pub fn secs(self) -> u64 {
+        match self {
+            ChartWindow::OneMinute => 60,
+            ChartWindow::FiveMinutes => 300,
+            ChartWindow::FifteenMinutes => 900,
+            ChartWindow::OneHour => 3600,
+            ChartWindow::SixHours => 6 * 3600,
+            ChartWindow::Day => 24 * 3600,
+        }
+    }
+
+    fn from_secs(secs: i64) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|window| window.secs() as i64 == secs)
+    }
+}
+
+impl std::fmt::Display for ChartWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChartWindow::OneMinute => write!(f, "1 Minute"),
+            ChartWindow::FiveMinutes => write!(f, "5 Minutes"),
+            ChartWindow::FifteenMinutes => write!(f, "15 Minutes"),
+            ChartWindow::OneHour => write!(f, "1 Hour"),
+            ChartWindow::SixHours => write!(f, "6 Hours"),
+            ChartWindow::Day => write!(f, "24 Hours"),
+        }
+    }
+}
+
+/// Time span shown by the charts, everything but [`ChartRange::Live`] is loaded from the history
+/// database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChartRange {
+    #[default]
+    Live,
+    SixHours,
+    Day,
+    Week,
+    Month,
+}
+
+impl ChartRange {
+    pub const ALL: &[ChartRange] = &[
+        ChartRange::Live,
+        ChartRange::SixHours,
+        ChartRange::Day,
+        ChartRange::Week,
+        ChartRange::Month,
+    ];
+
+    fn duration(self) -> Option<chrono::Duration> {
+        match self {
+            ChartRange::Live => None,
+            ChartRange::SixHours => Some(chrono::Duration::hours(6)),
+            ChartRange::Day => Some(chrono::Duration::days(1)),
+            ChartRange::Week => Some(chrono::Duration::weeks(1)),
+            ChartRange::Month => Some(chrono::Duration::days(30)),
+        }
+    }
+}
+
+impl std::fmt::Display for ChartRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChartRange::Live => write!(f, "Live"),
+            ChartRange::SixHours => write!(f, "6 Hours"),
+            ChartRange::Day => write!(f, "1 Day"),
+            ChartRange::Week => write!(f, "1 Week"),
+            ChartRange::Month => write!(f, "30 Days"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Svg,
+}
+
+impl ImageFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Svg => "svg",
+        }
+    }
+}
+
+/// The charts written by [`ChartGroup::export`], `None` for all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ExportTarget(pub Option<Quantity>);
+
+impl ExportTarget {
+    pub const ALL: &[ExportTarget] = &[
+        ExportTarget(None),
+        ExportTarget(Some(Quantity::TecTemperature)),
+        ExportTarget(Some(Quantity::Voltage)),
+        ExportTarget(Some(Quantity::Current)),
+        ExportTarget(Some(Quantity::PowerLevel)),
+        ExportTarget(Some(Quantity::Humidity)),
+        ExportTarget(Some(Quantity::DewPoint)),
+        ExportTarget(Some(Quantity::BoardTemperature)),
+    ];
+}
+
+impl std::fmt::Display for ExportTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            None => write!(f, "All Charts"),
+            Some(Quantity::TecTemperature) => write!(f, "TEC Temp"),
+            Some(Quantity::BoardTemperature) => write!(f, "PCB Temp"),
+            Some(Quantity::Humidity) => write!(f, "Humidity"),
+            Some(Quantity::DewPoint) => write!(f, "Dew Point"),
+            Some(Quantity::Voltage) => write!(f, "TEC Voltage"),
+            Some(Quantity::Current) => write!(f, "TEC Current"),
+            Some(Quantity::PowerLevel) => write!(f, "TEC Power Level"),
+        }
+    }
+}
+
+/// Draws `header` above `charts` like they are shown in the application.
+fn draw_export<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    header: &[String],
+    charts: &[&MonitoringChartf32],
+) -> Result<(), String> {
+    root.fill(&EXPORT_BACKGROUND_COLOR)
+        .map_err(|e| e.to_string())?;
+    let header_height = (header.len() as u32 + 1) * EXPORT_HEADER_LINE_HEIGHT;
+    let (header_area, chart_area) = root.split_vertically(header_height);
+    let style = ("sans-serif", 18)
+        .into_font()
+        .color(&plotters::style::colors::WHITE);
+    for (line, text) in header.iter().enumerate() {
+        let y = (line as u32 * EXPORT_HEADER_LINE_HEIGHT + EXPORT_HEADER_LINE_HEIGHT / 2) as i32;
+        header_area
+            .draw_text(text, &style, (10, y))
+            .map_err(|e| e.to_string())?;
+    }
+    for (area, chart) in chart_area
+        .split_evenly((charts.len(), 1))
+        .iter()
+        .zip(charts)
+    {
+        chart.build_chart(&ChartState::default(), ChartBuilder::on(area));
+    }
+    root.present().map_err(|e| e.to_string())
+}
 
 pub struct ChartGroup {
     tec_temp_chart: MonitoringChartf32,
@@ -32,6 +231,20 @@ pub struct ChartGroup {
     tec_current_chart: MonitoringChartf32,
     tec_power_chart: MonitoringChartf32,
     chart_height: f32,
+    range: ChartRange,
+    history_loaded: Option<Instant>,
+    /// Last zoom or pan since the shown points were loaded, until then the loaded points are
+    /// moved.
+    view_moved: Option<Instant>,
+    export_target: ExportTarget,
+    /// Length of the shown time range, the same for all charts.
+    window: chrono::Duration,
+    /// Window of [`ChartRange::Live`], kept while a history range is shown.
+    live_window: chrono::Duration,
+    /// End of the shown time range, `None` follows the newest data.
+    end: Option<DateTime<Utc>>,
+    /// Zoom and pan limits for recorded logs.
+    bounds: Option<(DateTime<Utc>, DateTime<Utc>)>,
 }
 
 impl Default for ChartGroup {
@@ -87,6 +300,14 @@ impl Default for ChartGroup {
                 "%".to_owned(),
             ),
             chart_height: 140.0,
+            range: ChartRange::Live,
+            history_loaded: None,
+            view_moved: None,
+            export_target: ExportTarget::default(),
+            window: chrono::Duration::seconds(300),
+            live_window: chrono::Duration::seconds(300),
+            end: None,
+            bounds: None,
         }
     }
 }
@@ -106,6 +327,262 @@ impl ChartGroup {
             .push_data(data.timestamp, data.tec_current);
         self.tec_power_chart
             .push_data(data.timestamp, data.tec_power_level as f32);
+        if self.end.is_none() && self.range == ChartRange::Live {
+            self.apply_view();
+        }
+    }
+
+    /// How long live data is kept, independent of the shown time range.
+    pub fn set_memory_limit(&mut self, limit: Duration) {
+        for (_, chart) in self.charts_mut() {
+            chart.limit = limit;
+        }
+    }
+
+    pub fn range(&self) -> ChartRange {
+        self.range
+    }
+
+    /// Switches to the live data for [`ChartRange::Live`], other ranges are shown after
+    /// [`ChartGroup::load_history`].
+    pub fn set_range(&mut self, range: ChartRange) {
+        self.range = range;
+        self.history_loaded = None;
+        self.view_moved = None;
+        self.end = None;
+        self.window = range.duration().unwrap_or(self.live_window);
+        if range == ChartRange::Live {
+            for (_, chart) in self.charts_mut() {
+                chart.show_live();
+            }
+        }
+        self.apply_view();
+    }
+
+    /// The preset matching the shown time range.
+    pub fn window(&self) -> Option<ChartWindow> {
+        ChartWindow::from_secs(self.window.num_seconds())
+    }
+
+    pub fn set_window(&mut self, window: chrono::Duration) {
+        self.window = self.clamp_window(window);
+        if self.range == ChartRange::Live {
+            self.live_window = self.window;
+        }
+        self.end = self.end.map(|end| self.clamp_end(end));
+        self.apply_view();
+    }
+
+    pub fn following(&self) -> bool {
+        self.end.is_none()
+    }
+
+    /// Follows the newest data or keeps the shown time range.
+    pub fn set_following(&mut self, following: bool) {
+        self.end = if following {
+            None
+        } else {
+            Some(self.view_range().1)
+        };
+        self.apply_view();
+    }
+
+    /// Limits zoom and pan to a recorded log and shows all of it.
+    pub fn set_bounds(&mut self, from: DateTime<Utc>, to: DateTime<Utc>) {
+        self.bounds = Some((from, to));
+        self.window = (to - from).max(chrono::Duration::seconds(MIN_WINDOW_SECS));
+        self.end = Some(to);
+        self.apply_view();
+    }
+
+    /// Scales the shown time range by `factor`, keeping the time at `anchor` (0 is the left,
+    /// 1 the right edge) in place. Following charts stay at the newest data.
+    pub fn zoom(&mut self, factor: f32, anchor: f32) {
+        let (from, to) = self.view_range();
+        self.window = self.clamp_window(scale(self.window, factor));
+        if self.end.is_some() {
+            let anchor_time = from + scale(to - from, anchor);
+            let end = anchor_time + scale(self.window, 1.0 - anchor);
+            self.end = Some(self.clamp_end(end));
+        }
+        if self.range == ChartRange::Live {
+            self.live_window = self.window;
+        }
+        self.move_view();
+    }
+
+    /// Moves the shown time range by `fraction` of its length, positive values move to older data.
+    pub fn pan(&mut self, fraction: f32) {
+        let end = self.view_range().1 - scale(self.window, fraction);
+        self.end = Some(self.clamp_end(end));
+        self.move_view();
+    }
+
+    /// Oldest and newest time shown.
+    pub fn view_range(&self) -> (DateTime<Utc>, DateTime<Utc>) {
+        let end = self.end.unwrap_or_else(|| match self.range {
+            ChartRange::Live => self
+                .tec_temp_chart
+                .data_points
+                .front()
+                .map_or_else(Utc::now, |point| point.0),
+            _ => Utc::now(),
+        });
+        (end - self.window, end)
+    }
+
+    fn clamp_window(&self, window: chrono::Duration) -> chrono::Duration {
+        let max = match (self.bounds, self.range) {
+            (Some((from, to)), _) => to - from,
+            (None, ChartRange::Live) => chrono::Duration::seconds(MAX_LIVE_WINDOW_SECS),
+            (None, _) => chrono::Duration::seconds(MAX_HISTORY_WINDOW_SECS),
+        };
+        let min = chrono::Duration::seconds(MIN_WINDOW_SECS);
+        window.min(max).max(min)
+    }
+
+    fn clamp_end(&self, end: DateTime<Utc>) -> DateTime<Utc> {
+        let (oldest, newest) = match (self.bounds, self.range) {
+            (Some(bounds), _) => bounds,
+            (None, ChartRange::Live) => {
+                let points = &self.tec_temp_chart.data_points;
+                let newest = points.front().map_or_else(Utc::now, |point| point.0);
+                (points.back().map_or(newest, |point| point.0), newest)
+            }
+            (None, _) => (DateTime::<Utc>::MIN_UTC, Utc::now()),
+        };
+        end.min(newest).max((oldest + self.window).min(newest))
+    }
+
+    /// Shows the current time range in all charts.
+    fn apply_view(&mut self) {
+        let view = self.view_range();
+        for (_, chart) in self.charts_mut() {
+            chart.view = view;
+            chart.cache.clear();
+        }
+    }
+
+    /// Shows the zoomed or panned time range with the points loaded so far, recorded points are
+    /// reloaded after [`RELOAD_DELAY`].
+    fn move_view(&mut self) {
+        if self.range != ChartRange::Live || self.bounds.is_some() {
+            self.view_moved = Some(Instant::now());
+        }
+        self.apply_view();
+    }
+
+    /// Whether recorded points have to be loaded for the time range shown after zooming or
+    /// panning, see [`ChartGroup::show_recorded`].
+    pub fn needs_reload(&self) -> bool {
+        self.view_moved
+            .is_some_and(|moved| moved.elapsed() >= RELOAD_DELAY)
+    }
+
+    /// Whether the shown history range is missing or outdated.
+    pub fn needs_history(&self) -> bool {
+        self.range != ChartRange::Live
+            && (self.needs_reload()
+                || self
+                    .history_loaded
+                    .is_none_or(|loaded| loaded.elapsed() >= HISTORY_REFRESH))
+    }
+
+    /// Loads the shown time range of a history range with averaged values.
+    pub fn load_history(&mut self, history: &HistoryDb) -> std::io::Result<()> {
+        if self.range == ChartRange::Live {
+            return Ok(());
+        }
+        self.history_loaded = Some(Instant::now());
+        self.show_recorded(|quantity, from, to, max_points| {
+            history.query(quantity, from, to, max_points)
+        })
+    }
+
+    /// Shows the values returned by `query` for the shown time range instead of the live data.
+    pub fn show_recorded(
+        &mut self,
+        query: impl Fn(
+            Quantity,
+            DateTime<Utc>,
+            DateTime<Utc>,
+            usize,
+        ) -> std::io::Result<Vec<HistoryPoint>>,
+    ) -> std::io::Result<()> {
+        self.view_moved = None;
+        let (from, to) = self.view_range();
+        for (quantity, chart) in self.charts_mut() {
+            chart.show_history(&query(quantity, from, to, HISTORY_POINTS)?);
+        }
+        self.apply_view();
+        Ok(())
+    }
+
+    pub fn set_export_target(&mut self, target: ExportTarget) {
+        self.export_target = target;
+    }
+
+    /// Writes the shown time range of the selected charts to a new file in `dir`, with `header`
+    /// above them.
+    pub fn export(
+        &self,
+        dir: &Path,
+        format: ImageFormat,
+        header: &[String],
+    ) -> std::io::Result<PathBuf> {
+        let target = self.export_target;
+        let charts: Vec<&MonitoringChartf32> = [
+            (Quantity::TecTemperature, &self.tec_temp_chart),
+            (Quantity::Voltage, &self.tec_voltage_chart),
+            (Quantity::Current, &self.tec_current_chart),
+            (Quantity::PowerLevel, &self.tec_power_chart),
+            (Quantity::Humidity, &self.humidty_chart),
+            (Quantity::DewPoint, &self.dew_point_chart),
+            (Quantity::BoardTemperature, &self.pcb_temp_chart),
+        ]
+        .into_iter()
+        .filter(|(quantity, _)| target.0.is_none_or(|selected| selected == *quantity))
+        .map(|(_, chart)| chart)
+        .collect();
+
+        std::fs::create_dir_all(dir)?;
+        let mut name = format!("cryo_cooler_{}", Utc::now().format("%Y_%m_%d_%H_%M_%S"));
+        if let Some(quantity) = target.0 {
+            name = format!("{name}_{}", quantity.column());
+        }
+        let path = dir.join(format!("{name}.{}", format.extension()));
+
+        let size = (
+            EXPORT_WIDTH,
+            (header.len() as u32 + 1) * EXPORT_HEADER_LINE_HEIGHT
+                + charts.len() as u32 * EXPORT_CHART_HEIGHT,
+        );
+        match format {
+            ImageFormat::Png => draw_export(
+                &BitMapBackend::new(&path, size).into_drawing_area(),
+                header,
+                &charts,
+            ),
+            ImageFormat::Svg => draw_export(
+                &SVGBackend::new(&path, size).into_drawing_area(),
+                header,
+                &charts,
+            ),
+        }
+        .map_err(std::io::Error::other)?;
+        Ok(path)
+    }
+
+    fn charts_mut(&mut self) -> [(Quantity, &mut MonitoringChartf32); 7] {
+        [
+            (Quantity::TecTemperature, &mut self.tec_temp_chart),
+            (Quantity::BoardTemperature, &mut self.pcb_temp_chart),
+            (Quantity::Humidity, &mut self.humidty_chart),
+            (Quantity::DewPoint, &mut self.dew_point_chart),
+            (Quantity::Voltage, &mut self.tec_voltage_chart),
+            (Quantity::Current, &mut self.tec_current_chart),
+            (Quantity::PowerLevel, &mut self.tec_power_chart),
+        ]
     }
 
     pub fn view(&self) -> Element<Message> {
@@ -122,6 +599,27 @@ impl ChartGroup {
             .into()
     }
 
+    pub fn view_export_controls(&self) -> Row<'_, Message> {
+        Row::new()
+            .push(iced::widget::pick_list(
+                ExportTarget::ALL,
+                Some(self.export_target),
+                Message::ExportTargetSelected,
+            ))
+            .push(
+                iced::widget::button(Text::new("PNG"))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::ExportCharts(ImageFormat::Png)),
+            )
+            .push(
+                iced::widget::button(Text::new("SVG"))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::ExportCharts(ImageFormat::Svg)),
+            )
+            .spacing(5)
+            .align_items(Alignment::Center)
+    }
+
     pub fn new_row(&self) -> Row<Message> {
         Row::new()
             .spacing(0)
@@ -139,6 +637,11 @@ struct MonitoringChartf32 {
     unit: String,
     cache: Cache,
     data_points: VecDeque<(DateTime<Utc>, f32)>,
+    /// Shown instead of `data_points` if set, newest first like them.
+    history_points: Option<VecDeque<(DateTime<Utc>, f32)>>,
+    /// Shown time range, set by the [`ChartGroup`].
+    view: (DateTime<Utc>, DateTime<Utc>),
+    /// How long `data_points` are kept.
     limit: Duration,
 }
 
@@ -158,18 +661,59 @@ impl MonitoringChartf32 {
             unit,
             cache: Cache::new(),
             data_points,
-            limit: Duration::from_secs(300),
+            history_points: None,
+            view: (DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MIN_UTC),
+            limit: Duration::from_secs(24 * 3600),
         }
     }
 
-    fn push_data(&mut self, time: DateTime<Utc>, value: f32) {
-        let cur_ms = time.timestamp_millis();
+    /// Widens the value axis to include `value`.
+    fn fit(&mut self, value: f32) {
         if value > self.max {
             self.max = (value - self.min) * 0.05 + value;
         }
         if value < self.min {
             self.min = value - (self.min - value) * 0.05;
         }
+    }
+
+    /// Points in the shown time range, newest first and thinned out to at most
+    /// [`MAX_DRAWN_POINTS`].
+    fn visible_points(&self) -> Vec<(DateTime<Utc>, f32)> {
+        let points = self.history_points.as_ref().unwrap_or(&self.data_points);
+        let (from, to) = self.view;
+        let start = points.partition_point(|point| point.0 > to);
+        let end = points.partition_point(|point| point.0 >= from);
+        let step = end.saturating_sub(start).div_ceil(MAX_DRAWN_POINTS).max(1);
+        points
+            .range(start..end.max(start))
+            .step_by(step)
+            .copied()
+            .collect()
+    }
+
+    fn show_history(&mut self, points: &[HistoryPoint]) {
+        for point in points {
+            self.fit(point.avg);
+        }
+        self.history_points = Some(
+            points
+                .iter()
+                .rev()
+                .map(|point| (point.timestamp, point.avg))
+                .collect(),
+        );
+        self.cache.clear();
+    }
+
+    fn show_live(&mut self) {
+        self.history_points = None;
+        self.cache.clear();
+    }
+
+    fn push_data(&mut self, time: DateTime<Utc>, value: f32) {
+        let cur_ms = time.timestamp_millis();
+        self.fit(value);
 
         self.data_points.push_front((time, value));
         loop {
@@ -203,8 +747,24 @@ impl MonitoringChartf32 {
 #[derive(Default)]
 struct ChartState {
     mouse_x_position: Option<f32>,
+    /// Last cursor position while the time axis is dragged.
+    drag_x_position: Option<f32>,
     bounds: iced::Rectangle,
 }
+
+/// Position of `x` in the plotting area of a chart at `bounds`, 0 is the left and 1 the right edge.
+fn plot_fraction(bounds: iced::Rectangle, x: f32) -> f32 {
+    ((x - bounds.x - PLOT_LEFT) / plot_width(bounds)).clamp(0.0, 1.0)
+}
+
+fn plot_width(bounds: iced::Rectangle) -> f32 {
+    (bounds.width - PLOT_LEFT - PLOT_RIGHT).max(1.0)
+}
+
+fn scale(duration: chrono::Duration, factor: f32) -> chrono::Duration {
+    chrono::Duration::milliseconds((duration.num_milliseconds() as f64 * f64::from(factor)) as i64)
+}
+
 impl Chart<Message> for MonitoringChartf32 {
     type State = ChartState;
 
@@ -215,22 +775,60 @@ impl Chart<Message> for MonitoringChartf32 {
         bounds: iced::Rectangle,
         cursor: iced::mouse::Cursor,
     ) -> (iced::event::Status, Option<Message>) {
-        if let iced::widget::canvas::Event::Mouse(mouse_event) = event {
-            if mouse_event == iced::mouse::Event::CursorLeft {
+        use iced::mouse::{Button, Event, ScrollDelta};
+
+        let iced::widget::canvas::Event::Mouse(mouse_event) = event else {
+            return (iced::event::Status::Ignored, None);
+        };
+        match mouse_event {
+            Event::CursorLeft => {
                 state.mouse_x_position = None;
+                state.drag_x_position = None;
+                return (iced::event::Status::Captured, None);
+            }
+            Event::ButtonReleased(Button::Left) if state.drag_x_position.is_some() => {
+                state.drag_x_position = None;
                 return (iced::event::Status::Captured, None);
             }
+            _ => {}
         }
-        if let iced::mouse::Cursor::Available(point) = cursor {
-            if point.x >= bounds.x && point.x <= bounds.x + bounds.width {
-                state.mouse_x_position = Some(point.x);
-                state.bounds = bounds;
-                self.cache.clear();
-            } else {
-                state.mouse_x_position = None;
+        let iced::mouse::Cursor::Available(point) = cursor else {
+            return (iced::event::Status::Ignored, None);
+        };
+        if let (Event::CursorMoved { .. }, Some(last_x)) = (mouse_event, state.drag_x_position) {
+            state.drag_x_position = Some(point.x);
+            state.mouse_x_position = None;
+            let fraction = (point.x - last_x) / plot_width(bounds);
+            return (
+                iced::event::Status::Captured,
+                Some(Message::ChartPan(fraction)),
+            );
+        }
+        if !cursor.is_over(bounds) {
+            state.mouse_x_position = None;
+            return (iced::event::Status::Ignored, None);
+        }
+        state.mouse_x_position = Some(point.x);
+        state.bounds = bounds;
+        self.cache.clear();
+        match mouse_event {
+            Event::WheelScrolled { delta } => {
+                let lines = match delta {
+                    ScrollDelta::Lines { y, .. } => y,
+                    ScrollDelta::Pixels { y, .. } => y / 40.0,
+                };
+                let anchor = plot_fraction(bounds, point.x);
+                (
+                    iced::event::Status::Captured,
+                    Some(Message::ChartZoom(ZOOM_STEP.powf(lines), anchor)),
+                )
+            }
+            Event::ButtonPressed(Button::Left) => {
+                state.drag_x_position = Some(point.x);
+                (iced::event::Status::Captured, None)
             }
+            _ => (iced::event::Status::Ignored, None),
         }
-        (iced::event::Status::Ignored, None)
     }
 
     fn mouse_interaction(
@@ -239,7 +837,9 @@ impl Chart<Message> for MonitoringChartf32 {
         _bounds: iced::Rectangle,
         _cursor: iced::mouse::Cursor,
     ) -> iced::mouse::Interaction {
-        if state.mouse_x_position.is_some() {
+        if state.drag_x_position.is_some() {
+            iced::mouse::Interaction::Grabbing
+        } else if state.mouse_x_position.is_some() {
             iced::mouse::Interaction::Crosshair
         } else {
             iced::mouse::Interaction::Idle
@@ -259,28 +859,17 @@ impl Chart<Message> for MonitoringChartf32 {
     fn build_chart<DB: DrawingBackend>(&self, state: &Self::State, mut chart: ChartBuilder<DB>) {
         //! This silently ignores error because there is nothing usefull that can be done about them.
 
-        let newest_time = self
-            .data_points
-            .front()
-            .unwrap_or(&(chrono::DateTime::<Utc>::MIN_UTC, 0.0))
-            .0;
-        let oldest_time = self
-            .data_points
-            .back()
-            .unwrap_or(&(chrono::DateTime::<Utc>::MIN_UTC, 0.0))
-            .0;
-
-        let hover_index = calc_hover_index(
-            state.mouse_x_position,
-            self.data_points.len(),
-            state.bounds.width,
-            state.bounds.x,
-        );
+        let data_points = self.visible_points();
+        let (oldest_time, newest_time) = self.view;
+        let multiple_days = newest_time - oldest_time > chrono::Duration::days(1);
+
+        let hover_index = state.mouse_x_position.and_then(|x| {
+            let time =
+                oldest_time + scale(newest_time - oldest_time, plot_fraction(state.bounds, x));
+            nearest_index(&data_points, time)
+        });
         let caption = if let Some(idx) = hover_index {
-            format!(
-                "{}  -  {:.2} {}",
-                self.title, self.data_points[idx].1, self.unit
-            )
+            format!("{}  -  {:.2} {}", self.title, data_points[idx].1, self.unit)
         } else {
             self.title.clone()
         };
@@ -288,8 +877,8 @@ impl Chart<Message> for MonitoringChartf32 {
         let mut chart = match chart
             .caption(caption, ("sans-serif", 22, &plotters::style::colors::WHITE))
             .x_label_area_size(14)
-            .y_label_area_size(28)
-            .margin(10)
+            .y_label_area_size(Y_LABEL_AREA_SIZE)
+            .margin(CHART_MARGIN)
             .build_cartesian_2d(oldest_time..newest_time, self.min..self.max)
         {
             Ok(chart) => chart,
@@ -314,12 +903,18 @@ impl Chart<Message> for MonitoringChartf32 {
                     .into_font()
                     .color(&plotters::style::colors::WHITE),
             )
-            .x_label_formatter(&|x| format!("{} ", x.time()))
+            .x_label_formatter(&|x| {
+                if multiple_days {
+                    format!("{} ", x.format("%m-%d %H:%M"))
+                } else {
+                    format!("{} ", x.time())
+                }
+            })
             .draw();
 
         let _ = chart.draw_series(
             AreaSeries::new(
-                self.data_points.iter().map(|x| (x.0, x.1)),
+                data_points.iter().map(|x| (x.0, x.1)),
                 self.min,
                 PLOT_LINE_COLOR.mix(0.175),
             )
@@ -328,7 +923,7 @@ impl Chart<Message> for MonitoringChartf32 {
 
         if let Some(idx) = hover_index {
             let _ = chart.draw_series(std::iter::once(plotters::prelude::Circle::new(
-                (self.data_points[idx].0, self.data_points[idx].1),
+                (data_points[idx].0, data_points[idx].1),
                 5_i32,
                 PLOT_LINE_COLOR.filled(),
             )));
@@ -336,23 +931,196 @@ impl Chart<Message> for MonitoringChartf32 {
     }
 }
 
-fn calc_hover_index(
-    x_pos_option: Option<f32>,
-    data_size: usize,
-    bounds_width: f32,
-    bounds_x: f32,
-) -> Option<usize> {
-    if data_size == 0 {
-        return None;
-    }
-    if let Some(x_pos) = x_pos_option {
-        let translation_factor = (data_size as f32) / bounds_width;
-        let mut hover_index =
-            data_size.saturating_sub(((x_pos - bounds_x) * translation_factor).round() as usize);
-        if hover_index >= data_size {
-            hover_index = data_size - 1;
-        }
-        return Some(hover_index);
-    }
-    None
+/// Index of the point closest to `time` in `points`, which are newest first.
+fn nearest_index(points: &[(DateTime<Utc>, f32)], time: DateTime<Utc>) -> Option<usize> {
+    let next = points.partition_point(|point| point.0 > time);
+    let older = points.get(next).map(|point| (next, point.0 - time));
+    let newer = next
+        .checked_sub(1)
+        .and_then(|index| points.get(index).map(|point| (index, point.0 - time)));
+    older
+        .into_iter()
+        .chain(newer)
+        .min_by_key(|(_, distance)| distance.abs())
+        .map(|(index, _)| index)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn time(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn secs(duration: chrono::Duration) -> i64 {
+        duration.num_seconds()
+    }
+
+    /// Charts limited to a recorded log of one hour.
+    fn recorded() -> ChartGroup {
+        let mut charts = ChartGroup::default();
+        charts.set_bounds(time(0), time(3600));
+        charts
+    }
+
+    #[test]
+    fn zoom() {
+        let mut charts = recorded();
+        assert_eq!(charts.view_range(), (time(0), time(3600)));
+
+        charts.zoom(0.5, 0.5);
+        assert_eq!(charts.view_range(), (time(900), time(2700)));
+        charts.zoom(0.5, 1.0);
+        assert_eq!(charts.view_range(), (time(1800), time(2700)));
+        charts.zoom(0.5, 0.0);
+        assert_eq!(charts.view_range(), (time(1800), time(2250)));
+
+        // Not wider than the log and not narrower than the minimum.
+        charts.zoom(0.001, 0.5);
+        assert_eq!(secs(charts.window), MIN_WINDOW_SECS);
+        charts.zoom(100.0, 0.5);
+        assert_eq!(charts.view_range(), (time(0), time(3600)));
+    }
+
+    #[test]
+    fn zoom_following() {
+        let mut charts = ChartGroup::default();
+        charts.tec_temp_chart.push_data(time(0), 0.0);
+        charts.zoom(2.0, 0.0);
+        assert!(charts.following());
+        assert_eq!(charts.view_range(), (time(-600), time(0)));
+        assert_eq!(secs(charts.live_window), 600);
+    }
+
+    #[test]
+    fn pan() {
+        let mut charts = recorded();
+        charts.set_window(chrono::Duration::seconds(600));
+        assert_eq!(charts.view_range(), (time(3000), time(3600)));
+
+        charts.pan(0.5);
+        assert_eq!(charts.view_range(), (time(2700), time(3300)));
+        charts.pan(-0.25);
+        assert_eq!(charts.view_range(), (time(2850), time(3450)));
+
+        // Stops at the ends of the log.
+        charts.pan(10.0);
+        assert_eq!(charts.view_range(), (time(0), time(600)));
+        charts.pan(-10.0);
+        assert_eq!(charts.view_range(), (time(3000), time(3600)));
+    }
+
+    #[test]
+    fn reload_after_moving() {
+        let mut charts = recorded();
+        assert!(!charts.needs_reload());
+        charts.pan(0.1);
+        assert!(charts.view_moved.is_some());
+        assert!(!charts.needs_reload());
+
+        charts.view_moved = Instant::now().checked_sub(RELOAD_DELAY);
+        assert!(charts.needs_reload());
+        charts.show_recorded(|_, _, _, _| Ok(Vec::new())).unwrap();
+        assert!(!charts.needs_reload());
+
+        // Live data is always complete.
+        let mut charts = ChartGroup::default();
+        charts.pan(0.1);
+        assert!(charts.view_moved.is_none());
+    }
+
+    #[test]
+    fn clamp_window() {
+        let mut charts = ChartGroup::default();
+        let window = |secs| chrono::Duration::seconds(secs);
+        assert_eq!(secs(charts.clamp_window(window(1))), MIN_WINDOW_SECS);
+        assert_eq!(
+            secs(charts.clamp_window(window(MAX_HISTORY_WINDOW_SECS))),
+            MAX_LIVE_WINDOW_SECS
+        );
+
+        charts.set_range(ChartRange::Week);
+        assert_eq!(
+            secs(charts.clamp_window(window(2 * MAX_HISTORY_WINDOW_SECS))),
+            MAX_HISTORY_WINDOW_SECS
+        );
+
+        let charts = recorded();
+        assert_eq!(secs(charts.clamp_window(window(7200))), 3600);
+        assert_eq!(secs(charts.clamp_window(window(1800))), 1800);
+    }
+
+    #[test]
+    fn clamp_end() {
+        let mut charts = recorded();
+        charts.set_window(chrono::Duration::seconds(600));
+        assert_eq!(charts.clamp_end(time(4000)), time(3600));
+        assert_eq!(charts.clamp_end(time(100)), time(600));
+        assert_eq!(charts.clamp_end(time(1000)), time(1000));
+
+        // Live charts are limited to the received data.
+        let mut charts = ChartGroup::default();
+        charts.tec_temp_chart.push_data(time(0), 0.0);
+        charts.tec_temp_chart.push_data(time(1000), 0.0);
+        assert_eq!(charts.clamp_end(time(2000)), time(1000));
+        assert_eq!(charts.clamp_end(time(0)), time(300));
+        assert_eq!(charts.clamp_end(time(500)), time(500));
+    }
+
+    #[test]
+    fn visible_points() {
+        let mut chart = ChartGroup::default().tec_temp_chart;
+        for secs in 0..10 {
+            chart.push_data(time(secs), secs as f32);
+        }
+        chart.view = (time(3), time(6));
+        assert_eq!(
+            chart.visible_points(),
+            [
+                (time(6), 6.0),
+                (time(5), 5.0),
+                (time(4), 4.0),
+                (time(3), 3.0)
+            ]
+        );
+        chart.view = (time(20), time(30));
+        assert!(chart.visible_points().is_empty());
+
+        // History points are shown instead of the live data.
+        chart.show_history(&[HistoryPoint {
+            timestamp: time(5),
+            min: 1.0,
+            avg: 2.0,
+            max: 3.0,
+        }]);
+        chart.view = (time(0), time(10));
+        assert_eq!(chart.visible_points(), [(time(5), 2.0)]);
+
+        // Thinned out.
+        chart.show_live();
+        for secs in 10..(3 * MAX_DRAWN_POINTS as i64) {
+            chart.push_data(time(secs), 0.0);
+        }
+        chart.view = (time(0), time(3 * MAX_DRAWN_POINTS as i64));
+        let points = chart.visible_points();
+        assert!(points.len() <= MAX_DRAWN_POINTS);
+        assert!(points.len() > MAX_DRAWN_POINTS / 2);
+        assert!(points.windows(2).all(|pair| pair[0].0 > pair[1].0));
+    }
+
+    #[test]
+    fn nearest_index() {
+        let points = [(time(30), 3.0), (time(20), 2.0), (time(10), 1.0)];
+        assert_eq!(super::nearest_index(&points, time(40)), Some(0));
+        assert_eq!(super::nearest_index(&points, time(26)), Some(0));
+        assert_eq!(super::nearest_index(&points, time(24)), Some(1));
+        assert_eq!(super::nearest_index(&points, time(20)), Some(1));
+        assert_eq!(super::nearest_index(&points, time(12)), Some(2));
+        assert_eq!(super::nearest_index(&points, time(0)), Some(2));
+        assert_eq!(super::nearest_index(&[], time(0)), None);
+    }
 }
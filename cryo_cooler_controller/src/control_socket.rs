@@ -0,0 +1,278 @@
+//! JSON-RPC 2.0 server on a unix domain socket, with one message per line.
+//!
+//! Only the owner of the socket file can connect. Besides the methods of [`crate::api::call`],
+//! `subscribe` sends a `monitoring` notification for every new sample to the connection.
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+
+use cryo_cooler_controller_lib::MonitoringData;
+use serde_json::{json, Value};
+
+use crate::api::{self, ApiError};
+use crate::controller::Controller;
+use crate::settings::AppSettings;
+
+const SOCKET_NAME: &str = "control.sock";
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+/// Slow clients are dropped instead of blocking the controller.
+const WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+
+pub fn socket_path(app_settings: &AppSettings) -> PathBuf {
+    app_settings.get_config_dir_path().join(SOCKET_NAME)
+}
+
+struct Incoming {
+    line: String,
+    stream: UnixStream,
+}
+
+#[derive(Debug, PartialEq)]
+struct Request {
+    /// `None` for notifications, which are not answered.
+    id: Option<Value>,
+    method: String,
+    params: Value,
+}
+
+pub struct ControlServer {
+    path: PathBuf,
+    stop: Arc<AtomicBool>,
+    requests: Receiver<Incoming>,
+    subscribers: Vec<UnixStream>,
+}
+
+impl ControlServer {
+    /// Creates the socket at `path`, replacing a stale one left by a crashed instance.
+    pub fn start(path: PathBuf) -> std::io::Result<Self> {
+        if path.exists() {
+            if UnixStream::connect(&path).is_ok() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::AddrInUse,
+                    format!("{} is used by another instance", path.display()),
+                ));
+            }
+            std::fs::remove_file(&path)?;
+        }
+        let listener = bind_private(&path)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, requests) = mpsc::channel();
+        let accept_stop = Arc::clone(&stop);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                if accept_stop.load(Ordering::Relaxed) {
+                    return;
+                }
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let sender = sender.clone();
+                std::thread::spawn(move || read_requests(stream, sender));
+            }
+        });
+        Ok(Self {
+            path,
+            stop,
+            requests,
+            subscribers: Vec::new(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Answers the received requests.
+    ///
+    /// Has to be called periodically by the owner of the controller.
+    pub fn poll(&mut self, controller: &mut Controller) {
+        while let Ok(mut incoming) = self.requests.try_recv() {
+            let response = match parse_request(&incoming.line) {
+                Ok(request) if request.method == "subscribe" => {
+                    if let Ok(stream) = incoming.stream.try_clone() {
+                        self.subscribers.push(stream);
+                    }
+                    request.id.map(|id| result_response(id, Value::Bool(true)))
+                }
+                Ok(request) => {
+                    let result = api::call(controller, &request.method, request.params);
+                    request.id.map(|id| match result {
+                        Ok(result) => result_response(id, result),
+                        Err(error) => {
+                            let code = match error {
+                                ApiError::MethodNotFound(_) => METHOD_NOT_FOUND,
+                                ApiError::InvalidParams(_) => INVALID_PARAMS,
+                                ApiError::Failed(_) => SERVER_ERROR,
+                            };
+                            error_response(id, code, &error.to_string())
+                        }
+                    })
+                }
+                Err(response) => Some(response),
+            };
+            if let Some(response) = response {
+                let _ = write_message(&mut incoming.stream, &response);
+            }
+        }
+    }
+
+    /// Sends `data` to all subscribed connections.
+    pub fn publish(&mut self, data: &MonitoringData) {
+        let notification = json!({
+            "jsonrpc": "2.0",
+            "method": "monitoring",
+            "params": api::monitoring_json(data),
+        });
+        self.subscribers
+            .retain_mut(|stream| write_message(stream, &notification).is_ok());
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // Wakes up the accept loop, so it sees the stop flag.
+        let _ = UnixStream::connect(&self.path);
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Binds a socket at `path` that only the owner can connect to.
+///
+/// The socket is created in a private directory and only moved to `path` once its permissions
+/// are restricted, so other users cannot connect in between.
+fn bind_private(path: &Path) -> std::io::Result<UnixListener> {
+    let dir = path.with_extension("tmp");
+    // Left by a crashed instance.
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)?;
+    }
+    std::fs::DirBuilder::new().mode(0o700).create(&dir)?;
+    let private_path = dir.join(SOCKET_NAME);
+    let listener = UnixListener::bind(&private_path).and_then(|listener| {
+        std::fs::set_permissions(&private_path, std::fs::Permissions::from_mode(0o600))?;
+        std::fs::rename(&private_path, path)?;
+        Ok(listener)
+    });
+    let _ = std::fs::remove_dir_all(&dir);
+    listener
+}
+
+fn read_requests(stream: UnixStream, sender: Sender<Incoming>) {
+    if stream.set_write_timeout(Some(WRITE_TIMEOUT)).is_err() {
+        return;
+    }
+    let Ok(reader) = stream.try_clone() else {
+        return;
+    };
+    for line in BufReader::new(reader).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Ok(stream) = stream.try_clone() else {
+            return;
+        };
+        if sender.send(Incoming { line, stream }).is_err() {
+            return;
+        }
+    }
+}
+
+fn write_message(stream: &mut UnixStream, message: &Value) -> std::io::Result<()> {
+    writeln!(stream, "{message}")
+}
+
+fn result_response(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// Returns the request or the error response to send.
+fn parse_request(line: &str) -> Result<Request, Value> {
+    let value: Value = serde_json::from_str(line)
+        .map_err(|e| error_response(Value::Null, PARSE_ERROR, &e.to_string()))?;
+    let id = value.get("id").cloned();
+    let invalid =
+        |message: &str| error_response(id.clone().unwrap_or_default(), INVALID_REQUEST, message);
+    if value.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+        return Err(invalid("Expected jsonrpc 2.0"));
+    }
+    let Some(method) = value.get("method").and_then(Value::as_str) else {
+        return Err(invalid("Missing method"));
+    };
+    Ok(Request {
+        id,
+        method: method.to_owned(),
+        params: value.get("params").cloned().unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests() {
+        assert_eq!(
+            parse_request(
+                r#"{"jsonrpc": "2.0", "id": 1, "method": "set_offset", "params": {"offset": 3}}"#
+            ),
+            Ok(Request {
+                id: Some(json!(1)),
+                method: "set_offset".to_owned(),
+                params: json!({ "offset": 3 }),
+            })
+        );
+        assert_eq!(
+            parse_request(r#"{"jsonrpc": "2.0", "method": "disable"}"#)
+                .unwrap()
+                .id,
+            None
+        );
+        let error = parse_request("{").unwrap_err();
+        assert_eq!(error["error"]["code"], PARSE_ERROR);
+        assert_eq!(error["id"], Value::Null);
+        let error = parse_request(r#"{"id": 2, "method": "status"}"#).unwrap_err();
+        assert_eq!(error["error"]["code"], INVALID_REQUEST);
+        assert_eq!(error["id"], 2);
+    }
+
+    #[test]
+    fn private_socket() {
+        let dir = tempdir::TempDir::new("control_socket").unwrap();
+        let path = dir.path().join(SOCKET_NAME);
+        let server = ControlServer::start(path.clone()).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(UnixStream::connect(&path).is_ok());
+        assert!(!path.with_extension("tmp").exists());
+
+        // A second instance does not take over the socket.
+        assert_eq!(
+            ControlServer::start(path.clone()).err().unwrap().kind(),
+            std::io::ErrorKind::AddrInUse
+        );
+        drop(server);
+        assert!(!path.exists());
+    }
+}
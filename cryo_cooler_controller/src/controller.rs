@@ -0,0 +1,881 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+use cryo_cooler_controller_lib::{
+    Calibration, DeviceParameters, MonitoringData, OcpEvent, OcpFilter, OcpState, ProtocolStats,
+    SelfTest, SelfTestConfig, Severity, StatusWord, Tec, TecStatus, VerifyError, Watchdog,
+    WatchdogEvent,
+};
+
+use serde_json::Value;
+
+use crate::event_log::EventLog;
+use crate::live_apply::LiveApply;
+use crate::settings::{AppSettings, OcpResponse, RecoveryAction, SetpointMode};
+
+const PARAMETER_READ_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalibrationQuantity {
+    Voltage,
+    Current,
+}
+
+impl CalibrationQuantity {
+    pub const ALL: &[CalibrationQuantity] =
+        &[CalibrationQuantity::Voltage, CalibrationQuantity::Current];
+
+    pub fn unit(self) -> &'static str {
+        match self {
+            CalibrationQuantity::Voltage => "V",
+            CalibrationQuantity::Current => "A",
+        }
+    }
+}
+
+impl std::fmt::Display for CalibrationQuantity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CalibrationQuantity::Voltage => write!(f, "TEC Voltage"),
+            CalibrationQuantity::Current => write!(f, "TEC Current"),
+        }
+    }
+}
+
+/// Setpoint captured when the TEC was enabled.
+#[derive(Debug, Clone, Copy)]
+enum ActiveSetpoint {
+    Offset(f32),
+    Absolute { target: f32, safety_margin: f32 },
+}
+
+/// Setpoint and PID settings, used to undo a live apply.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ParameterSnapshot {
+    p: f32,
+    i: f32,
+    d: f32,
+    setpoint_mode: SetpointMode,
+    set_point: f32,
+    absolute_target: f32,
+    safety_margin: f32,
+}
+
+/// Something the user, or a program on their behalf, did to the cooler.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserAction {
+    pub timestamp: DateTime<Utc>,
+    pub description: String,
+}
+
+/// Appends `path = value` for every leaf of `after` that differs from `before`.
+fn changed_settings(before: &Value, after: &Value, path: &str, changes: &mut Vec<String>) {
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => {
+            for (key, value) in after {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                changed_settings(
+                    before.get(key).unwrap_or(&Value::Null),
+                    value,
+                    &path,
+                    changes,
+                );
+            }
+        }
+        (before, after) if before != after => changes.push(format!("{path} = {after}")),
+        _ => {}
+    }
+}
+
+/// Monitors and regulates a connected cooler, independent of the GUI.
+///
+/// [`Controller::tick`] has to be called periodically, all safety logic runs from there.
+pub struct Controller {
+    port_path: PathBuf,
+    watchdog: Watchdog,
+    enabled_before_fault: bool,
+    /// Set if the user has to decide whether the TEC is enabled again after a fault.
+    recovery_prompt: bool,
+    ocp_filter: OcpFilter,
+    active_setpoint: ActiveSetpoint,
+    /// Added to the configured offset after sustained over current events.
+    offset_backoff: f32,
+    /// Offset last written to the cooler, `None` while the TEC is not running with it.
+    sent_offset: Option<f32>,
+    sensor_disagreement: bool,
+    tec: Tec,
+    last_data: Option<MonitoringData>,
+    /// Parameters last read from the cooler.
+    device_parameters: Option<DeviceParameters>,
+    last_parameter_read: Option<Instant>,
+    live_apply: LiveApply<ParameterSnapshot>,
+    /// Running or finished self-test, the TEC is not regulated while it runs.
+    self_test: Option<SelfTest>,
+    tec_status: TecStatus,
+    status_word: StatusWord,
+    firmware_version_major: u8,
+    firmware_version_minor: u8,
+    hardware_version: u32,
+    event_log: EventLog,
+    /// Actions not yet taken by [`Controller::take_user_actions`].
+    user_actions: Vec<UserAction>,
+    error_text: Option<String>,
+    app_settings: AppSettings,
+}
+
+impl Controller {
+    pub fn new<T>(serial_port: &T, app_settings: AppSettings) -> Result<Self, std::io::Error>
+    where
+        T: AsRef<Path> + std::fmt::Debug,
+    {
+        let mut tec = Tec::new(&serial_port.as_ref().as_os_str())?;
+        tec.set_calibration(app_settings.get_calibration(serial_port.as_ref()));
+        tec.set_verify_config(app_settings.get_verify_config());
+        let fw_version = tec.fw_version()?;
+        let hardware_version = tec.hw_version()?;
+        let status_word = tec.status_word()?;
+        let tec_status = status_word.status();
+        let mut controller = Controller {
+            port_path: serial_port.as_ref().to_path_buf(),
+            watchdog: Watchdog::new(app_settings.get_watchdog_config(), Instant::now()),
+            enabled_before_fault: !tec_status.contains(TecStatus::LOW_POWER_MODE_ACTIVE),
+            recovery_prompt: false,
+            ocp_filter: OcpFilter::new(app_settings.get_ocp_config()),
+            active_setpoint: ActiveSetpoint::Offset(app_settings.get_set_point()),
+            offset_backoff: 0.0,
+            sent_offset: None,
+            sensor_disagreement: false,
+            tec,
+            last_data: None,
+            device_parameters: None,
+            last_parameter_read: None,
+            live_apply: LiveApply::default(),
+            self_test: None,
+            tec_status,
+            status_word,
+            firmware_version_major: fw_version.0,
+            firmware_version_minor: fw_version.1,
+            hardware_version,
+            event_log: Default::default(),
+            user_actions: Vec::new(),
+            error_text: None,
+            app_settings,
+        };
+        if controller.app_settings.get_enable_on_startup() {
+            let _ = controller.enable_tec();
+        }
+        Ok(controller)
+    }
+
+    pub fn settings(&self) -> &AppSettings {
+        &self.app_settings
+    }
+
+    pub fn port_path(&self) -> &Path {
+        &self.port_path
+    }
+
+    pub fn tec_status(&self) -> TecStatus {
+        self.tec_status
+    }
+
+    pub fn status_word(&self) -> StatusWord {
+        self.status_word
+    }
+
+    pub fn watchdog(&self) -> &Watchdog {
+        &self.watchdog
+    }
+
+    pub fn ocp_state(&self) -> OcpState {
+        self.ocp_filter.state()
+    }
+
+    pub fn sensor_disagreement(&self) -> bool {
+        self.sensor_disagreement
+    }
+
+    pub fn sent_offset(&self) -> Option<f32> {
+        self.sent_offset
+    }
+
+    pub fn last_data(&self) -> Option<&MonitoringData> {
+        self.last_data.as_ref()
+    }
+
+    pub fn device_parameters(&self) -> Option<DeviceParameters> {
+        self.device_parameters
+    }
+
+    pub fn live_apply_pending(&self) -> bool {
+        self.live_apply.is_pending()
+    }
+
+    pub fn can_undo_live_apply(&self) -> bool {
+        self.live_apply.can_undo()
+    }
+
+    pub fn self_test(&self) -> Option<&SelfTest> {
+        self.self_test.as_ref()
+    }
+
+    pub fn protocol_stats(&self) -> &ProtocolStats {
+        self.tec.stats()
+    }
+
+    pub fn calibration(&self) -> Calibration {
+        self.tec.calibration()
+    }
+
+    pub fn firmware_version(&self) -> (u8, u8) {
+        (self.firmware_version_major, self.firmware_version_minor)
+    }
+
+    pub fn hardware_version(&self) -> u32 {
+        self.hardware_version
+    }
+
+    pub fn event_log(&self) -> &EventLog {
+        &self.event_log
+    }
+
+    pub fn event_log_mut(&mut self) -> &mut EventLog {
+        &mut self.event_log
+    }
+
+    pub fn error_text(&self) -> Option<&str> {
+        self.error_text.as_deref()
+    }
+
+    pub fn set_error(&mut self, text: String) {
+        self.error_text = Some(text);
+    }
+
+    pub fn take_error(&mut self) -> Option<String> {
+        self.error_text.take()
+    }
+
+    /// Shows `text` as error and returns it to callers that report it themselves.
+    fn fail(&mut self, text: String) -> Result<(), String> {
+        self.error_text = Some(text.clone());
+        Err(text)
+    }
+
+    /// Actions since the last call, oldest first.
+    pub fn take_user_actions(&mut self) -> Vec<UserAction> {
+        std::mem::take(&mut self.user_actions)
+    }
+
+    fn record_action(&mut self, description: String) {
+        self.user_actions.push(UserAction {
+            timestamp: Utc::now(),
+            description,
+        });
+    }
+
+    pub fn recovery_prompt(&self) -> bool {
+        self.recovery_prompt
+    }
+
+    /// Takes over the setpoint from the settings.
+    fn capture_setpoint(&mut self) {
+        self.active_setpoint = match self.app_settings.get_setpoint_mode() {
+            SetpointMode::Offset => ActiveSetpoint::Offset(self.app_settings.get_set_point()),
+            SetpointMode::Absolute => ActiveSetpoint::Absolute {
+                target: self.app_settings.get_absolute_target(),
+                safety_margin: self.app_settings.get_safety_margin(),
+            },
+        };
+    }
+
+    fn enable_tec(&mut self) -> Result<(), String> {
+        self.capture_setpoint();
+        let offset = self.device_offset();
+        match self.tec.enable(
+            self.app_settings.get_p_coef(),
+            self.app_settings.get_i_coef(),
+            self.app_settings.get_d_coef(),
+            self.app_settings.get_max_power(),
+            offset,
+        ) {
+            Ok(()) => {
+                self.sent_offset = Some(offset);
+                Ok(())
+            }
+            Err(err) => {
+                if let Some(verify_error) = VerifyError::from_io(&err) {
+                    self.event_log
+                        .push(Utc::now(), Severity::Fault, verify_error.to_string());
+                }
+                self.fail(format!("Failed to enable TEC ({err})"))
+            }
+        }
+    }
+
+    fn disable_tec(&mut self) -> Result<(), String> {
+        match self.tec.disable() {
+            Ok(()) => {
+                self.sent_offset = None;
+                Ok(())
+            }
+            Err(err) => self.fail(format!("Failed to disable TEC ({err})")),
+        }
+    }
+
+    /// Enables the TEC with the configured values.
+    pub fn enable(&mut self) -> Result<(), String> {
+        self.offset_backoff = 0.0;
+        self.live_apply.reset();
+        self.record_action("TEC enabled".to_owned());
+        self.enable_tec()
+    }
+
+    pub fn disable(&mut self) -> Result<(), String> {
+        self.live_apply.reset();
+        self.record_action("TEC disabled".to_owned());
+        self.disable_tec()
+    }
+
+    /// Answers the question raised after recovering from a fault.
+    pub fn answer_recovery(&mut self, re_enable: bool) {
+        self.recovery_prompt = false;
+        self.record_action(if re_enable {
+            "TEC enabled after recovery".to_owned()
+        } else {
+            "TEC kept disabled after recovery".to_owned()
+        });
+        // Failures are shown like any other error.
+        let _ = if re_enable {
+            self.enable_tec()
+        } else {
+            self.disable_tec()
+        };
+    }
+
+    /// Offset as it has to be written to the cooler.
+    fn device_offset(&self) -> f32 {
+        //! If the host dew point is used, the offset is corrected by the difference to the
+        //! dew point of the cooler, so the cooler regulates relative to the host dew point.
+        let Some(data) = &self.last_data else {
+            return match self.active_setpoint {
+                ActiveSetpoint::Offset(offset) => offset,
+                ActiveSetpoint::Absolute { safety_margin, .. } => safety_margin,
+            } + self.offset_backoff;
+        };
+        let reference_dew_point = match data.host_dew_point() {
+            Some(host_dew_point) if self.app_settings.get_use_host_dew_point() => host_dew_point,
+            _ => data.dew_point_temperature,
+        };
+        let offset = match self.active_setpoint {
+            ActiveSetpoint::Offset(offset) => {
+                offset + reference_dew_point - data.dew_point_temperature
+            }
+            ActiveSetpoint::Absolute {
+                target,
+                safety_margin,
+            } => cryo_cooler_controller_lib::offset_for_target(
+                target,
+                reference_dew_point,
+                data.dew_point_temperature,
+                safety_margin,
+            ),
+        };
+        offset + self.offset_backoff
+    }
+
+    /// Parameters the cooler is expected to use.
+    pub fn configured_parameters(&self) -> DeviceParameters {
+        DeviceParameters {
+            setpoint_offset: self.sent_offset.unwrap_or_else(|| self.device_offset()),
+            p: self.app_settings.get_p_coef(),
+            i: self.app_settings.get_i_coef(),
+            d: self.app_settings.get_d_coef(),
+        }
+    }
+
+    fn refresh_device_parameters(&mut self, now: Instant) {
+        if self
+            .last_parameter_read
+            .is_some_and(|last| now.saturating_duration_since(last) < PARAMETER_READ_INTERVAL)
+        {
+            return;
+        }
+        self.last_parameter_read = Some(now);
+        match self.tec.read_parameters() {
+            Ok(parameters) => self.device_parameters = Some(parameters),
+            Err(err) => {
+                self.error_text = Some(format!("Failed to read parameters from cooler ({err})"))
+            }
+        }
+    }
+
+    /// Writes the configured setpoint and P/I/D without disabling the TEC.
+    pub fn apply_parameters(&mut self) -> Result<(), String> {
+        self.capture_setpoint();
+        let offset = self.device_offset();
+        let result = self
+            .tec
+            .set_pid(
+                self.app_settings.get_p_coef(),
+                self.app_settings.get_i_coef(),
+                self.app_settings.get_d_coef(),
+            )
+            .and_then(|()| self.tec.set_setpoint_offset(offset));
+        self.last_parameter_read = None;
+        match result {
+            Ok(()) => {
+                if self.sent_offset.is_some() {
+                    self.sent_offset = Some(offset);
+                }
+                self.event_log.push(
+                    Utc::now(),
+                    Severity::Info,
+                    "Applied PID and offset to cooler".to_owned(),
+                );
+                Ok(())
+            }
+            Err(err) => self.fail(format!("Failed to apply parameters ({err})")),
+        }
+    }
+
+    fn parameter_snapshot(&self) -> ParameterSnapshot {
+        ParameterSnapshot {
+            p: self.app_settings.get_p_coef(),
+            i: self.app_settings.get_i_coef(),
+            d: self.app_settings.get_d_coef(),
+            setpoint_mode: self.app_settings.get_setpoint_mode(),
+            set_point: self.app_settings.get_set_point(),
+            absolute_target: self.app_settings.get_absolute_target(),
+            safety_margin: self.app_settings.get_safety_margin(),
+        }
+    }
+
+    fn restore_parameter_snapshot(&mut self, snapshot: ParameterSnapshot) -> std::io::Result<()> {
+        self.app_settings.set_p_coef(snapshot.p)?;
+        self.app_settings.set_i_coef(snapshot.i)?;
+        self.app_settings.set_d_coef(snapshot.d)?;
+        self.app_settings
+            .set_setpoint_mode(snapshot.setpoint_mode)?;
+        self.app_settings.set_set_point(snapshot.set_point)?;
+        self.app_settings
+            .set_absolute_target(snapshot.absolute_target)?;
+        self.app_settings.set_safety_margin(snapshot.safety_margin)
+    }
+
+    /// Changes the settings and takes over the derived configurations.
+    pub fn update_settings(
+        &mut self,
+        update: impl FnOnce(&mut AppSettings) -> std::io::Result<()>,
+    ) -> Result<(), String> {
+        let before = self.app_settings.get_settings_json();
+        let saved = match update(&mut self.app_settings) {
+            Ok(()) => Ok(()),
+            Err(e) => self.fail(format!("Failed to save settings ({e})")),
+        };
+        let mut changes = Vec::new();
+        changed_settings(
+            &before,
+            &self.app_settings.get_settings_json(),
+            "",
+            &mut changes,
+        );
+        if !changes.is_empty() {
+            self.record_action(format!("Settings changed: {}", changes.join(", ")));
+        }
+        self.watchdog
+            .set_config(self.app_settings.get_watchdog_config());
+        self.ocp_filter
+            .set_config(self.app_settings.get_ocp_config());
+        self.tec
+            .set_verify_config(self.app_settings.get_verify_config());
+        if !self.app_settings.get_live_apply() {
+            self.live_apply.cancel_pending();
+        }
+        saved.and(self.sync_offset())
+    }
+
+    /// Changes setpoint or PID settings, which are written to the cooler if live apply is active.
+    pub fn edit_parameters(
+        &mut self,
+        update: impl FnOnce(&mut AppSettings) -> std::io::Result<()>,
+    ) {
+        let previous = self.parameter_snapshot();
+        // Failures are shown like any other error.
+        let _ = self.update_settings(update);
+        if !self.app_settings.get_live_apply() || self.sent_offset.is_none() {
+            return;
+        }
+        self.live_apply.edited(previous, Instant::now());
+    }
+
+    fn apply_pending_edits(&mut self, now: Instant) {
+        if self.sent_offset.is_none() {
+            self.live_apply.cancel_pending();
+        }
+        if self.live_apply.poll(self.parameter_snapshot(), now) {
+            let _ = self.apply_parameters();
+        }
+    }
+
+    pub fn undo_live_apply(&mut self) {
+        let Some(previous) = self.live_apply.take_undo() else {
+            return;
+        };
+        self.record_action("Live apply undone".to_owned());
+        if let Err(e) = self.restore_parameter_snapshot(previous) {
+            self.error_text = Some(format!("Failed to save settings ({e})"));
+            return;
+        }
+        let _ = self.apply_parameters();
+    }
+
+    /// Takes over P/I/D and, in offset mode, the offset read from the cooler into the settings.
+    pub fn load_parameters_from_device(&mut self) {
+        let Some(parameters) = self.device_parameters else {
+            return;
+        };
+        self.record_action("Parameters loaded from device".to_owned());
+        let mut result = self
+            .app_settings
+            .set_p_coef(parameters.p)
+            .and_then(|()| self.app_settings.set_i_coef(parameters.i))
+            .and_then(|()| self.app_settings.set_d_coef(parameters.d));
+        if self.app_settings.get_setpoint_mode() == SetpointMode::Offset {
+            result =
+                result.and_then(|()| self.app_settings.set_set_point(parameters.setpoint_offset));
+        }
+        if let Err(e) = result {
+            self.error_text = Some(format!("Failed to save settings ({e})"));
+        }
+    }
+
+    /// Writes the offset again if it drifted from the one last written.
+    fn sync_offset(&mut self) -> Result<(), String> {
+        let Some(sent_offset) = self.sent_offset else {
+            return Ok(());
+        };
+        if self.tec_status.contains(TecStatus::LOW_POWER_MODE_ACTIVE) {
+            return Ok(());
+        }
+        let offset = self.device_offset();
+        if (offset - sent_offset).abs() < 0.1 {
+            return Ok(());
+        }
+        match self.tec.set_setpoint_offset(offset) {
+            Ok(()) => {
+                self.sent_offset = Some(offset);
+                Ok(())
+            }
+            Err(err) => self.fail(format!("Failed to set offset ({err})")),
+        }
+    }
+
+    fn check_dew_point(&mut self, data: &MonitoringData) {
+        let Some(deviation) = data.dew_point_deviation() else {
+            return;
+        };
+        let disagreement = deviation.abs() > self.app_settings.get_dew_point_tolerance();
+        if disagreement != self.sensor_disagreement {
+            self.sensor_disagreement = disagreement;
+            if disagreement {
+                self.event_log.push(
+                    data.timestamp,
+                    Severity::Warning,
+                    format!("Dew point sensors disagree by {deviation:.1} C"),
+                );
+            } else {
+                self.event_log.push(
+                    data.timestamp,
+                    Severity::Info,
+                    "Dew point sensors agree again".to_owned(),
+                );
+            }
+        }
+    }
+
+    fn store_calibration(&mut self, calibration: Calibration) {
+        self.tec.set_calibration(calibration);
+        if let Err(e) = self
+            .app_settings
+            .set_calibration(&self.port_path, calibration)
+        {
+            self.error_text = Some(format!("Failed to save settings ({e})"));
+        }
+    }
+
+    pub fn set_calibration_offset(&mut self, quantity: CalibrationQuantity, offset: f32) {
+        let mut calibration = self.tec.calibration();
+        match quantity {
+            CalibrationQuantity::Voltage => calibration.voltage_offset = offset,
+            CalibrationQuantity::Current => calibration.current_offset = offset,
+        }
+        self.store_calibration(calibration);
+    }
+
+    pub fn reset_calibration(&mut self) {
+        self.tec.set_calibration(Calibration::default());
+        if let Err(e) = self.app_settings.reset_calibration(&self.port_path) {
+            self.error_text = Some(format!("Failed to save settings ({e})"));
+        }
+    }
+
+    /// Calibrates `quantity` against an externally measured `reading`.
+    ///
+    /// Returns whether the calibration was stored.
+    pub fn calibrate(&mut self, quantity: CalibrationQuantity, reading: f32) -> bool {
+        let mut calibration = self.tec.calibration();
+        let (raw, offset) = match quantity {
+            CalibrationQuantity::Voltage => {
+                (self.tec.tec_voltage_raw(), calibration.voltage_offset)
+            }
+            CalibrationQuantity::Current => {
+                (self.tec.tec_current_raw(), calibration.current_offset)
+            }
+        };
+        let raw = match raw {
+            Ok(raw) => raw,
+            Err(err) => {
+                self.error_text = Some(format!("Failed to get data from coooler ({err})"));
+                return false;
+            }
+        };
+        let Some(factor) = Calibration::factor_for(raw, reading, offset) else {
+            self.error_text = Some(format!(
+                "Can not calibrate {quantity} with a reading of {reading} {} (raw value {raw})",
+                quantity.unit()
+            ));
+            return false;
+        };
+        match quantity {
+            CalibrationQuantity::Voltage => calibration.voltage_factor = factor,
+            CalibrationQuantity::Current => calibration.current_factor = factor,
+        }
+        self.store_calibration(calibration);
+        self.event_log.push(
+            Utc::now(),
+            Severity::Info,
+            format!("Calibrated {quantity} (factor {factor:.4})"),
+        );
+        true
+    }
+
+    /// Starts the self-test with the active setpoint.
+    ///
+    /// The current rise is measured from the idle TEC, so the TEC has to be disabled. It stays
+    /// disabled after the test.
+    pub fn start_self_test(&mut self) {
+        if !self.tec_status.contains(TecStatus::LOW_POWER_MODE_ACTIVE) {
+            self.error_text = Some("Disable the TEC before starting the self-test".to_owned());
+            return;
+        }
+        self.capture_setpoint();
+        self.self_test = Some(SelfTest::new(SelfTestConfig {
+            p: self.app_settings.get_p_coef(),
+            i: self.app_settings.get_i_coef(),
+            d: self.app_settings.get_d_coef(),
+            power_level: self.app_settings.get_max_power(),
+            setpoint: self.device_offset(),
+            ..Default::default()
+        }));
+        self.sent_offset = None;
+        self.event_log
+            .push(Utc::now(), Severity::Info, "Self-test started".to_owned());
+    }
+
+    pub fn abort_self_test(&mut self) {
+        if let Some(self_test) = &mut self.self_test {
+            self_test.abort(&mut self.tec);
+            self.finish_self_test();
+        }
+    }
+
+    /// Discards the report of a finished self-test.
+    pub fn close_self_test(&mut self) {
+        if self.self_test.as_ref().is_some_and(SelfTest::is_finished) {
+            self.self_test = None;
+        }
+    }
+
+    fn finish_self_test(&mut self) {
+        let Some(self_test) = &self.self_test else {
+            return;
+        };
+        let report = self_test.report();
+        let (severity, text) = match (report.finished, report.passed()) {
+            (None, _) => (Severity::Warning, "Self-test aborted"),
+            (Some(_), true) => (Severity::Info, "Self-test passed"),
+            (Some(_), false) => (Severity::Fault, "Self-test failed"),
+        };
+        self.event_log.push(Utc::now(), severity, text.to_owned());
+    }
+
+    pub fn save_self_test_report(&mut self) {
+        let Some(self_test) = &self.self_test else {
+            return;
+        };
+        let report = self_test.report();
+        let path = self.app_settings.get_config_dir_path().join(format!(
+            "self_test_{}.txt",
+            report.started.format("%Y_%m_%d_%H_%M_%S")
+        ));
+        match std::fs::write(
+            &path,
+            format!("Port: {}\n{report}\n", self.port_path.display()),
+        ) {
+            Ok(()) => self.event_log.push(
+                Utc::now(),
+                Severity::Info,
+                format!("Saved self-test report to {}", path.display()),
+            ),
+            Err(e) => self.error_text = Some(format!("Failed to save self-test report ({e})")),
+        }
+    }
+
+    fn handle_ocp_event(&mut self, event: OcpEvent) {
+        let now = Utc::now();
+        match event {
+            OcpEvent::Spurious { peak_current } => self.event_log.push(
+                now,
+                Severity::Warning,
+                format!("Ignored OCP flag (peak current {peak_current:.2} A)"),
+            ),
+            OcpEvent::SustainedCleared => {
+                self.event_log
+                    .push(now, Severity::Info, "Over current cleared".to_owned())
+            }
+            OcpEvent::SustainedStarted { peak_current } => {
+                self.event_log.push(
+                    now,
+                    Severity::Fault,
+                    format!("Sustained over current ({peak_current:.2} A)"),
+                );
+                match self.app_settings.get_ocp_response() {
+                    OcpResponse::Warn => {
+                        self.error_text =
+                            Some(format!("Sustained over current ({peak_current:.2} A)"));
+                    }
+                    OcpResponse::ReduceSetpoint => {
+                        let step = self.app_settings.get_ocp_setpoint_step();
+                        self.offset_backoff += step;
+                        let _ = self.sync_offset();
+                        self.event_log.push(
+                            now,
+                            Severity::Info,
+                            format!("Raised offset by {step:.1} C"),
+                        );
+                    }
+                    OcpResponse::Disable => {
+                        let _ = self.disable_tec();
+                    }
+                }
+            }
+        }
+    }
+
+    fn handle_watchdog_events(&mut self, events: &[WatchdogEvent]) {
+        for event in events {
+            self.event_log.push_watchdog_event(event);
+        }
+        let recovered = events.iter().any(WatchdogEvent::is_recovery)
+            && !self.watchdog.is_heartbeat_missed()
+            && !self.watchdog.is_failsafe_active();
+        if !recovered {
+            return;
+        }
+
+        match self.app_settings.get_recovery_action() {
+            RecoveryAction::ReEnable => {
+                if self.enabled_before_fault {
+                    let _ = self.enable_tec();
+                }
+            }
+            RecoveryAction::StayDisabled => {
+                let _ = self.disable_tec();
+            }
+            RecoveryAction::AskUser => {
+                self.recovery_prompt = self.enabled_before_fault;
+            }
+        }
+    }
+
+    /// Runs the periodic communication with the cooler.
+    ///
+    /// Returns the new measurements if the cooler was monitored.
+    pub fn tick(&mut self, now: Instant) -> Option<MonitoringData> {
+        if let Some(event) = self.watchdog.check(now) {
+            self.handle_watchdog_events(&[event]);
+        }
+        self.apply_pending_edits(now);
+        if !self.watchdog.heartbeat_due(now) {
+            return None;
+        }
+        self.watchdog.record_attempt(now);
+
+        match self.tec.status_word() {
+            Ok(word) => {
+                let status = word.status();
+                if !self.watchdog.is_heartbeat_missed()
+                    && !status.contains(TecStatus::FAILSAFE_ACTIVE)
+                {
+                    self.enabled_before_fault = !status.contains(TecStatus::LOW_POWER_MODE_ACTIVE);
+                }
+                // Disabled here, by another program or by the failsafe.
+                if status.intersects(TecStatus::LOW_POWER_MODE_ACTIVE | TecStatus::FAILSAFE_ACTIVE)
+                {
+                    self.sent_offset = None;
+                }
+                let status_events = self.status_word.diff(word, Utc::now());
+                self.event_log.push_status_events(&status_events);
+                self.status_word = word;
+                self.tec_status = status;
+                let events = self.watchdog.record_heartbeat(status, now);
+                self.handle_watchdog_events(&events);
+            }
+            Err(err) => {
+                if let Err(e) = self.tec.reset_connection() {
+                    self.error_text = Some(format!("Failed to communicate with coooler ({e:?})"));
+                    return None;
+                } else {
+                    self.error_text = Some(format!("Failed to communicate with coooler ({err:?})"))
+                }
+            }
+        }
+        if let Some(self_test) = self.self_test.as_mut().filter(|t| !t.is_finished()) {
+            let result = self_test.advance(&mut self.tec, now);
+            if self_test.is_finished() {
+                self.sent_offset = None;
+                self.finish_self_test();
+            }
+            if let Err(err) = result {
+                self.error_text = Some(format!("Self-test failed ({err})"));
+            }
+            return None;
+        }
+        match self.tec.monitor() {
+            Ok(data) => {
+                if let Some(event) = self.ocp_filter.update(
+                    self.tec_status.contains(TecStatus::OCP_ACTIVE),
+                    data.tec_current,
+                    now,
+                ) {
+                    self.handle_ocp_event(event);
+                }
+                self.check_dew_point(&data);
+                self.last_data = Some(data.clone());
+                let _ = self.sync_offset();
+                self.refresh_device_parameters(now);
+                Some(data)
+            }
+            Err(err) => {
+                self.error_text = Some(format!("Failed to get data from coooler ({err})"));
+                None
+            }
+        }
+    }
+}
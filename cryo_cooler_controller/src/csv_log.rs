@@ -0,0 +1,198 @@
+//! Continuous logging of the samples to one CSV file per day.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Days, NaiveDate};
+use cryo_cooler_controller_lib::{MonitoringData, StatusWord};
+
+pub const HEADER: &str = "timestamp,tec_temperature,board_temperature,humidity,dew_point,voltage,current,power_level,status_word";
+const FILE_PREFIX: &str = "cryo_cooler_";
+
+pub struct CsvLogger {
+    dir: PathBuf,
+    retention_days: u32,
+    /// Date of the open file, the file is rotated on the first sample of a new day.
+    file: Option<(NaiveDate, BufWriter<File>)>,
+}
+
+/// Path of the log file for `date`, dates are in UTC like the timestamps.
+pub fn file_path(dir: &Path, date: NaiveDate) -> PathBuf {
+    dir.join(format!("{FILE_PREFIX}{}.csv", date.format("%Y-%m-%d")))
+}
+
+fn file_date(path: &Path) -> Option<NaiveDate> {
+    let name = path.file_name()?.to_str()?;
+    let date = name.strip_prefix(FILE_PREFIX)?.strip_suffix(".csv")?;
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+/// One line without the line break.
+pub fn format_line(data: &MonitoringData, status_word: StatusWord) -> String {
+    format!(
+        "{},{},{},{},{},{},{},{},0x{:08X}",
+        data.timestamp.to_rfc3339(),
+        data.tec_temperature,
+        data.pcb_temperature,
+        data.humidity,
+        data.dew_point_temperature,
+        data.tec_voltage,
+        data.tec_current,
+        data.tec_power_level,
+        status_word.raw()
+    )
+}
+
+/// Inverse of [`format_line`].
+pub fn parse_line(line: &str) -> Option<(MonitoringData, StatusWord)> {
+    let mut fields = line.trim_end().split(',');
+    let mut next = || fields.next();
+    let data = MonitoringData {
+        timestamp: DateTime::parse_from_rfc3339(next()?).ok()?.into(),
+        tec_temperature: next()?.parse().ok()?,
+        pcb_temperature: next()?.parse().ok()?,
+        humidity: next()?.parse().ok()?,
+        dew_point_temperature: next()?.parse().ok()?,
+        tec_voltage: next()?.parse().ok()?,
+        tec_current: next()?.parse().ok()?,
+        tec_power_level: next()?.parse().ok()?,
+    };
+    let status_word = u32::from_str_radix(next()?.strip_prefix("0x")?, 16).ok()?;
+    if next().is_some() {
+        return None;
+    }
+    Some((data, StatusWord::new(status_word)))
+}
+
+/// Reads a log file written by [`CsvLogger`].
+///
+/// An incomplete last line, e.g. after a power loss, is ignored.
+pub fn read(path: &Path) -> std::io::Result<Vec<(MonitoringData, StatusWord)>> {
+    let invalid = |text: String| std::io::Error::new(std::io::ErrorKind::InvalidData, text);
+    let mut lines = BufReader::new(File::open(path)?).lines();
+    if lines.next().transpose()?.as_deref() != Some(HEADER) {
+        return Err(invalid("not a cryo cooler log".to_owned()));
+    }
+    let mut samples = Vec::new();
+    let mut lines = lines.enumerate().peekable();
+    while let Some((index, line)) = lines.next() {
+        match parse_line(&line?) {
+            Some(sample) => samples.push(sample),
+            None if lines.peek().is_none() => {}
+            None => return Err(invalid(format!("invalid line {}", index + 2))),
+        }
+    }
+    Ok(samples)
+}
+
+impl CsvLogger {
+    /// Creates `dir` if it does not exist, no file is opened before the first sample.
+    pub fn new(dir: PathBuf, retention_days: u32) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            retention_days,
+            file: None,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Appends a sample, opening the file of the day and deleting expired files as needed.
+    pub fn log(&mut self, data: &MonitoringData, status_word: StatusWord) -> std::io::Result<()> {
+        let date = data.timestamp.date_naive();
+        let writer = match &mut self.file {
+            Some((file_date, writer)) if *file_date == date => writer,
+            _ => {
+                self.file = None;
+                self.delete_expired(date)?;
+                let path = file_path(&self.dir, date);
+                let file = OpenOptions::new().create(true).append(true).open(path)?;
+                let mut writer = BufWriter::new(file);
+                if writer.get_ref().metadata()?.len() == 0 {
+                    writeln!(writer, "{HEADER}")?;
+                }
+                &mut self.file.insert((date, writer)).1
+            }
+        };
+        writeln!(writer, "{}", format_line(data, status_word))?;
+        // Keep the file complete if the program is killed.
+        writer.flush()
+    }
+
+    fn delete_expired(&self, today: NaiveDate) -> std::io::Result<()> {
+        if self.retention_days == 0 {
+            return Ok(());
+        }
+        let Some(oldest) = today.checked_sub_days(Days::new(u64::from(self.retention_days))) else {
+            return Ok(());
+        };
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if file_date(&path).is_some_and(|date| date < oldest) {
+                std::fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn sample(day: u32, hour: u32) -> MonitoringData {
+        MonitoringData {
+            timestamp: Utc.with_ymd_and_hms(2024, 3, day, hour, 0, 0).unwrap(),
+            tec_temperature: 12.5,
+            pcb_temperature: 30.0,
+            humidity: 45.0,
+            dew_point_temperature: 9.0,
+            tec_voltage: 12.1,
+            tec_current: 3.2,
+            tec_power_level: 40,
+        }
+    }
+
+    #[test]
+    fn rotation() {
+        let dir = tempdir::TempDir::new("csv_log").unwrap();
+        let date = |day| NaiveDate::from_ymd_opt(2024, 3, day).unwrap();
+        std::fs::write(file_path(dir.path(), date(1)), HEADER).unwrap();
+        std::fs::write(file_path(dir.path(), date(8)), HEADER).unwrap();
+        std::fs::write(dir.path().join("notes.csv"), "keep").unwrap();
+
+        let mut logger = CsvLogger::new(dir.path().to_path_buf(), 7).unwrap();
+        logger
+            .log(&sample(10, 23), StatusWord::new(0x1A01F))
+            .unwrap();
+        // A new logger appends to the file of the day without a second header.
+        let mut logger = CsvLogger::new(dir.path().to_path_buf(), 7).unwrap();
+        logger
+            .log(&sample(10, 23), StatusWord::new(0x1A01F))
+            .unwrap();
+        logger.log(&sample(11, 0), StatusWord::new(0)).unwrap();
+
+        assert!(!file_path(dir.path(), date(1)).exists());
+        assert!(file_path(dir.path(), date(8)).exists());
+        assert!(dir.path().join("notes.csv").exists());
+        let day = std::fs::read_to_string(file_path(dir.path(), date(10))).unwrap();
+        let line = "2024-03-10T23:00:00+00:00,12.5,30,45,9,12.1,3.2,40,0x0001A01F";
+        assert_eq!(day, format!("{HEADER}\n{line}\n{line}\n"));
+        let next_day = std::fs::read_to_string(file_path(dir.path(), date(11))).unwrap();
+        assert_eq!(next_day.lines().count(), 2);
+
+        let samples = read(&file_path(dir.path(), date(10))).unwrap();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(format_line(&samples[0].0, samples[0].1), line);
+        assert_eq!(
+            read(&dir.path().join("notes.csv")).unwrap_err().kind(),
+            std::io::ErrorKind::InvalidData
+        );
+    }
+}
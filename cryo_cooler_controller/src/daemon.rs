@@ -0,0 +1,81 @@
+//! Headless mode that regulates the cooler without the GUI, e.g. as a systemd service.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use cryo_cooler_controller_lib::Severity;
+
+use crate::controller::Controller;
+use crate::event_log::write_journal;
+use crate::services::Services;
+use crate::settings::AppSettings;
+
+/// Time between two calls of [`Controller::tick`].
+const TICK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Runs until SIGTERM or SIGINT is received and returns the exit code of the process.
+pub fn run(app_settings: AppSettings) -> i32 {
+    let terminate = Arc::new(AtomicBool::new(false));
+    for signal in [signal_hook::consts::SIGTERM, signal_hook::consts::SIGINT] {
+        if let Err(e) = signal_hook::flag::register(signal, Arc::clone(&terminate)) {
+            write_journal(
+                Severity::Fault,
+                &format!("Failed to register signal handler ({e})"),
+            );
+            return 1;
+        }
+    }
+
+    let Some(port) = app_settings.get_last_port_ident().clone() else {
+        write_journal(
+            Severity::Fault,
+            "No serial port configured, connect once using the GUI",
+        );
+        return 1;
+    };
+    let mut controller = match Controller::new(&port, app_settings) {
+        Ok(controller) => controller,
+        Err(e) => {
+            write_journal(
+                Severity::Fault,
+                &format!("Failed to connect to {} ({e})", port.display()),
+            );
+            return 1;
+        }
+    };
+    controller.event_log_mut().set_echo(true);
+    write_journal(Severity::Info, &format!("Connected to {}", port.display()));
+
+    let mut services = Services::default();
+    services.sync(&mut controller);
+
+    while !terminate.load(Ordering::Relaxed) {
+        services.poll(&mut controller);
+        if let Some(error) = controller.take_error() {
+            write_journal(Severity::Fault, &error);
+        }
+        // Nobody can answer the question after a fault, so the TEC stays disabled.
+        if controller.recovery_prompt() {
+            write_journal(
+                Severity::Warning,
+                "Recovered from a fault, TEC stays disabled",
+            );
+            controller.answer_recovery(false);
+        }
+        if let Some(data) = controller.tick(Instant::now()) {
+            services.publish(&mut controller, &data);
+        }
+        std::thread::sleep(TICK_INTERVAL);
+    }
+
+    write_journal(Severity::Info, "Shutting down");
+    if controller.sent_offset().is_some() {
+        if let Err(error) = controller.disable() {
+            write_journal(Severity::Fault, &error);
+            return 1;
+        }
+        write_journal(Severity::Info, "TEC disabled");
+    }
+    0
+}
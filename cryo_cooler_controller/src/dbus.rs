@@ -0,0 +1,332 @@
+//! Session D-Bus service for desktop integration.
+//!
+//! The service `io.github.juvgrfunex.CryoCooler` exports the object `/io/github/juvgrfunex/CryoCooler`
+//! with the measurements, the enabled state and the status flags as properties. Changes are
+//! announced with `PropertiesChanged`. The methods `Enable`, `Disable` and `SetOffset` control the
+//! cooler.
+
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use cryo_cooler_controller_lib::MonitoringData;
+use serde_json::{json, Value};
+use zbus::blocking::object_server::InterfaceRef;
+use zbus::blocking::{connection, Connection};
+use zbus::fdo;
+
+use crate::api::{self, ApiError};
+use crate::controller::Controller;
+
+pub const SERVICE_NAME: &str = "io.github.juvgrfunex.CryoCooler";
+const OBJECT_PATH: &str = "/io/github/juvgrfunex/CryoCooler";
+
+/// Longest time a method call waits for the owner of the controller.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Properties {
+    tec_temperature: f64,
+    board_temperature: f64,
+    humidity: f64,
+    dew_point: f64,
+    voltage: f64,
+    current: f64,
+    power_level: u8,
+    enabled: bool,
+    status_flags: Vec<String>,
+}
+
+impl Properties {
+    fn new(controller: &Controller, data: &MonitoringData) -> Self {
+        let status = api::status_json(controller);
+        Self {
+            tec_temperature: f64::from(data.tec_temperature),
+            board_temperature: f64::from(data.pcb_temperature),
+            humidity: f64::from(data.humidity),
+            dew_point: f64::from(data.dew_point_temperature),
+            voltage: f64::from(data.tec_voltage),
+            current: f64::from(data.tec_current),
+            power_level: data.tec_power_level,
+            enabled: status["enabled"].as_bool().unwrap_or_default(),
+            status_flags: controller
+                .tec_status()
+                .iter_names()
+                .map(|(name, _)| name.to_owned())
+                .collect(),
+        }
+    }
+}
+
+struct Request {
+    method: &'static str,
+    params: Value,
+    reply: Sender<Result<(), ApiError>>,
+}
+
+struct Cooler {
+    properties: Arc<Mutex<Properties>>,
+    requests: Sender<Request>,
+}
+
+impl Cooler {
+    fn properties(&self) -> MutexGuard<'_, Properties> {
+        self.properties
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Forwards the call to the owner of the controller and waits for the result.
+    fn call(&self, method: &'static str, params: Value) -> fdo::Result<()> {
+        let (reply, result) = mpsc::channel();
+        self.requests
+            .send(Request {
+                method,
+                params,
+                reply,
+            })
+            .map_err(|_| fdo::Error::Failed("The controller stopped".to_owned()))?;
+        match result.recv_timeout(REPLY_TIMEOUT) {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(ApiError::InvalidParams(reason))) => Err(fdo::Error::InvalidArgs(reason)),
+            Ok(Err(error)) => Err(fdo::Error::Failed(error.to_string())),
+            Err(_) => Err(fdo::Error::Timeout(
+                "The controller did not respond".to_owned(),
+            )),
+        }
+    }
+}
+
+#[zbus::interface(name = "io.github.juvgrfunex.CryoCooler")]
+impl Cooler {
+    fn enable(&self) -> fdo::Result<()> {
+        self.call("enable", Value::Null)
+    }
+
+    fn disable(&self) -> fdo::Result<()> {
+        self.call("disable", Value::Null)
+    }
+
+    fn set_offset(&self, offset: f64) -> fdo::Result<()> {
+        self.call("set_offset", json!({ "offset": offset }))
+    }
+
+    #[zbus(property)]
+    fn tec_temperature(&self) -> f64 {
+        self.properties().tec_temperature
+    }
+
+    #[zbus(property)]
+    fn board_temperature(&self) -> f64 {
+        self.properties().board_temperature
+    }
+
+    #[zbus(property)]
+    fn humidity(&self) -> f64 {
+        self.properties().humidity
+    }
+
+    #[zbus(property)]
+    fn dew_point(&self) -> f64 {
+        self.properties().dew_point
+    }
+
+    #[zbus(property)]
+    fn voltage(&self) -> f64 {
+        self.properties().voltage
+    }
+
+    #[zbus(property)]
+    fn current(&self) -> f64 {
+        self.properties().current
+    }
+
+    #[zbus(property)]
+    fn power_level(&self) -> u8 {
+        self.properties().power_level
+    }
+
+    #[zbus(property)]
+    fn enabled(&self) -> bool {
+        self.properties().enabled
+    }
+
+    #[zbus(property)]
+    fn status_flags(&self) -> Vec<String> {
+        self.properties().status_flags.clone()
+    }
+}
+
+pub struct DbusService {
+    // Keeps the name registered until the service is dropped.
+    _connection: Connection,
+    interface: InterfaceRef<Cooler>,
+    properties: Arc<Mutex<Properties>>,
+    requests: Receiver<Request>,
+}
+
+impl DbusService {
+    /// Registers the service on the session bus.
+    pub fn start() -> zbus::Result<Self> {
+        Self::start_on(connection::Builder::session()?)
+    }
+
+    fn start_on(builder: connection::Builder<'_>) -> zbus::Result<Self> {
+        let properties = Arc::new(Mutex::new(Properties::default()));
+        let (sender, requests) = mpsc::channel();
+        let cooler = Cooler {
+            properties: Arc::clone(&properties),
+            requests: sender,
+        };
+        let connection = builder
+            .name(SERVICE_NAME)?
+            .serve_at(OBJECT_PATH, cooler)?
+            .build()?;
+        let interface = connection
+            .object_server()
+            .interface::<_, Cooler>(OBJECT_PATH)?;
+        Ok(Self {
+            _connection: connection,
+            interface,
+            properties,
+            requests,
+        })
+    }
+
+    /// Executes the received method calls.
+    ///
+    /// Has to be called periodically by the owner of the controller.
+    pub fn poll(&mut self, controller: &mut Controller) {
+        while let Ok(request) = self.requests.try_recv() {
+            let result = api::call(controller, request.method, request.params).map(|_| ());
+            let _ = request.reply.send(result);
+        }
+    }
+
+    /// Updates the properties and announces the changed ones.
+    pub fn publish(&mut self, controller: &Controller, data: &MonitoringData) {
+        self.set_properties(Properties::new(controller, data));
+    }
+
+    fn set_properties(&self, new: Properties) {
+        let old = std::mem::replace(
+            &mut *self
+                .properties
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            new.clone(),
+        );
+        let cooler = self.interface.get();
+        let emitter = self.interface.signal_emitter();
+        // Signals are best effort, a broken connection is noticed by the clients anyway.
+        let _ = zbus::block_on(async {
+            if old.tec_temperature != new.tec_temperature {
+                cooler.tec_temperature_changed(emitter).await?;
+            }
+            if old.board_temperature != new.board_temperature {
+                cooler.board_temperature_changed(emitter).await?;
+            }
+            if old.humidity != new.humidity {
+                cooler.humidity_changed(emitter).await?;
+            }
+            if old.dew_point != new.dew_point {
+                cooler.dew_point_changed(emitter).await?;
+            }
+            if old.voltage != new.voltage {
+                cooler.voltage_changed(emitter).await?;
+            }
+            if old.current != new.current {
+                cooler.current_changed(emitter).await?;
+            }
+            if old.power_level != new.power_level {
+                cooler.power_level_changed(emitter).await?;
+            }
+            if old.enabled != new.enabled {
+                cooler.enabled_changed(emitter).await?;
+            }
+            if old.status_flags != new.status_flags {
+                cooler.status_flags_changed(emitter).await?;
+            }
+            zbus::Result::Ok(())
+        });
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use cryo_cooler_controller_lib::TecStatus;
+    use std::io::{BufRead, BufReader, Write};
+    use std::process::{Command, Stdio};
+
+    #[zbus::proxy(
+        interface = "io.github.juvgrfunex.CryoCooler",
+        default_service = "io.github.juvgrfunex.CryoCooler",
+        default_path = "/io/github/juvgrfunex/CryoCooler"
+    )]
+    trait Cooler {
+        fn set_offset(&self, offset: f64) -> zbus::Result<()>;
+
+        #[zbus(property)]
+        fn tec_temperature(&self) -> zbus::Result<f64>;
+
+        #[zbus(property)]
+        fn status_flags(&self) -> zbus::Result<Vec<String>>;
+    }
+
+    /// Runs against a private bus, the test is skipped if `dbus-daemon` is not installed.
+    #[test]
+    fn private_bus() {
+        let Ok(mut daemon) = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .spawn()
+        else {
+            let _ = writeln!(
+                std::io::stderr(),
+                "Skipping private_bus, dbus-daemon is not installed"
+            );
+            return;
+        };
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+
+        let service =
+            DbusService::start_on(connection::Builder::address(address.trim()).unwrap()).unwrap();
+        // Named like in `Properties::new`.
+        let status_flags: Vec<String> = TecStatus::OCP_ACTIVE
+            .iter_names()
+            .map(|(name, _)| name.to_owned())
+            .collect();
+        service.set_properties(Properties {
+            tec_temperature: 12.5,
+            status_flags: status_flags.clone(),
+            ..Default::default()
+        });
+
+        let client = connection::Builder::address(address.trim())
+            .unwrap()
+            .build()
+            .unwrap();
+        let proxy = CoolerProxyBlocking::new(&client).unwrap();
+        assert_eq!(proxy.tec_temperature().unwrap(), 12.5);
+        assert_eq!(status_flags, ["OCP_ACTIVE"]);
+        assert_eq!(proxy.status_flags().unwrap(), status_flags);
+
+        let call = std::thread::spawn(move || proxy.set_offset(3.0));
+        let request = service.requests.recv_timeout(REPLY_TIMEOUT).unwrap();
+        assert_eq!(request.method, "set_offset");
+        assert_eq!(request.params, json!({ "offset": 3.0 }));
+        request
+            .reply
+            .send(Err(ApiError::Failed("Not connected".to_owned())))
+            .unwrap();
+        assert!(call.join().unwrap().is_err());
+
+        drop(service);
+        let _ = daemon.kill();
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::VecDeque;
+use std::io::Write;
+
+use chrono::{DateTime, Utc};
+use cryo_cooler_controller_lib::{Severity, StatusEvent, WatchdogEvent};
+use iced::{
+    widget::{scrollable, Column, Text},
+    Color, Element, Length,
+};
+
+use crate::Message;
+
+const MAX_ENTRIES: usize = 200;
+
+struct LogEntry {
+    timestamp: DateTime<Utc>,
+    severity: Severity,
+    text: String,
+}
+
+/// Writes `text` to stderr, prefixed with the syslog priority of `severity` for journald.
+pub fn write_journal(severity: Severity, text: &str) {
+    let priority = match severity {
+        Severity::Fault => 3,
+        Severity::Warning => 4,
+        Severity::Info => 6,
+    };
+    let _ = writeln!(std::io::stderr(), "<{priority}>{text}");
+}
+
+#[derive(Default)]
+pub struct EventLog {
+    entries: VecDeque<LogEntry>,
+    /// Also write new entries to stderr.
+    echo: bool,
+}
+
+impl EventLog {
+    /// Also writes every new entry with [`write_journal`].
+    pub fn set_echo(&mut self, echo: bool) {
+        self.echo = echo;
+    }
+
+    pub fn push(&mut self, timestamp: DateTime<Utc>, severity: Severity, text: String) {
+        if self.echo {
+            write_journal(severity, &text);
+        }
+        self.entries.push_front(LogEntry {
+            timestamp,
+            severity,
+            text,
+        });
+        self.entries.truncate(MAX_ENTRIES);
+    }
+
+    pub fn push_status_events(&mut self, events: &[StatusEvent]) {
+        for event in events {
+            self.push(event.timestamp, event.severity(), event.description());
+        }
+    }
+
+    pub fn push_watchdog_event(&mut self, event: &WatchdogEvent) {
+        //! Failsafe transitions are already logged as status events.
+        match event {
+            WatchdogEvent::HeartbeatMissed { since } => self.push(
+                Utc::now(),
+                Severity::Fault,
+                format!("No heartbeat for {:.1} s", since.as_secs_f32()),
+            ),
+            WatchdogEvent::HeartbeatRestored { outage } => self.push(
+                Utc::now(),
+                Severity::Info,
+                format!("Heartbeat restored after {:.1} s", outage.as_secs_f32()),
+            ),
+            WatchdogEvent::FailsafeActivated | WatchdogEvent::FailsafeCleared => {}
+        }
+    }
+
+    /// Entries in a scrollable list of the given `height`.
+    pub fn view(&self, height: Length) -> Element<'_, Message> {
+        let mut col = Column::new().spacing(2).width(Length::Fill);
+        for entry in &self.entries {
+            let color = match entry.severity {
+                Severity::Info => Color::WHITE,
+                Severity::Warning => Color::from_rgb(0xE8 as f32 / 255.0, 0xA3 as f32 / 255.0, 0.0),
+                Severity::Fault => Color::from_rgb(
+                    0xC3 as f32 / 255.0,
+                    0x42 as f32 / 255.0,
+                    0x3F as f32 / 255.0,
+                ),
+            };
+            col = col.push(
+                Text::new(format!(
+                    "{}  {}",
+                    entry.timestamp.format("%H:%M:%S"),
+                    entry.text
+                ))
+                .size(14)
+                .style(color),
+            );
+        }
+
+        Column::new()
+            .spacing(5)
+            .width(Length::Fill)
+            .height(height)
+            .push(Text::new("Event Log"))
+            .push(scrollable(col).height(Length::Fill))
+            .into()
+    }
+}
@@ -0,0 +1,685 @@
+//! SQLite database with the samples, status transitions and user actions.
+//!
+//! A background thread aggregates the samples to one minute and the minutes to one hour buckets
+//! with minimum, average and maximum. Old data is only kept in the coarser resolutions, so long
+//! time spans can be shown without loading every sample.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, TimeZone, Utc};
+use cryo_cooler_controller_lib::{FlagChange, MonitoringData, StatusEvent, StatusWord, TecStatus};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+
+use crate::controller::UserAction;
+
+const MINUTE_MS: i64 = 60_000;
+const HOUR_MS: i64 = 3_600_000;
+const DOWNSAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+/// Assumed time between two samples when choosing the resolution of a query.
+const SAMPLE_INTERVAL_MS: i64 = 1000;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS samples (
+    timestamp INTEGER NOT NULL,
+    tec_temperature REAL NOT NULL,
+    board_temperature REAL NOT NULL,
+    humidity REAL NOT NULL,
+    dew_point REAL NOT NULL,
+    voltage REAL NOT NULL,
+    current REAL NOT NULL,
+    power_level INTEGER NOT NULL,
+    status_word INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS samples_timestamp ON samples (timestamp);
+CREATE TABLE IF NOT EXISTS aggregates (
+    resolution INTEGER NOT NULL,
+    quantity TEXT NOT NULL,
+    bucket INTEGER NOT NULL,
+    min REAL NOT NULL,
+    avg REAL NOT NULL,
+    max REAL NOT NULL,
+    count INTEGER NOT NULL,
+    PRIMARY KEY (resolution, quantity, bucket)
+);
+CREATE TABLE IF NOT EXISTS status_transitions (
+    timestamp INTEGER NOT NULL,
+    flag INTEGER NOT NULL,
+    raised INTEGER NOT NULL,
+    description TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS actions (
+    timestamp INTEGER NOT NULL,
+    description TEXT NOT NULL
+);
+-- Buckets before done_until are aggregated.
+CREATE TABLE IF NOT EXISTS progress (
+    resolution INTEGER PRIMARY KEY,
+    done_until INTEGER NOT NULL
+);
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantity {
+    TecTemperature,
+    BoardTemperature,
+    Humidity,
+    DewPoint,
+    Voltage,
+    Current,
+    PowerLevel,
+}
+
+impl Quantity {
+    pub const ALL: &[Quantity] = &[
+        Quantity::TecTemperature,
+        Quantity::BoardTemperature,
+        Quantity::Humidity,
+        Quantity::DewPoint,
+        Quantity::Voltage,
+        Quantity::Current,
+        Quantity::PowerLevel,
+    ];
+
+    /// Name of the database and CSV column.
+    pub fn column(self) -> &'static str {
+        match self {
+            Quantity::TecTemperature => "tec_temperature",
+            Quantity::BoardTemperature => "board_temperature",
+            Quantity::Humidity => "humidity",
+            Quantity::DewPoint => "dew_point",
+            Quantity::Voltage => "voltage",
+            Quantity::Current => "current",
+            Quantity::PowerLevel => "power_level",
+        }
+    }
+
+    pub fn value(self, data: &MonitoringData) -> f32 {
+        match self {
+            Quantity::TecTemperature => data.tec_temperature,
+            Quantity::BoardTemperature => data.pcb_temperature,
+            Quantity::Humidity => data.humidity,
+            Quantity::DewPoint => data.dew_point_temperature,
+            Quantity::Voltage => data.tec_voltage,
+            Quantity::Current => data.tec_current,
+            Quantity::PowerLevel => f32::from(data.tec_power_level),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Raw,
+    Minute,
+    Hour,
+}
+
+impl Resolution {
+    fn bucket_ms(self) -> i64 {
+        match self {
+            Resolution::Raw => 0,
+            Resolution::Minute => MINUTE_MS,
+            Resolution::Hour => HOUR_MS,
+        }
+    }
+
+    fn finer(self) -> Option<Resolution> {
+        match self {
+            Resolution::Raw => None,
+            Resolution::Minute => Some(Resolution::Raw),
+            Resolution::Hour => Some(Resolution::Minute),
+        }
+    }
+}
+
+/// How long each resolution is kept.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Retention {
+    pub raw: Duration,
+    pub minute: Duration,
+    /// `None` keeps the hourly aggregates, status transitions and actions forever.
+    pub hour: Option<Duration>,
+}
+
+/// Value of a quantity over one bucket, or a single sample with minimum and maximum equal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HistoryPoint {
+    pub timestamp: DateTime<Utc>,
+    pub min: f32,
+    pub avg: f32,
+    pub max: f32,
+}
+
+fn duration_ms(duration: Duration) -> i64 {
+    i64::try_from(duration.as_millis()).unwrap_or(i64::MAX)
+}
+
+fn open_connection(path: &Path) -> rusqlite::Result<Connection> {
+    let connection = Connection::open(path)?;
+    connection.busy_timeout(Duration::from_secs(5))?;
+    connection.pragma_update(None, "journal_mode", "WAL")?;
+    Ok(connection)
+}
+
+fn done_until(connection: &Connection, resolution: i64) -> rusqlite::Result<i64> {
+    Ok(connection
+        .query_row(
+            "SELECT done_until FROM progress WHERE resolution = ?1",
+            [resolution],
+            |row| row.get(0),
+        )
+        .optional()?
+        .unwrap_or(0))
+}
+
+/// Aggregates the completed buckets and deletes expired data.
+fn downsample(connection: &Connection, now_ms: i64, retention: &Retention) -> rusqlite::Result<()> {
+    let transaction = connection.unchecked_transaction()?;
+
+    let from = done_until(&transaction, MINUTE_MS)?;
+    let until = now_ms / MINUTE_MS * MINUTE_MS;
+    if until > from {
+        for quantity in Quantity::ALL {
+            transaction.execute(
+                &format!(
+                    "INSERT OR REPLACE INTO aggregates (resolution, quantity, bucket, min, avg, max, count)
+                     SELECT ?1, ?2, timestamp / ?1 * ?1 AS minute, MIN({column}), AVG({column}), MAX({column}), COUNT(*)
+                     FROM samples WHERE timestamp >= ?3 AND timestamp < ?4 GROUP BY minute",
+                    column = quantity.column()
+                ),
+                params![MINUTE_MS, quantity.column(), from, until],
+            )?;
+        }
+        transaction.execute(
+            "INSERT OR REPLACE INTO progress (resolution, done_until) VALUES (?1, ?2)",
+            params![MINUTE_MS, until],
+        )?;
+    }
+
+    let from = done_until(&transaction, HOUR_MS)?;
+    let until = now_ms / HOUR_MS * HOUR_MS;
+    if until > from {
+        transaction.execute(
+            "INSERT OR REPLACE INTO aggregates (resolution, quantity, bucket, min, avg, max, count)
+             SELECT ?1, quantity, bucket / ?1 * ?1 AS hour, MIN(min), SUM(avg * count) / SUM(count), MAX(max), SUM(count)
+             FROM aggregates WHERE resolution = ?2 AND bucket >= ?3 AND bucket < ?4 GROUP BY quantity, hour",
+            params![HOUR_MS, MINUTE_MS, from, until],
+        )?;
+        transaction.execute(
+            "INSERT OR REPLACE INTO progress (resolution, done_until) VALUES (?1, ?2)",
+            params![HOUR_MS, until],
+        )?;
+    }
+
+    // Data is only deleted once it is part of the next coarser resolution.
+    let raw_oldest =
+        (now_ms - duration_ms(retention.raw)).min(done_until(&transaction, MINUTE_MS)?);
+    transaction.execute("DELETE FROM samples WHERE timestamp < ?1", [raw_oldest])?;
+    let minute_oldest =
+        (now_ms - duration_ms(retention.minute)).min(done_until(&transaction, HOUR_MS)?);
+    transaction.execute(
+        "DELETE FROM aggregates WHERE resolution = ?1 AND bucket < ?2",
+        params![MINUTE_MS, minute_oldest],
+    )?;
+    if let Some(hour) = retention.hour {
+        let oldest = now_ms - duration_ms(hour);
+        transaction.execute(
+            "DELETE FROM aggregates WHERE resolution = ?1 AND bucket < ?2",
+            params![HOUR_MS, oldest],
+        )?;
+        transaction.execute(
+            "DELETE FROM status_transitions WHERE timestamp < ?1",
+            [oldest],
+        )?;
+        transaction.execute("DELETE FROM actions WHERE timestamp < ?1", [oldest])?;
+    }
+    transaction.commit()
+}
+
+pub struct HistoryDb {
+    connection: Connection,
+    retention: Retention,
+    last_status_word: Option<StatusWord>,
+    /// Errors of the background downsampling.
+    errors: Receiver<String>,
+    stop: Arc<AtomicBool>,
+}
+
+impl HistoryDb {
+    /// Opens or creates the database and starts the background downsampling.
+    pub fn open(path: &Path, retention: Retention) -> std::io::Result<Self> {
+        let (db, sender) = Self::connect(path, retention.clone())?;
+        let stop = Arc::clone(&db.stop);
+        let path = path.to_path_buf();
+        std::thread::spawn(move || {
+            let connection = match open_connection(&path) {
+                Ok(connection) => connection,
+                Err(e) => {
+                    let _ = sender.send(e.to_string());
+                    return;
+                }
+            };
+            while !stop.load(Ordering::Relaxed) {
+                if let Err(e) = downsample(&connection, Utc::now().timestamp_millis(), &retention) {
+                    let _ = sender.send(e.to_string());
+                }
+                let start = Instant::now();
+                while start.elapsed() < DOWNSAMPLE_INTERVAL && !stop.load(Ordering::Relaxed) {
+                    std::thread::sleep(Duration::from_millis(200));
+                }
+            }
+        });
+        Ok(db)
+    }
+
+    /// Opens a database recorded elsewhere without changing it, e.g. a copy from another machine.
+    ///
+    /// Queries use every resolution that still contains data.
+    pub fn open_read_only(path: &Path) -> std::io::Result<Self> {
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(std::io::Error::other)?;
+        let oldest = |sql: &str| -> std::io::Result<Duration> {
+            let oldest: Option<i64> = connection
+                .query_row(sql, [], |row| row.get(0))
+                .map_err(std::io::Error::other)?;
+            let age = Utc::now().timestamp_millis() - oldest.unwrap_or(i64::MAX);
+            Ok(Duration::from_millis(u64::try_from(age).unwrap_or(0)))
+        };
+        let retention = Retention {
+            raw: oldest("SELECT MIN(timestamp) FROM samples")?,
+            minute: oldest(&format!(
+                "SELECT MIN(bucket) FROM aggregates WHERE resolution = {MINUTE_MS}"
+            ))?,
+            hour: None,
+        };
+        Ok(Self::from_connection(connection, retention).0)
+    }
+
+    fn connect(path: &Path, retention: Retention) -> std::io::Result<(Self, mpsc::Sender<String>)> {
+        let connection = open_connection(path).map_err(std::io::Error::other)?;
+        connection
+            .execute_batch(SCHEMA)
+            .map_err(std::io::Error::other)?;
+        Ok(Self::from_connection(connection, retention))
+    }
+
+    fn from_connection(
+        connection: Connection,
+        retention: Retention,
+    ) -> (Self, mpsc::Sender<String>) {
+        let (sender, errors) = mpsc::channel();
+        (
+            Self {
+                connection,
+                retention,
+                last_status_word: None,
+                errors,
+                stop: Arc::new(AtomicBool::new(false)),
+            },
+            sender,
+        )
+    }
+
+    /// Time of the oldest and the newest stored value.
+    pub fn span(&self) -> std::io::Result<Option<(DateTime<Utc>, DateTime<Utc>)>> {
+        let (oldest, newest): (Option<i64>, Option<i64>) = self
+            .connection
+            .query_row(
+                "SELECT MIN(oldest), MAX(newest) FROM (
+                     SELECT MIN(timestamp) AS oldest, MAX(timestamp) AS newest FROM samples
+                     UNION ALL
+                     SELECT MIN(bucket), MAX(bucket + resolution - 1) FROM aggregates
+                 )",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(std::io::Error::other)?;
+        Ok(oldest.zip(newest).and_then(|(oldest, newest)| {
+            Some((
+                Utc.timestamp_millis_opt(oldest).single()?,
+                Utc.timestamp_millis_opt(newest).single()?,
+            ))
+        }))
+    }
+
+    /// All stored status flag changes, oldest first.
+    pub fn status_events(&self) -> std::io::Result<Vec<StatusEvent>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT timestamp, flag, raised FROM status_transitions ORDER BY timestamp")
+            .map_err(std::io::Error::other)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok(StatusEvent {
+                    timestamp: Utc
+                        .timestamp_millis_opt(row.get(0)?)
+                        .single()
+                        .unwrap_or_default(),
+                    flag: TecStatus::from_bits_retain(row.get(1)?),
+                    change: if row.get(2)? {
+                        FlagChange::Set
+                    } else {
+                        FlagChange::Cleared
+                    },
+                })
+            })
+            .map_err(std::io::Error::other)?;
+        rows.collect::<rusqlite::Result<_>>()
+            .map_err(std::io::Error::other)
+    }
+
+    /// All stored user actions, oldest first.
+    pub fn actions(&self) -> std::io::Result<Vec<UserAction>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT timestamp, description FROM actions ORDER BY timestamp")
+            .map_err(std::io::Error::other)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok(UserAction {
+                    timestamp: Utc
+                        .timestamp_millis_opt(row.get(0)?)
+                        .single()
+                        .unwrap_or_default(),
+                    description: row.get(1)?,
+                })
+            })
+            .map_err(std::io::Error::other)?;
+        rows.collect::<rusqlite::Result<_>>()
+            .map_err(std::io::Error::other)
+    }
+
+    /// Error of the background downsampling since the last call.
+    pub fn take_error(&self) -> Option<String> {
+        self.errors.try_recv().ok()
+    }
+
+    /// Stores a sample and the status flags that changed since the previous one.
+    pub fn record_sample(
+        &mut self,
+        data: &MonitoringData,
+        status_word: StatusWord,
+    ) -> std::io::Result<()> {
+        let timestamp = data.timestamp.timestamp_millis();
+        let transaction = self
+            .connection
+            .unchecked_transaction()
+            .map_err(std::io::Error::other)?;
+        transaction
+            .execute(
+                "INSERT INTO samples VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    timestamp,
+                    data.tec_temperature,
+                    data.pcb_temperature,
+                    data.humidity,
+                    data.dew_point_temperature,
+                    data.tec_voltage,
+                    data.tec_current,
+                    data.tec_power_level,
+                    status_word.raw(),
+                ],
+            )
+            .map_err(std::io::Error::other)?;
+        if let Some(last) = self.last_status_word {
+            for event in last.diff(status_word, data.timestamp) {
+                transaction
+                    .execute(
+                        "INSERT INTO status_transitions VALUES (?1, ?2, ?3, ?4)",
+                        params![
+                            timestamp,
+                            event.flag.bits(),
+                            event.change == FlagChange::Set,
+                            event.description(),
+                        ],
+                    )
+                    .map_err(std::io::Error::other)?;
+            }
+        }
+        transaction.commit().map_err(std::io::Error::other)?;
+        self.last_status_word = Some(status_word);
+        Ok(())
+    }
+
+    pub fn record_action(&self, action: &UserAction) -> std::io::Result<()> {
+        self.connection
+            .execute(
+                "INSERT INTO actions VALUES (?1, ?2)",
+                params![action.timestamp.timestamp_millis(), action.description],
+            )
+            .map(|_| ())
+            .map_err(std::io::Error::other)
+    }
+
+    /// Finest resolution that is still kept at `from` and has at most about `max_points` points.
+    fn resolution_for(&self, from: i64, to: i64, max_points: usize, now: i64) -> Resolution {
+        let span = to - from;
+        let max_points = i64::try_from(max_points).unwrap_or(i64::MAX);
+        if span / SAMPLE_INTERVAL_MS <= max_points && from >= now - duration_ms(self.retention.raw)
+        {
+            Resolution::Raw
+        } else if span / MINUTE_MS <= max_points && from >= now - duration_ms(self.retention.minute)
+        {
+            Resolution::Minute
+        } else {
+            Resolution::Hour
+        }
+    }
+
+    fn query_resolution(
+        &self,
+        resolution: Resolution,
+        quantity: Quantity,
+        from: i64,
+        to: i64,
+    ) -> rusqlite::Result<Vec<HistoryPoint>> {
+        let point = |timestamp: i64, min: f64, avg: f64, max: f64| HistoryPoint {
+            timestamp: Utc
+                .timestamp_millis_opt(timestamp)
+                .single()
+                .unwrap_or_default(),
+            min: min as f32,
+            avg: avg as f32,
+            max: max as f32,
+        };
+        if resolution == Resolution::Raw {
+            let mut statement = self.connection.prepare_cached(&format!(
+                "SELECT timestamp, {} FROM samples WHERE timestamp >= ?1 AND timestamp <= ?2 ORDER BY timestamp",
+                quantity.column()
+            ))?;
+            let rows = statement.query_map([from, to], |row| {
+                let value = row.get(1)?;
+                Ok(point(row.get(0)?, value, value, value))
+            })?;
+            return rows.collect();
+        }
+        let mut statement = self.connection.prepare_cached(
+            "SELECT bucket, min, avg, max FROM aggregates
+             WHERE resolution = ?1 AND quantity = ?2 AND bucket > ?3 - ?1 AND bucket <= ?4 ORDER BY bucket",
+        )?;
+        let rows = statement.query_map(
+            params![resolution.bucket_ms(), quantity.column(), from, to],
+            |row| Ok(point(row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+        rows.collect()
+    }
+
+    /// Values of `quantity` between `from` and `to` in the finest resolution with at most about
+    /// `max_points` points.
+    ///
+    /// The newest buckets that are not aggregated yet are filled in from the finer resolutions.
+    pub fn query(
+        &self,
+        quantity: Quantity,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        max_points: usize,
+    ) -> std::io::Result<Vec<HistoryPoint>> {
+        self.query_at(
+            quantity,
+            from.timestamp_millis(),
+            to.timestamp_millis(),
+            max_points,
+            Utc::now().timestamp_millis(),
+        )
+        .map_err(std::io::Error::other)
+    }
+
+    fn query_at(
+        &self,
+        quantity: Quantity,
+        from: i64,
+        to: i64,
+        max_points: usize,
+        now: i64,
+    ) -> rusqlite::Result<Vec<HistoryPoint>> {
+        let mut resolution = self.resolution_for(from, to, max_points, now);
+        let mut points = Vec::new();
+        let mut start = from;
+        loop {
+            let mut part = self.query_resolution(resolution, quantity, start, to)?;
+            if let Some(last) = part.last() {
+                start = last.timestamp.timestamp_millis() + resolution.bucket_ms().max(1);
+            }
+            points.append(&mut part);
+            match resolution.finer() {
+                Some(finer) if start <= to => resolution = finer,
+                _ => return Ok(points),
+            }
+        }
+    }
+}
+
+impl Drop for HistoryDb {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    const START_MS: i64 = 1_710_064_800_000; // 2024-03-10 10:00:00 UTC
+
+    fn retention() -> Retention {
+        Retention {
+            raw: Duration::from_secs(3600),
+            minute: Duration::from_secs(24 * 3600),
+            hour: None,
+        }
+    }
+
+    fn sample(timestamp: i64, tec_temperature: f32) -> MonitoringData {
+        MonitoringData {
+            timestamp: Utc.timestamp_millis_opt(timestamp).unwrap(),
+            tec_temperature,
+            pcb_temperature: 30.0,
+            humidity: 45.0,
+            dew_point_temperature: 9.0,
+            tec_voltage: 12.0,
+            tec_current: 3.0,
+            tec_power_level: 40,
+        }
+    }
+
+    fn count(db: &HistoryDb, sql: &str) -> i64 {
+        db.connection.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn downsampling() {
+        let dir = tempdir::TempDir::new("history").unwrap();
+        let (mut db, _) =
+            HistoryDb::connect(&dir.path().join("history.sqlite3"), retention()).unwrap();
+        // Two and a half hours with one sample every 30 s, alternating between 10 and 20 C.
+        for i in 0..300 {
+            let temperature = if i % 2 == 0 { 10.0 } else { 20.0 };
+            let word = StatusWord::new(if i == 10 { 1 << 13 } else { 0 });
+            db.record_sample(&sample(START_MS + i * 30_000, temperature), word)
+                .unwrap();
+        }
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM status_transitions"), 2);
+
+        let now = START_MS + 150 * MINUTE_MS + 10_000;
+        downsample(&db.connection, now, &retention()).unwrap();
+        assert_eq!(
+            count(&db, "SELECT COUNT(*) FROM aggregates WHERE resolution = 60000 AND quantity = 'tec_temperature'"),
+            150
+        );
+        assert_eq!(
+            count(&db, "SELECT COUNT(*) FROM aggregates WHERE resolution = 3600000 AND quantity = 'tec_temperature'"),
+            2
+        );
+        // Samples older than an hour are deleted.
+        assert_eq!(
+            count(&db, "SELECT MIN(timestamp) FROM samples"),
+            now - 3_600_000 + 20_000
+        );
+
+        let hour = db
+            .query_resolution(Resolution::Hour, Quantity::TecTemperature, START_MS, now)
+            .unwrap();
+        assert_eq!(
+            hour[0],
+            HistoryPoint {
+                timestamp: Utc.timestamp_millis_opt(START_MS).unwrap(),
+                min: 10.0,
+                avg: 15.0,
+                max: 20.0
+            }
+        );
+
+        // The hour and minute that are not aggregated yet come from the finer resolutions.
+        assert_eq!(db.resolution_for(START_MS, now, 100, now), Resolution::Hour);
+        let points = db
+            .query_at(Quantity::TecTemperature, START_MS, now, 100, now)
+            .unwrap();
+        assert_eq!(points.len(), 2 + 30);
+        assert_eq!(
+            points[2].timestamp,
+            Utc.timestamp_millis_opt(START_MS + 2 * HOUR_MS).unwrap()
+        );
+        assert_eq!(
+            db.resolution_for(now - 600_000, now, 1000, now),
+            Resolution::Raw
+        );
+        assert_eq!(
+            db.resolution_for(START_MS, now, 1000, now),
+            Resolution::Minute
+        );
+    }
+
+    #[test]
+    fn read_only() {
+        let dir = tempdir::TempDir::new("history").unwrap();
+        let path = dir.path().join("history.sqlite3");
+        let (mut db, _) = HistoryDb::connect(&path, retention()).unwrap();
+        for i in 0..3 {
+            let word = StatusWord::new(if i == 1 { 1 << 13 } else { 0 });
+            db.record_sample(&sample(START_MS + i * 1000, 10.0), word)
+                .unwrap();
+        }
+        let action = UserAction {
+            timestamp: Utc.timestamp_millis_opt(START_MS).unwrap(),
+            description: "TEC enabled".to_owned(),
+        };
+        db.record_action(&action).unwrap();
+
+        let db = HistoryDb::open_read_only(&path).unwrap();
+        let (from, to) = db.span().unwrap().unwrap();
+        assert_eq!(from.timestamp_millis(), START_MS);
+        assert_eq!(to.timestamp_millis(), START_MS + 2000);
+        assert_eq!(db.actions().unwrap(), vec![action]);
+        let events = db.status_events().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].change, FlagChange::Set);
+        assert_eq!(events[0].flag.bits(), 1 << 13);
+        let points = db.query(Quantity::TecTemperature, from, to, 100).unwrap();
+        assert_eq!(points.len(), 3);
+    }
+}
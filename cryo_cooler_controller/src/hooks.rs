@@ -0,0 +1,399 @@
+//! User commands run on events, configured in the `hooks` section of the settings.
+//!
+//! Commands run in the system shell with the event details in environment variables:
+//! `CRYO_EVENT`, `CRYO_FLAG`, `CRYO_THRESHOLD`, `CRYO_TIMESTAMP`, `CRYO_PORT`, `CRYO_STATUS` and,
+//! once the cooler was monitored, `CRYO_TEC_TEMPERATURE`, `CRYO_DEW_POINT`, `CRYO_HUMIDITY` and
+//! `CRYO_POWER_LEVEL`.
+
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use cryo_cooler_controller_lib::{MonitoringData, Severity, TecStatus};
+
+use crate::controller::Controller;
+use crate::settings::HookEvent;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hook {
+    pub event: HookEvent,
+    pub flag: Option<String>,
+    pub threshold: Option<f32>,
+    pub command: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HookConfig {
+    pub timeout: Duration,
+    pub condensation_margin: f32,
+    pub hooks: Vec<Hook>,
+}
+
+impl HookEvent {
+    pub fn name(self) -> &'static str {
+        match self {
+            HookEvent::TecEnabled => "TecEnabled",
+            HookEvent::TecDisabled => "TecDisabled",
+            HookEvent::StatusRaised => "StatusRaised",
+            HookEvent::StatusCleared => "StatusCleared",
+            HookEvent::ConnectionLost => "ConnectionLost",
+            HookEvent::ConnectionRestored => "ConnectionRestored",
+            HookEvent::CondensationMarginBreached => "CondensationMarginBreached",
+            HookEvent::TemperatureAbove => "TemperatureAbove",
+            HookEvent::TemperatureBelow => "TemperatureBelow",
+        }
+    }
+}
+
+/// Something that happened since the last check.
+#[derive(Debug, Clone, PartialEq)]
+enum Trigger {
+    Event(HookEvent),
+    Flag(HookEvent, &'static str),
+    Temperature { previous: f32, current: f32 },
+}
+
+impl Hook {
+    fn matches(&self, trigger: &Trigger) -> bool {
+        match *trigger {
+            Trigger::Event(event) => self.event == event,
+            Trigger::Flag(event, flag) => {
+                self.event == event && self.flag.as_deref().is_none_or(|name| name == flag)
+            }
+            Trigger::Temperature { previous, current } => match (self.event, self.threshold) {
+                (HookEvent::TemperatureAbove, Some(threshold)) => {
+                    previous <= threshold && current > threshold
+                }
+                (HookEvent::TemperatureBelow, Some(threshold)) => {
+                    previous >= threshold && current < threshold
+                }
+                _ => false,
+            },
+        }
+    }
+}
+
+/// Watches the controller and runs the configured hooks.
+pub struct HookRunner {
+    config: HookConfig,
+    last_status: Option<TecStatus>,
+    heartbeat_missed: bool,
+    last_sample: Option<DateTime<Utc>>,
+    last_temperature: Option<f32>,
+    margin_breached: bool,
+    /// Problems reported by the threads waiting for the commands.
+    results: Receiver<String>,
+    result_sender: Sender<String>,
+}
+
+impl Default for HookRunner {
+    fn default() -> Self {
+        let (result_sender, results) = mpsc::channel();
+        Self {
+            config: HookConfig {
+                timeout: Duration::ZERO,
+                condensation_margin: 0.0,
+                hooks: Vec::new(),
+            },
+            last_status: None,
+            heartbeat_missed: false,
+            last_sample: None,
+            last_temperature: None,
+            margin_breached: false,
+            results,
+            result_sender,
+        }
+    }
+}
+
+impl HookRunner {
+    pub fn set_config(&mut self, config: HookConfig) {
+        self.config = config;
+    }
+
+    /// Runs the hooks for the changes since the last call and logs failed hooks.
+    ///
+    /// Has to be called periodically by the owner of the controller.
+    pub fn check(&mut self, controller: &mut Controller) {
+        let triggers = self.detect(
+            controller.tec_status(),
+            controller.watchdog().is_heartbeat_missed(),
+            controller.last_data(),
+        );
+        for trigger in &triggers {
+            for hook in self
+                .config
+                .hooks
+                .iter()
+                .filter(|hook| hook.matches(trigger))
+            {
+                let env = environment(hook, trigger, controller);
+                match spawn(&hook.command, &env) {
+                    Ok(child) => {
+                        let command = hook.command.clone();
+                        let timeout = self.config.timeout;
+                        let results = self.result_sender.clone();
+                        std::thread::spawn(move || {
+                            if let Some(problem) = wait(child, timeout) {
+                                let _ = results.send(format!("Hook `{command}` {problem}"));
+                            }
+                        });
+                    }
+                    Err(e) => controller.event_log_mut().push(
+                        Utc::now(),
+                        Severity::Warning,
+                        format!("Failed to run hook `{}` ({e})", hook.command),
+                    ),
+                }
+            }
+        }
+        while let Ok(text) = self.results.try_recv() {
+            controller
+                .event_log_mut()
+                .push(Utc::now(), Severity::Warning, text);
+        }
+    }
+
+    fn detect(
+        &mut self,
+        status: TecStatus,
+        heartbeat_missed: bool,
+        data: Option<&MonitoringData>,
+    ) -> Vec<Trigger> {
+        let mut triggers = Vec::new();
+        if let Some(last_status) = self.last_status.replace(status) {
+            for (name, flag) in (last_status ^ status).iter_names() {
+                let raised = status.contains(flag);
+                if flag == TecStatus::LOW_POWER_MODE_ACTIVE {
+                    triggers.push(Trigger::Event(if raised {
+                        HookEvent::TecDisabled
+                    } else {
+                        HookEvent::TecEnabled
+                    }));
+                }
+                let event = if raised {
+                    HookEvent::StatusRaised
+                } else {
+                    HookEvent::StatusCleared
+                };
+                triggers.push(Trigger::Flag(event, name));
+            }
+        }
+
+        if heartbeat_missed != self.heartbeat_missed {
+            self.heartbeat_missed = heartbeat_missed;
+            triggers.push(Trigger::Event(if heartbeat_missed {
+                HookEvent::ConnectionLost
+            } else {
+                HookEvent::ConnectionRestored
+            }));
+        }
+
+        let Some(data) = data.filter(|data| self.last_sample != Some(data.timestamp)) else {
+            return triggers;
+        };
+        self.last_sample = Some(data.timestamp);
+        let breached =
+            data.tec_temperature < data.dew_point_temperature + self.config.condensation_margin;
+        if breached && !self.margin_breached {
+            triggers.push(Trigger::Event(HookEvent::CondensationMarginBreached));
+        }
+        self.margin_breached = breached;
+        if let Some(previous) = self.last_temperature.replace(data.tec_temperature) {
+            triggers.push(Trigger::Temperature {
+                previous,
+                current: data.tec_temperature,
+            });
+        }
+        triggers
+    }
+}
+
+fn environment(hook: &Hook, trigger: &Trigger, controller: &Controller) -> Vec<(String, String)> {
+    let mut env = vec![
+        ("CRYO_EVENT".to_owned(), hook.event.name().to_owned()),
+        ("CRYO_TIMESTAMP".to_owned(), Utc::now().to_rfc3339()),
+        (
+            "CRYO_PORT".to_owned(),
+            controller.port_path().display().to_string(),
+        ),
+        (
+            "CRYO_STATUS".to_owned(),
+            controller.tec_status().bits().to_string(),
+        ),
+    ];
+    if let Trigger::Flag(_, flag) = trigger {
+        env.push(("CRYO_FLAG".to_owned(), (*flag).to_owned()));
+    }
+    if let Some(threshold) = hook.threshold {
+        env.push(("CRYO_THRESHOLD".to_owned(), threshold.to_string()));
+    }
+    if let Some(data) = controller.last_data() {
+        env.extend([
+            (
+                "CRYO_TEC_TEMPERATURE".to_owned(),
+                data.tec_temperature.to_string(),
+            ),
+            (
+                "CRYO_DEW_POINT".to_owned(),
+                data.dew_point_temperature.to_string(),
+            ),
+            ("CRYO_HUMIDITY".to_owned(), data.humidity.to_string()),
+            (
+                "CRYO_POWER_LEVEL".to_owned(),
+                data.tec_power_level.to_string(),
+            ),
+        ]);
+    }
+    env
+}
+
+fn spawn(command: &str, env: &[(String, String)]) -> std::io::Result<Child> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell
+        .arg(command)
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+}
+
+/// Waits for `child` and kills it after `timeout`, returns what went wrong.
+fn wait(mut child: Child, timeout: Duration) -> Option<String> {
+    let start = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return None,
+            Ok(Some(status)) => return Some(format!("failed ({status})")),
+            Ok(None) if start.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Some(format!("timed out after {} s", timeout.as_secs_f32()));
+            }
+            Ok(None) => std::thread::sleep(POLL_INTERVAL),
+            Err(e) => return Some(format!("failed ({e})")),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn hook(event: HookEvent, flag: Option<&str>, threshold: Option<f32>) -> Hook {
+        Hook {
+            event,
+            flag: flag.map(str::to_owned),
+            threshold,
+            command: String::new(),
+        }
+    }
+
+    fn sample(seconds: i64, tec_temperature: f32) -> MonitoringData {
+        MonitoringData {
+            timestamp: DateTime::from_timestamp(seconds, 0).unwrap(),
+            tec_temperature,
+            pcb_temperature: 30.0,
+            humidity: 50.0,
+            dew_point_temperature: 10.0,
+            tec_voltage: 12.0,
+            tec_current: 3.0,
+            tec_power_level: 50,
+        }
+    }
+
+    #[test]
+    fn triggers() {
+        let mut runner = HookRunner::default();
+        runner.set_config(HookConfig {
+            timeout: Duration::from_secs(1),
+            condensation_margin: 1.0,
+            hooks: Vec::new(),
+        });
+        let disabled = TecStatus::POWER_OK | TecStatus::LOW_POWER_MODE_ACTIVE;
+        assert!(runner.detect(disabled, false, None).is_empty());
+
+        let triggers = runner.detect(TecStatus::POWER_OK, true, Some(&sample(0, 20.0)));
+        assert_eq!(
+            triggers,
+            vec![
+                Trigger::Event(HookEvent::TecEnabled),
+                Trigger::Flag(HookEvent::StatusCleared, "LOW_POWER_MODE_ACTIVE"),
+                Trigger::Event(HookEvent::ConnectionLost),
+            ]
+        );
+        assert!(triggers.iter().any(|trigger| hook(
+            HookEvent::StatusCleared,
+            Some("LOW_POWER_MODE_ACTIVE"),
+            None
+        )
+        .matches(trigger)));
+        assert!(!triggers.iter().any(|trigger| hook(
+            HookEvent::StatusCleared,
+            Some("OCP_ACTIVE"),
+            None
+        )
+        .matches(trigger)));
+
+        // The same sample is only evaluated once.
+        assert!(runner
+            .detect(TecStatus::POWER_OK, true, Some(&sample(0, 20.0)))
+            .is_empty());
+
+        let triggers = runner.detect(TecStatus::POWER_OK, false, Some(&sample(1, 10.5)));
+        assert_eq!(
+            triggers,
+            vec![
+                Trigger::Event(HookEvent::ConnectionRestored),
+                Trigger::Event(HookEvent::CondensationMarginBreached),
+                Trigger::Temperature {
+                    previous: 20.0,
+                    current: 10.5
+                },
+            ]
+        );
+        assert!(hook(HookEvent::TemperatureBelow, None, Some(15.0)).matches(&triggers[2]));
+        assert!(!hook(HookEvent::TemperatureAbove, None, Some(15.0)).matches(&triggers[2]));
+        assert!(!hook(HookEvent::TemperatureBelow, None, None).matches(&triggers[2]));
+
+        // Still breached, no new trigger.
+        let triggers = runner.detect(TecStatus::POWER_OK, false, Some(&sample(2, 10.2)));
+        assert_eq!(
+            triggers,
+            vec![Trigger::Temperature {
+                previous: 10.5,
+                current: 10.2
+            }]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn timeout() {
+        let env = [("CRYO_EVENT".to_owned(), "TecEnabled".to_owned())];
+        let child = spawn("test \"$CRYO_EVENT\" = TecEnabled", &env).unwrap();
+        assert_eq!(wait(child, Duration::from_secs(5)), None);
+        let child = spawn("exit 3", &env).unwrap();
+        assert!(wait(child, Duration::from_secs(5)).is_some());
+        let start = Instant::now();
+        let child = spawn("sleep 10", &env).unwrap();
+        assert_eq!(
+            wait(child, Duration::from_millis(200)),
+            Some("timed out after 0.2 s".to_owned())
+        );
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
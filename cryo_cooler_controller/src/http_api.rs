@@ -0,0 +1,674 @@
+//! HTTP server with a JSON API and a WebSocket stream of the measurements.
+//!
+//! | Request          | Response                                             |
+//! |------------------|------------------------------------------------------|
+//! | `GET /status`    | status flags                                         |
+//! | `GET /monitor`   | latest measurements                                  |
+//! | `GET /settings`  | all settings without the MQTT password               |
+//! | `GET /history`   | measurements of a time range, see below              |
+//! | `GET /ws`        | WebSocket sending every new sample                   |
+//! | `POST /enable`   | enables the TEC, optionally with `p`, `i`, `d`, `offset` and `max_power` |
+//! | `POST /disable`  | disables the TEC                                     |
+//! | `POST /offset`   | sets the offset, body `{"offset": 3}`                |
+//! | `POST /pid`      | sets the PID values, body `{"p": 100, "i": 1, "d": 1}` |
+//!
+//! `GET /history` takes the query parameters `from` and `to` as RFC 3339 timestamps, the last five
+//! minutes by default, and `max_points`. With the history database enabled, the measurements are
+//! averaged to at most `max_points` points, otherwise the ones of the last five minutes are kept.
+//!
+//! Web pages must not control the cooler, so POST requests need a JSON content type, which
+//! browsers only send after a CORS preflight, and a `Host` or `Origin` naming another server is
+//! rejected, which stops DNS rebinding.
+//!
+//! Connections are accepted by this module, which answers WebSocket handshakes itself and passes
+//! all other requests on to the HTTP server on an internal port. A WebSocket connection is then
+//! owned by its thread, which reads it with a timeout in between sending samples, so pings and
+//! closes of the client are answered.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use cryo_cooler_controller_lib::MonitoringData;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+use tungstenite::handshake::server::{ErrorResponse, Request as HandshakeRequest};
+
+use crate::api::{self, ApiError};
+use crate::controller::Controller;
+use crate::history::{HistoryDb, Quantity};
+
+/// Same time span as the charts.
+const HISTORY_DURATION: Duration = Duration::from_secs(300);
+const DEFAULT_HISTORY_POINTS: usize = 500;
+const MAX_HISTORY_POINTS: usize = 10_000;
+/// More WebSocket handshakes are refused.
+const MAX_WEBSOCKETS: usize = 8;
+/// How long a WebSocket connection waits for frames of the client before sending new samples.
+const WEBSOCKET_READ_TIMEOUT: Duration = Duration::from_millis(100);
+/// Clients stalling a request head, a handshake or a write for this long are dropped.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Longest request head looked at to tell WebSocket handshakes from other requests.
+const MAX_HEAD_SIZE: usize = 8192;
+const HEAD_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+type WebSockets = Arc<Mutex<Vec<Sender<String>>>>;
+
+pub struct HttpServer {
+    address: String,
+    /// Where the accept loop listens, with a loopback address if it listens on all of them.
+    local_address: SocketAddr,
+    stop: Arc<AtomicBool>,
+    /// Answers the requests passed on by the accept loop.
+    server: Arc<Server>,
+    requests: Receiver<Request>,
+    history: VecDeque<MonitoringData>,
+    /// One sender per connected WebSocket, each served by its own thread. Senders of closed
+    /// connections are removed by [`HttpServer::publish`].
+    websockets: WebSockets,
+}
+
+impl HttpServer {
+    pub fn start(address: &str) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let mut local_address = listener.local_addr()?;
+        if local_address.ip().is_unspecified() {
+            local_address.set_ip(match local_address {
+                SocketAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+                SocketAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+            });
+        }
+        let server = Arc::new(Server::http("127.0.0.1:0").map_err(std::io::Error::other)?);
+        let backend = server
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| std::io::Error::other("HTTP server without an IP address"))?;
+
+        let (sender, requests) = mpsc::channel();
+        let incoming = Arc::clone(&server);
+        std::thread::spawn(move || {
+            for request in incoming.incoming_requests() {
+                if sender.send(request).is_err() {
+                    return;
+                }
+            }
+        });
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let websockets = WebSockets::default();
+        let accept_stop = Arc::clone(&stop);
+        let accept_websockets = Arc::clone(&websockets);
+        let port = local_address.port();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                if accept_stop.load(Ordering::Relaxed) {
+                    return;
+                }
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let websockets = Arc::clone(&accept_websockets);
+                std::thread::spawn(move || serve_connection(stream, backend, port, websockets));
+            }
+        });
+        Ok(Self {
+            address: address.to_owned(),
+            local_address,
+            stop,
+            server,
+            requests,
+            history: VecDeque::new(),
+            websockets,
+        })
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Answers the received requests, `history` serves `GET /history` if it is enabled.
+    ///
+    /// Has to be called periodically by the owner of the controller.
+    pub fn poll(&mut self, controller: &mut Controller, history: Option<&HistoryDb>) {
+        while let Ok(mut request) = self.requests.try_recv() {
+            if let Err((status, error)) = check_request(&request, self.local_address.port()) {
+                let _ = request.respond(json_response(status, &json!({ "error": error })));
+                continue;
+            }
+            let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+            let (path, query) = (path.to_owned(), query.to_owned());
+            let result = match (request.method(), path.as_str()) {
+                // Handshakes are answered by the accept loop.
+                (Method::Get, "/ws") => {
+                    let _ = request.respond(json_response(
+                        400,
+                        &json!({ "error": "Expected a WebSocket handshake" }),
+                    ));
+                    continue;
+                }
+                (Method::Get, "/status") => Ok(api::status_json(controller)),
+                (Method::Get, "/monitor") => api::call(controller, "monitor", Value::Null),
+                (Method::Get, "/settings") => Ok(controller.settings().get_settings_json()),
+                (Method::Get, "/history") => {
+                    history_params(&query, Utc::now()).and_then(|params| match history {
+                        Some(history) => query_history(history, params),
+                        None => Ok(Value::Array(
+                            self.history
+                                .iter()
+                                .filter(|data| (params.0..=params.1).contains(&data.timestamp))
+                                .map(api::monitoring_json)
+                                .collect(),
+                        )),
+                    })
+                }
+                (Method::Post, "/enable" | "/disable" | "/offset" | "/pid") => {
+                    let method = match path.as_str() {
+                        "/offset" => "set_offset",
+                        "/pid" => "set_pid",
+                        method => &method[1..],
+                    };
+                    read_body(&mut request).and_then(|params| api::call(controller, method, params))
+                }
+                (_, "/status" | "/monitor" | "/settings" | "/history" | "/ws") => {
+                    let _ = request.respond(json_response(
+                        405,
+                        &json!({ "error": "Method not allowed" }),
+                    ));
+                    continue;
+                }
+                _ => Err(ApiError::MethodNotFound(path)),
+            };
+            let response = match result {
+                Ok(value) => json_response(200, &value),
+                Err(error) => {
+                    let status = match error {
+                        ApiError::MethodNotFound(_) => 404,
+                        ApiError::InvalidParams(_) => 400,
+                        ApiError::Failed(_) => 500,
+                    };
+                    json_response(status, &json!({ "error": error.to_string() }))
+                }
+            };
+            let _ = request.respond(response);
+        }
+    }
+
+    /// Adds `data` to the history and sends it to all connected WebSockets.
+    pub fn publish(&mut self, data: &MonitoringData) {
+        self.history.push_back(data.clone());
+        let oldest = Utc::now() - HISTORY_DURATION;
+        while self
+            .history
+            .front()
+            .is_some_and(|data| data.timestamp < oldest)
+        {
+            self.history.pop_front();
+        }
+        let message = api::monitoring_json(data).to_string();
+        self.websockets
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .retain(|websocket| websocket.send(message.clone()).is_ok());
+    }
+}
+
+impl Drop for HttpServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // Wakes up the accept loop, so it sees the stop flag.
+        let _ = TcpStream::connect(self.local_address);
+        self.server.unblock();
+    }
+}
+
+/// Serves a WebSocket handshake itself and passes other requests on to `backend`.
+fn serve_connection(stream: TcpStream, backend: SocketAddr, port: u16, websockets: WebSockets) {
+    if stream.set_read_timeout(Some(CLIENT_TIMEOUT)).is_err()
+        || stream.set_write_timeout(Some(CLIENT_TIMEOUT)).is_err()
+    {
+        return;
+    }
+    let Ok(head) = peek_head(&stream) else {
+        return;
+    };
+    if is_websocket_handshake(&head) {
+        serve_websocket(stream, port, websockets);
+    } else if stream.set_read_timeout(None).is_ok() && stream.set_write_timeout(None).is_ok() {
+        let _ = forward(stream, backend);
+    }
+}
+
+/// The request head at the start of `stream`, which is left unread.
+fn peek_head(stream: &TcpStream) -> io::Result<Vec<u8>> {
+    let deadline = Instant::now() + CLIENT_TIMEOUT;
+    let mut head = vec![0; MAX_HEAD_SIZE];
+    loop {
+        let size = stream.peek(&mut head)?;
+        let complete = head[..size].windows(4).any(|end| end == b"\r\n\r\n");
+        if complete || size == 0 || size == MAX_HEAD_SIZE || Instant::now() >= deadline {
+            head.truncate(size);
+            return Ok(head);
+        }
+        // Peeking returns right away while parts of the head are missing.
+        std::thread::sleep(HEAD_POLL_INTERVAL);
+    }
+}
+
+/// Whether `head` is a `GET /ws` request asking for a WebSocket.
+fn is_websocket_handshake(head: &[u8]) -> bool {
+    let head = String::from_utf8_lossy(head);
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let method = request_line.next();
+    let path = request_line
+        .next()
+        .and_then(|target| target.split('?').next());
+    method == Some("GET")
+        && path == Some("/ws")
+        && lines.any(|line| {
+            line.split_once(':').is_some_and(|(field, value)| {
+                field.trim().eq_ignore_ascii_case("Upgrade")
+                    && value.trim().eq_ignore_ascii_case("websocket")
+            })
+        })
+}
+
+/// Sends the samples published to the connection until it is closed.
+// The error of the handshake callback is the response given by tungstenite.
+#[allow(clippy::result_large_err)]
+fn serve_websocket(stream: TcpStream, port: u16, websockets: WebSockets) {
+    let (sender, messages) = mpsc::channel::<String>();
+    let callback = move |request: &HandshakeRequest, response| {
+        let header = |field| {
+            request
+                .headers()
+                .get(field)
+                .and_then(|value| value.to_str().ok())
+        };
+        check_authorities(header("Host"), header("Origin"), port)
+            .map_err(|(status, error)| handshake_error(status, error))?;
+        let mut websockets = websockets
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if websockets.len() >= MAX_WEBSOCKETS {
+            return Err(handshake_error(503, "Too many WebSocket clients"));
+        }
+        websockets.push(sender);
+        Ok(response)
+    };
+    let Ok(mut websocket) = tungstenite::accept_hdr(stream, callback) else {
+        return;
+    };
+    if websocket
+        .get_ref()
+        .set_read_timeout(Some(WEBSOCKET_READ_TIMEOUT))
+        .is_err()
+    {
+        return;
+    }
+    loop {
+        // Messages of the client are ignored, reading answers pings and closes.
+        match websocket.read() {
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(_) => return,
+        }
+        if !websocket.can_write() {
+            // Closed by the client, the next read sends the reply.
+            continue;
+        }
+        loop {
+            match messages.try_recv() {
+                Ok(message) => {
+                    if websocket.send(tungstenite::Message::Text(message)).is_err() {
+                        return;
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    let _ = websocket.close(None);
+                    let _ = websocket.flush();
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn handshake_error(status: u16, error: &str) -> ErrorResponse {
+    let mut response = ErrorResponse::new(Some(json!({ "error": error }).to_string()));
+    if let Ok(status) = tungstenite::http::StatusCode::from_u16(status) {
+        *response.status_mut() = status;
+    }
+    response
+}
+
+/// Passes the connection on to the HTTP server at `backend` until either side closes it.
+fn forward(client: TcpStream, backend: SocketAddr) -> io::Result<()> {
+    let server = TcpStream::connect(backend)?;
+    let (mut client_reader, mut server_writer) = (client.try_clone()?, server.try_clone()?);
+    let upload = std::thread::spawn(move || {
+        let _ = io::copy(&mut client_reader, &mut server_writer);
+        let _ = server_writer.shutdown(Shutdown::Write);
+    });
+    let _ = io::copy(&mut &server, &mut &client);
+    let _ = client.shutdown(Shutdown::Both);
+    let _ = upload.join();
+    Ok(())
+}
+
+/// `from`, `to` and `max_points` of a `GET /history` query.
+fn history_params(
+    query: &str,
+    now: DateTime<Utc>,
+) -> Result<(DateTime<Utc>, DateTime<Utc>, usize), ApiError> {
+    let mut from = None;
+    let mut to = now;
+    let mut max_points = DEFAULT_HISTORY_POINTS;
+    let invalid =
+        |field: &str, value: &str| ApiError::InvalidParams(format!("Invalid {field} \"{value}\""));
+    let time = |field: &str, value: &str| {
+        DateTime::parse_from_rfc3339(value)
+            .map(|time| time.with_timezone(&Utc))
+            .map_err(|_| invalid(field, value))
+    };
+    for (field, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+        match field {
+            "from" => from = Some(time(field, value)?),
+            "to" => to = time(field, value)?,
+            "max_points" => {
+                max_points = value
+                    .parse()
+                    .ok()
+                    .filter(|points| (1..=MAX_HISTORY_POINTS).contains(points))
+                    .ok_or_else(|| invalid(field, value))?;
+            }
+            _ => {}
+        }
+    }
+    let from = from.unwrap_or_else(|| to - HISTORY_DURATION);
+    if from > to {
+        return Err(ApiError::InvalidParams("from is after to".to_owned()));
+    }
+    Ok((from, to, max_points))
+}
+
+/// Averaged measurements from the history database, like the ones of the WebSocket.
+fn query_history(
+    history: &HistoryDb,
+    (from, to, max_points): (DateTime<Utc>, DateTime<Utc>, usize),
+) -> Result<Value, ApiError> {
+    let mut samples = BTreeMap::new();
+    for &quantity in Quantity::ALL {
+        let points = history
+            .query(quantity, from, to, max_points)
+            .map_err(|e| ApiError::Failed(e.to_string()))?;
+        for point in points {
+            let sample = samples
+                .entry(point.timestamp)
+                .or_insert_with(|| json!({ "timestamp": point.timestamp.to_rfc3339() }));
+            sample[quantity.column()] = json!(point.avg);
+        }
+    }
+    Ok(Value::Array(samples.into_values().collect()))
+}
+
+fn header<'a>(request: &'a Request, field: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(field))
+        .map(|header| header.value.as_str())
+}
+
+/// Whether `authority` (`host[:port]`) addresses this server by an IP address or as `localhost`.
+///
+/// Any other name may be a domain of a web page that resolves to this machine.
+fn is_own_authority(authority: &str, port: u16) -> bool {
+    let (host, authority_port) = match authority.rsplit_once(':') {
+        Some((host, authority_port)) if !authority_port.ends_with(']') => {
+            (host, authority_port.parse().ok())
+        }
+        _ => (authority, Some(80)),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    authority_port == Some(port)
+        && (host.eq_ignore_ascii_case("localhost") || host.parse::<IpAddr>().is_ok())
+}
+
+/// Rejects a `Host` or `Origin` naming another server.
+fn check_authorities(
+    host: Option<&str>,
+    origin: Option<&str>,
+    port: u16,
+) -> Result<(), (u16, &'static str)> {
+    if host.is_some_and(|host| !is_own_authority(host, port)) {
+        return Err((403, "Host not allowed"));
+    }
+    if let Some(origin) = origin {
+        let authority = origin.strip_prefix("http://").unwrap_or_default();
+        if !is_own_authority(authority, port) {
+            return Err((403, "Origin not allowed"));
+        }
+    }
+    Ok(())
+}
+
+/// Rejects requests made by web pages, see the module documentation.
+fn check_request(request: &Request, port: u16) -> Result<(), (u16, &'static str)> {
+    check_authorities(header(request, "Host"), header(request, "Origin"), port)?;
+    let json = header(request, "Content-Type").is_some_and(|content_type| {
+        content_type
+            .split(';')
+            .next()
+            .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/json"))
+    });
+    if *request.method() == Method::Post && !json {
+        return Err((415, "Content-Type must be application/json"));
+    }
+    Ok(())
+}
+
+fn read_body(request: &mut Request) -> Result<Value, ApiError> {
+    let mut body = String::new();
+    request
+        .as_reader()
+        .read_to_string(&mut body)
+        .map_err(|e| ApiError::InvalidParams(e.to_string()))?;
+    if body.trim().is_empty() {
+        return Ok(Value::Null);
+    }
+    serde_json::from_str(&body).map_err(|e| ApiError::InvalidParams(e.to_string()))
+}
+
+fn json_response(status: u16, value: &Value) -> Response<std::io::Cursor<Vec<u8>>> {
+    let mut response = Response::from_string(value.to_string()).with_status_code(status);
+    if let Ok(header) = Header::from_bytes("Content-Type", "application/json") {
+        response.add_header(header);
+    }
+    response
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use chrono::TimeZone;
+    use cryo_cooler_controller_lib::StatusWord;
+
+    use super::*;
+    use crate::history::Retention;
+
+    fn sample(timestamp: DateTime<Utc>) -> MonitoringData {
+        MonitoringData {
+            timestamp,
+            tec_temperature: 4.5,
+            pcb_temperature: 25.0,
+            humidity: 50.0,
+            dew_point_temperature: 14.0,
+            tec_voltage: 12.0,
+            tec_current: 3.0,
+            tec_power_level: 40,
+        }
+    }
+
+    #[test]
+    fn own_authority() {
+        assert!(is_own_authority("127.0.0.1:8383", 8383));
+        assert!(is_own_authority("localhost:8383", 8383));
+        assert!(is_own_authority("[::1]:8383", 8383));
+        assert!(is_own_authority("192.168.1.20:8383", 8383));
+        assert!(is_own_authority("localhost", 80));
+        assert!(!is_own_authority("localhost", 8383));
+        assert!(!is_own_authority("127.0.0.1:8080", 8383));
+        assert!(!is_own_authority("attacker.example:8383", 8383));
+        assert!(!is_own_authority("", 8383));
+    }
+
+    #[test]
+    fn history_query() {
+        let now = Utc::now();
+        assert_eq!(
+            history_params("", now).unwrap(),
+            (now - HISTORY_DURATION, now, DEFAULT_HISTORY_POINTS)
+        );
+        let (from, to, max_points) = history_params(
+            "from=2024-03-10T10:00:00Z&to=2024-03-10T12:00:00+01:00&max_points=20&x=1",
+            now,
+        )
+        .unwrap();
+        assert_eq!(from.to_rfc3339(), "2024-03-10T10:00:00+00:00");
+        assert_eq!(to.to_rfc3339(), "2024-03-10T11:00:00+00:00");
+        assert_eq!(max_points, 20);
+        for query in [
+            "from=yesterday",
+            "max_points=0",
+            "max_points=100000",
+            "from=2024-03-10T11:00:00Z&to=2024-03-10T10:00:00Z",
+        ] {
+            assert!(matches!(
+                history_params(query, now),
+                Err(ApiError::InvalidParams(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn database_history() {
+        let dir = tempdir::TempDir::new("http_api").unwrap();
+        let retention = Retention {
+            raw: Duration::from_secs(3600),
+            minute: Duration::from_secs(3600),
+            hour: None,
+        };
+        let mut db = HistoryDb::open(&dir.path().join("history.sqlite3"), retention).unwrap();
+        // Stored with millisecond precision.
+        let now = Utc
+            .timestamp_millis_opt(Utc::now().timestamp_millis())
+            .unwrap();
+        for (seconds, tec_temperature) in [(30, 10.0), (20, 11.0), (10, 12.0)] {
+            let data = MonitoringData {
+                tec_temperature,
+                ..sample(now - chrono::Duration::seconds(seconds))
+            };
+            db.record_sample(&data, StatusWord::new(0)).unwrap();
+        }
+        let history = query_history(&db, (now - HISTORY_DURATION, now, 100)).unwrap();
+        let history = history.as_array().unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0]["tec_temperature"], 10.0);
+        assert_eq!(history[2]["tec_temperature"], 12.0);
+        assert_eq!(history[2]["power_level"], 40.0);
+        assert_eq!(
+            history[2]["timestamp"],
+            (now - chrono::Duration::seconds(10)).to_rfc3339()
+        );
+    }
+
+    #[test]
+    fn websocket_handshake() {
+        let handshake = "GET /ws?token=1 HTTP/1.1\r\nHost: localhost\r\nUpgrade: WebSocket\r\n\r\n";
+        assert!(is_websocket_handshake(handshake.as_bytes()));
+        assert!(!is_websocket_handshake(
+            b"GET /ws HTTP/1.1\r\nHost: localhost\r\n\r\n"
+        ));
+        assert!(!is_websocket_handshake(
+            b"GET /status HTTP/1.1\r\nUpgrade: websocket\r\n\r\n"
+        ));
+        assert!(!is_websocket_handshake(
+            b"POST /ws HTTP/1.1\r\nUpgrade: websocket\r\n\r\n"
+        ));
+        assert!(!is_websocket_handshake(b""));
+    }
+
+    #[test]
+    fn forwarding() {
+        let server = HttpServer::start("127.0.0.1:0").unwrap();
+        let mut stream = TcpStream::connect(server.local_address).unwrap();
+        stream
+            .write_all(b"GET /status HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        let request = server
+            .requests
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(request.url(), "/status");
+        assert_eq!(header(&request, "Host"), Some("localhost"));
+        request
+            .respond(json_response(200, &json!({ "ok": true })))
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.ends_with(r#"{"ok":true}"#));
+    }
+
+    #[test]
+    fn websocket() {
+        let mut server = HttpServer::start("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}/ws", server.local_address);
+        let (mut client, _) = tungstenite::connect(&url).unwrap();
+
+        server.publish(&MonitoringData {
+            tec_temperature: 4.5,
+            ..sample(Utc::now())
+        });
+        let message = client.read().unwrap();
+        let sample: Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+        assert_eq!(sample["tec_temperature"], 4.5);
+
+        client
+            .send(tungstenite::Message::Ping(b"ping".to_vec()))
+            .unwrap();
+        assert_eq!(
+            client.read().unwrap(),
+            tungstenite::Message::Pong(b"ping".to_vec())
+        );
+
+        let mut others: Vec<_> = (1..MAX_WEBSOCKETS)
+            .map(|_| tungstenite::connect(&url).unwrap().0)
+            .collect();
+        assert!(tungstenite::connect(&url).is_err());
+
+        client.close(None).unwrap();
+        assert!(client.read().unwrap().is_close());
+        assert!(matches!(
+            client.read(),
+            Err(tungstenite::Error::ConnectionClosed)
+        ));
+
+        // Closing the server closes the connections.
+        drop(server);
+        assert!(others[0].read().unwrap().is_close());
+    }
+}
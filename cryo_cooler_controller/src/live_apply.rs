@@ -0,0 +1,134 @@
+//! Bookkeeping for writing edited parameters to the cooler while the TEC is running.
+
+use std::time::{Duration, Instant};
+
+/// Time without further edits before live changes are written to the cooler.
+pub const LIVE_APPLY_DEBOUNCE: Duration = Duration::from_millis(800);
+
+/// Debounces edits of the parameters `T` and remembers the parameters before the last write, so
+/// it can be undone.
+#[derive(Debug, Clone)]
+pub struct LiveApply<T> {
+    /// Parameters before the first edit that was not written yet, and the time of the last edit.
+    pending: Option<(T, Instant)>,
+    /// Parameters before the last write.
+    undo: Option<T>,
+}
+
+impl<T> Default for LiveApply<T> {
+    fn default() -> Self {
+        Self {
+            pending: None,
+            undo: None,
+        }
+    }
+}
+
+impl<T: Copy + PartialEq> LiveApply<T> {
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.undo.is_some()
+    }
+
+    /// Notes an edit, `previous` are the parameters right before it.
+    ///
+    /// Edits in quick succession are combined, so an undo goes back to the parameters before the
+    /// first of them.
+    pub fn edited(&mut self, previous: T, now: Instant) {
+        let previous = self.pending.map_or(previous, |(snapshot, _)| snapshot);
+        self.pending = Some((previous, now));
+    }
+
+    /// Whether the edits have to be written now, with `current` as the edited parameters.
+    ///
+    /// Edits that ended up at the parameters before them are dropped without a write.
+    pub fn poll(&mut self, current: T, now: Instant) -> bool {
+        let Some((previous, edited)) = self.pending else {
+            return false;
+        };
+        if now.saturating_duration_since(edited) < LIVE_APPLY_DEBOUNCE {
+            return false;
+        }
+        self.pending = None;
+        if previous == current {
+            return false;
+        }
+        self.undo = Some(previous);
+        true
+    }
+
+    /// Parameters to restore to undo the last write, also drops edits that were not written yet.
+    pub fn take_undo(&mut self) -> Option<T> {
+        let undo = self.undo.take()?;
+        self.pending = None;
+        Some(undo)
+    }
+
+    /// Drops the edits that were not written yet.
+    pub fn cancel_pending(&mut self) {
+        self.pending = None;
+    }
+
+    /// Forgets everything, for example after the TEC was enabled or disabled.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debounce() {
+        let start = Instant::now();
+        let mut live_apply = LiveApply::default();
+        live_apply.edited(1.0, start);
+        assert!(live_apply.is_pending());
+        assert!(!live_apply.poll(2.0, start + Duration::from_millis(500)));
+        live_apply.edited(2.0, start + Duration::from_millis(500));
+        assert!(!live_apply.poll(3.0, start + Duration::from_millis(1000)));
+        assert!(live_apply.poll(3.0, start + Duration::from_millis(1300)));
+        assert!(!live_apply.is_pending());
+        assert!(!live_apply.poll(3.0, start + Duration::from_millis(2000)));
+
+        // Combined edits undo to the value before the first one.
+        assert_eq!(live_apply.take_undo(), Some(1.0));
+        assert!(!live_apply.can_undo());
+    }
+
+    #[test]
+    fn unchanged() {
+        let start = Instant::now();
+        let mut live_apply = LiveApply::default();
+        live_apply.edited(1.0, start);
+        live_apply.edited(2.0, start + Duration::from_millis(100));
+        assert!(!live_apply.poll(1.0, start + Duration::from_secs(1)));
+        assert!(!live_apply.is_pending());
+        assert!(!live_apply.can_undo());
+    }
+
+    #[test]
+    fn undo_drops_pending() {
+        let start = Instant::now();
+        let mut live_apply = LiveApply::default();
+        live_apply.edited(1.0, start);
+        assert!(live_apply.poll(2.0, start + Duration::from_secs(1)));
+        live_apply.edited(2.0, start + Duration::from_secs(2));
+        assert_eq!(live_apply.take_undo(), Some(1.0));
+        assert!(!live_apply.is_pending());
+        assert_eq!(live_apply.take_undo(), None);
+
+        live_apply.edited(1.0, start + Duration::from_secs(3));
+        live_apply.cancel_pending();
+        assert!(!live_apply.poll(2.0, start + Duration::from_secs(4)));
+
+        live_apply.edited(1.0, start + Duration::from_secs(5));
+        assert!(live_apply.poll(2.0, start + Duration::from_secs(6)));
+        live_apply.reset();
+        assert!(!live_apply.can_undo());
+    }
+}
@@ -17,9 +17,28 @@
 extern crate iced;
 extern crate plotters;
 
+mod api;
 mod charts;
+#[cfg(unix)]
+mod control_socket;
+mod controller;
+mod csv_log;
+mod daemon;
+#[cfg(target_os = "linux")]
+mod dbus;
+mod event_log;
+mod history;
+mod hooks;
+mod http_api;
+mod live_apply;
+mod metrics;
+mod mqtt;
+mod recording;
 mod running;
+mod scripting;
+mod services;
 mod settings;
+mod viewer;
 
 use iced::{
     alignment, executor,
@@ -33,11 +52,15 @@ use tray_icon::{
     menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem},
     TrayIconBuilder,
 };
+use viewer::ViewerState;
 
 const ICON: &[u8; 0x4000] = include_bytes!(concat!(env!("OUT_DIR"), "/icon.bin"));
 
 fn main() {
     let settings = settings::AppSettings::new();
+    if std::env::args().skip(1).any(|arg| arg == "--daemon") {
+        std::process::exit(daemon::run(settings));
+    }
     let icon =
         tray_icon::icon::Icon::from_rgba(ICON.to_vec(), 64, 64).expect("Failed to open icon");
 
@@ -90,8 +113,56 @@ pub enum Message {
     UpdateICoef(f32),
     UpdateDCoef(f32),
     UpdateSetpoint(f32),
+    SetpointModeSelected(settings::SetpointMode),
+    UpdateAbsoluteTarget(f32),
+    UpdateSafetyMargin(f32),
     UpdateMaxPower(u8),
     ApplyStartupCheckboxToggled(bool),
+    UpdateHeartbeatInterval(u64),
+    UpdateWatchdogTimeout(u64),
+    UpdateRecoveryAction(settings::RecoveryAction),
+    DiagnosticsToggled(bool),
+    AdvancedToggled(bool),
+    UpdateOcpDebounce(u64),
+    UpdateOcpThreshold(f32),
+    UpdateOcpResponse(settings::OcpResponse),
+    UpdateOcpSetpointStep(f32),
+    UpdateDewPointTolerance(f32),
+    UseHostDewPointToggled(bool),
+    VerifyWritesToggled(bool),
+    ControlSocketToggled(bool),
+    HttpApiToggled(bool),
+    MetricsToggled(bool),
+    MqttToggled(bool),
+    DbusServiceToggled(bool),
+    ScriptingToggled(bool),
+    ReloadScripts,
+    LoggingToggled(bool),
+    HistoryToggled(bool),
+    ChartRangeSelected(charts::ChartRange),
+    ChartWindowSelected(charts::ChartWindow),
+    ChartZoom(f32, f32),
+    ChartPan(f32),
+    FollowLiveToggled(bool),
+    ExportTargetSelected(charts::ExportTarget),
+    ExportCharts(charts::ImageFormat),
+    ApplyParameters,
+    LoadParametersFromDevice,
+    LiveApplyToggled(bool),
+    UndoLiveApply,
+    OpenCalibration,
+    CloseCalibration,
+    CalibrationQuantitySelected(controller::CalibrationQuantity),
+    UpdateCalibrationReading(f32),
+    UpdateCalibrationOffset(f32),
+    ApplyCalibration,
+    ResetCalibration,
+    StartSelfTest,
+    AbortSelfTest,
+    SaveSelfTestReport,
+    CloseSelfTest,
+    RecoveryReEnable,
+    RecoveryKeepDisabled,
 
     PortSelected(PortIdent),
     Open,
@@ -100,6 +171,10 @@ pub enum Message {
     FontLoaded,
     FontLoadingFailed,
     OpenCheckboxToggled(bool),
+    LogPathChanged(String),
+    OpenLog,
+    ViewerFullRange,
+    CloseViewer,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -117,6 +192,9 @@ impl std::fmt::Display for PortIdent {
 struct HomeState {
     selected_port: Option<PortIdent>,
     error_text: Option<String>,
+    /// CSV log or history database to open without a cooler.
+    log_path: String,
+    log_error: Option<String>,
     app_settings: settings::AppSettings,
 }
 
@@ -132,9 +210,18 @@ impl HomeState {
             None => ports.pop(),
         };
 
+        let history_path = app_settings.get_history_path();
+        let log_path = if history_path.exists() {
+            history_path.display().to_string()
+        } else {
+            String::new()
+        };
+
         Self {
             selected_port: port,
             error_text: None,
+            log_path,
+            log_error: None,
             app_settings,
         }
     }
@@ -151,6 +238,10 @@ impl HomeState {
                     self.error_text = Some(format!("Failed to save settings ({e})"));
                 }
             }
+            Message::LogPathChanged(path) => {
+                self.log_path = path;
+                self.log_error = None;
+            }
             _ => {}
         }
         Command::none()
@@ -180,7 +271,16 @@ impl HomeState {
             .style(iced::theme::Button::Primary)
             .on_press(Message::Open);
 
-        let content = Column::new()
+        let log_input = iced::widget::text_input("CSV log or history database", &self.log_path)
+            .on_input(Message::LogPathChanged)
+            .on_submit(Message::OpenLog)
+            .padding(10)
+            .width(Length::Fixed(250.0));
+        let open_log_btn = button("Open Log")
+            .style(iced::theme::Button::Secondary)
+            .on_press(Message::OpenLog);
+
+        let mut content = Column::new()
             .align_items(iced::Alignment::Center)
             .push(Row::new().spacing(20).push(label))
             .push(iced::widget::vertical_space(Length::Fixed(50.0)))
@@ -203,7 +303,24 @@ impl HomeState {
                         self.app_settings.get_open_port_on_startup(),
                         Message::OpenCheckboxToggled,
                     )),
+            )
+            .push(iced::widget::vertical_space(Length::Fixed(50.0)))
+            .push(
+                Row::new()
+                    .spacing(20)
+                    .align_items(iced::Alignment::Center)
+                    .push(log_input)
+                    .push(open_log_btn),
             );
+        if let Some(error) = &self.log_error {
+            content = content
+                .push(iced::widget::vertical_space(Length::Fixed(15.0)))
+                .push(Text::new(error).style(Color::from_rgb(
+                    0xC3 as f32 / 255.0,
+                    0x42 as f32 / 255.0,
+                    0x3F as f32 / 255.0,
+                )));
+        }
 
         let content = iced_aw::Modal::new(self.error_text.is_some(), content,
             iced_aw::Card::new(
@@ -244,6 +361,7 @@ struct CryoCoolerController {
 enum State {
     Home(HomeState),
     Running(RunningState),
+    Viewer(ViewerState),
 }
 
 impl Application for CryoCoolerController {
@@ -360,6 +478,30 @@ impl Application for CryoCoolerController {
                     }
                 }
             }
+            Message::OpenLog => {
+                if let State::Home(ref mut home) = self.state {
+                    let path = std::path::PathBuf::from(home.log_path.trim());
+                    match ViewerState::new(&path, home.app_settings.clone()) {
+                        Ok(viewer) => {
+                            self.state = State::Viewer(viewer);
+                            return Command::single(iced_runtime::command::Action::Window(
+                                iced_runtime::window::Action::Resize(Size::new(1400, 1000)),
+                            ));
+                        }
+                        Err(error) => {
+                            home.log_error =
+                                Some(format!("Error opening {} ({error})", path.display()));
+                            return iced_runtime::Command::none();
+                        }
+                    }
+                }
+            }
+            Message::CloseViewer => {
+                if let State::Viewer(ref viewer) = self.state {
+                    self.state = State::Home(HomeState::new(viewer.settings().clone()));
+                    return iced_runtime::Command::none();
+                }
+            }
             Message::Hide => {
                 return Command::single(iced_runtime::command::Action::Window(
                     iced_runtime::window::Action::ChangeMode(iced::window::Mode::Hidden),
@@ -376,6 +518,7 @@ impl Application for CryoCoolerController {
         match &mut self.state {
             State::Home(state) => state.update(message),
             State::Running(state) => state.update(message),
+            State::Viewer(state) => state.update(message),
         }
     }
 
@@ -383,6 +526,7 @@ impl Application for CryoCoolerController {
         match &self.state {
             State::Home(state) => state.view(),
             State::Running(state) => state.view(),
+            State::Viewer(state) => state.view(),
         }
     }
 
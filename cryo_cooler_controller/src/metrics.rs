@@ -0,0 +1,222 @@
+//! Prometheus exporter for the measurements, status flags and serial communication.
+
+use std::fmt::Write;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+
+use cryo_cooler_controller_lib::{ProtocolStats, TecStatus};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::controller::Controller;
+
+pub struct MetricsServer {
+    address: String,
+    server: Arc<Server>,
+    requests: Receiver<Request>,
+}
+
+impl MetricsServer {
+    pub fn start(address: &str) -> std::io::Result<Self> {
+        let server = Arc::new(Server::http(address).map_err(std::io::Error::other)?);
+        let (sender, requests) = mpsc::channel();
+        let incoming = Arc::clone(&server);
+        std::thread::spawn(move || {
+            for request in incoming.incoming_requests() {
+                if sender.send(request).is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(Self {
+            address: address.to_owned(),
+            server,
+            requests,
+        })
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Answers the received scrapes.
+    ///
+    /// Has to be called periodically by the owner of the controller.
+    pub fn poll(&mut self, controller: &Controller) {
+        while let Ok(request) = self.requests.try_recv() {
+            let response = if request.method() != &Method::Get {
+                Response::from_string("Method not allowed").with_status_code(405)
+            } else if request.url().split('?').next() != Some("/metrics") {
+                Response::from_string("Not found").with_status_code(404)
+            } else {
+                let mut response = Response::from_string(render(controller));
+                if let Ok(header) = Header::from_bytes("Content-Type", "text/plain; version=0.0.4")
+                {
+                    response.add_header(header);
+                }
+                response
+            };
+            let _ = request.respond(response);
+        }
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+    }
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    let _ = writeln!(
+        out,
+        "# HELP {name} {help}\n# TYPE {name} gauge\n{name} {value}"
+    );
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    let _ = writeln!(
+        out,
+        "# HELP {name} {help}\n# TYPE {name} counter\n{name} {value}"
+    );
+}
+
+/// Metrics in the Prometheus text format.
+pub fn render(controller: &Controller) -> String {
+    let mut out = String::new();
+    if let Some(data) = controller.last_data() {
+        for (name, help, value) in [
+            (
+                "cryo_cooler_tec_temperature_celsius",
+                "Temperature of the TEC.",
+                data.tec_temperature,
+            ),
+            (
+                "cryo_cooler_board_temperature_celsius",
+                "Temperature of the controller board.",
+                data.pcb_temperature,
+            ),
+            (
+                "cryo_cooler_humidity_percent",
+                "Relative humidity.",
+                data.humidity,
+            ),
+            (
+                "cryo_cooler_dew_point_celsius",
+                "Dew point reported by the cooler.",
+                data.dew_point_temperature,
+            ),
+            (
+                "cryo_cooler_tec_voltage_volts",
+                "TEC voltage.",
+                data.tec_voltage,
+            ),
+            (
+                "cryo_cooler_tec_current_amperes",
+                "TEC current.",
+                data.tec_current,
+            ),
+            (
+                "cryo_cooler_tec_power_level_percent",
+                "TEC power level.",
+                f32::from(data.tec_power_level),
+            ),
+        ] {
+            gauge(&mut out, name, help, f64::from(value));
+        }
+    }
+
+    let status = controller.tec_status();
+    let _ = writeln!(
+        out,
+        "# HELP cryo_cooler_status_flag Status flags of the last heartbeat.\n# TYPE cryo_cooler_status_flag gauge"
+    );
+    for (name, flag) in TecStatus::all().iter_names() {
+        let _ = writeln!(
+            out,
+            "cryo_cooler_status_flag{{flag=\"{name}\"}} {}",
+            u8::from(status.contains(flag))
+        );
+    }
+    gauge(
+        &mut out,
+        "cryo_cooler_heartbeat_missed",
+        "1 if the cooler did not answer the heartbeat in time.",
+        f64::from(u8::from(controller.watchdog().is_heartbeat_missed())),
+    );
+
+    render_protocol_stats(&mut out, controller.protocol_stats());
+    out
+}
+
+fn render_protocol_stats(out: &mut String, stats: &ProtocolStats) {
+    counter(
+        out,
+        "cryo_cooler_commands_sent_total",
+        "Commands sent to the cooler.",
+        stats.commands_sent,
+    );
+    counter(
+        out,
+        "cryo_cooler_crc_errors_total",
+        "Responses with an incorrect checksum.",
+        stats.crc_errors,
+    );
+    counter(
+        out,
+        "cryo_cooler_op_code_mismatches_total",
+        "Responses that did not belong to the sent command.",
+        stats.op_code_mismatches,
+    );
+    counter(
+        out,
+        "cryo_cooler_io_errors_total",
+        "Failed reads and writes on the serial port.",
+        stats.io_errors,
+    );
+    counter(
+        out,
+        "cryo_cooler_reconnects_total",
+        "Times the serial port was opened again.",
+        stats.reconnects,
+    );
+
+    let name = "cryo_cooler_command_latency_seconds";
+    let _ = writeln!(
+        out,
+        "# HELP {name} Time between sending a command and receiving the response.\n# TYPE {name} histogram"
+    );
+    for (bound, count) in stats.latency.cumulative_counts() {
+        let _ = writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {count}");
+    }
+    let _ = writeln!(
+        out,
+        "{name}_bucket{{le=\"+Inf\"}} {count}\n{name}_sum {}\n{name}_count {count}",
+        stats.latency.sum().as_secs_f64(),
+        count = stats.latency.count()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn protocol_stats() {
+        let mut stats = ProtocolStats {
+            commands_sent: 3,
+            crc_errors: 1,
+            ..Default::default()
+        };
+        stats.latency.observe(Duration::from_millis(4));
+        stats.latency.observe(Duration::from_millis(20));
+        let mut out = String::new();
+        render_protocol_stats(&mut out, &stats);
+        assert!(out.contains("\ncryo_cooler_commands_sent_total 3\n"));
+        assert!(out.contains("\ncryo_cooler_crc_errors_total 1\n"));
+        assert!(out.contains("\ncryo_cooler_command_latency_seconds_bucket{le=\"0.005\"} 1\n"));
+        assert!(out.contains("\ncryo_cooler_command_latency_seconds_bucket{le=\"0.025\"} 2\n"));
+        assert!(out.contains("\ncryo_cooler_command_latency_seconds_bucket{le=\"+Inf\"} 2\n"));
+        assert!(out.ends_with("cryo_cooler_command_latency_seconds_count 2\n"));
+    }
+}
@@ -0,0 +1,372 @@
+//! MQTT publishing of the measurements with Home Assistant discovery.
+//!
+//! With the topic prefix `cryo_cooler`, samples are published to `cryo_cooler/monitoring`, the
+//! status flags to `cryo_cooler/status` and the configured values to `cryo_cooler/parameters`.
+//! `cryo_cooler/set/enabled` (`ON`/`OFF`), `cryo_cooler/set/offset`, `cryo_cooler/set/p`,
+//! `cryo_cooler/set/i` and `cryo_cooler/set/d` change the cooler.
+
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+use cryo_cooler_controller_lib::MonitoringData;
+use rumqttc::{Client, Event, LastWill, MqttOptions, Packet, QoS};
+use serde_json::{json, Value};
+
+use crate::api;
+use crate::controller::Controller;
+
+/// Wait time before reconnecting after the connection to the broker failed.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Broker connection as configured in the settings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    pub credentials: Option<(String, String)>,
+    pub topic_prefix: String,
+    /// Prefix of the Home Assistant discovery topics, `None` disables discovery.
+    pub discovery_prefix: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Command {
+    Enable(bool),
+    Offset(f32),
+    P(f32),
+    I(f32),
+    D(f32),
+}
+
+enum Incoming {
+    Connected,
+    Publish { topic: String, payload: Vec<u8> },
+}
+
+pub struct MqttClient {
+    config: MqttConfig,
+    client: Client,
+    incoming: Receiver<Incoming>,
+}
+
+impl MqttClient {
+    /// Connects in the background, the broker does not have to be reachable yet.
+    pub fn start(config: MqttConfig) -> Self {
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        options.set_last_will(LastWill::new(
+            availability_topic(&config.topic_prefix),
+            "offline",
+            QoS::AtLeastOnce,
+            true,
+        ));
+        if let Some((username, password)) = &config.credentials {
+            options.set_credentials(username, password);
+        }
+        let (client, mut connection) = Client::new(options, 64);
+        let (sender, incoming) = mpsc::channel();
+        std::thread::spawn(move || {
+            // Ends when the client is dropped.
+            for notification in connection.iter() {
+                let incoming = match notification {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => Incoming::Connected,
+                    Ok(Event::Incoming(Packet::Publish(publish))) => Incoming::Publish {
+                        topic: publish.topic,
+                        payload: publish.payload.to_vec(),
+                    },
+                    Ok(_) => continue,
+                    Err(_) => {
+                        std::thread::sleep(RECONNECT_DELAY);
+                        continue;
+                    }
+                };
+                if sender.send(incoming).is_err() {
+                    return;
+                }
+            }
+        });
+        Self {
+            config,
+            client,
+            incoming,
+        }
+    }
+
+    pub fn config(&self) -> &MqttConfig {
+        &self.config
+    }
+
+    fn publish(&self, topic: String, payload: String, retain: bool) {
+        let _ = self
+            .client
+            .try_publish(topic, QoS::AtLeastOnce, retain, payload);
+    }
+
+    /// Executes received commands and announces the cooler after (re)connecting.
+    ///
+    /// Has to be called periodically by the owner of the controller.
+    pub fn poll(&mut self, controller: &mut Controller) {
+        while let Ok(incoming) = self.incoming.try_recv() {
+            match incoming {
+                Incoming::Connected => {
+                    let prefix = &self.config.topic_prefix;
+                    let _ = self
+                        .client
+                        .try_subscribe(format!("{prefix}/set/+"), QoS::AtLeastOnce);
+                    if let Some(discovery_prefix) = &self.config.discovery_prefix {
+                        for (topic, payload) in
+                            discovery_messages(prefix, discovery_prefix, &self.config.client_id)
+                        {
+                            self.publish(topic, payload.to_string(), true);
+                        }
+                    }
+                    self.publish(availability_topic(prefix), "online".to_owned(), true);
+                }
+                Incoming::Publish { topic, payload } => {
+                    let Some(command) = parse_command(&self.config.topic_prefix, &topic, &payload)
+                    else {
+                        continue;
+                    };
+                    let parameters = controller.configured_parameters();
+                    let (method, params) = match command {
+                        Command::Enable(true) => ("enable", Value::Null),
+                        Command::Enable(false) => ("disable", Value::Null),
+                        Command::Offset(offset) => ("set_offset", json!({ "offset": offset })),
+                        Command::P(p) => (
+                            "set_pid",
+                            json!({ "p": p, "i": parameters.i, "d": parameters.d }),
+                        ),
+                        Command::I(i) => (
+                            "set_pid",
+                            json!({ "p": parameters.p, "i": i, "d": parameters.d }),
+                        ),
+                        Command::D(d) => (
+                            "set_pid",
+                            json!({ "p": parameters.p, "i": parameters.i, "d": d }),
+                        ),
+                    };
+                    // Failures are reported by the controller, there is nobody to answer to.
+                    let _ = api::call(controller, method, params);
+                    self.publish_state(controller);
+                }
+            }
+        }
+    }
+
+    /// Publishes a new sample together with the current status.
+    pub fn publish_sample(&mut self, controller: &Controller, data: &MonitoringData) {
+        self.publish(
+            format!("{}/monitoring", self.config.topic_prefix),
+            api::monitoring_json(data).to_string(),
+            false,
+        );
+        self.publish_state(controller);
+    }
+
+    fn publish_state(&self, controller: &Controller) {
+        let prefix = &self.config.topic_prefix;
+        self.publish(
+            format!("{prefix}/status"),
+            api::status_json(controller).to_string(),
+            true,
+        );
+        let parameters = controller.configured_parameters();
+        self.publish(
+            format!("{prefix}/parameters"),
+            json!({
+                "offset": controller.settings().get_set_point(),
+                "applied_offset": parameters.setpoint_offset,
+                "p": parameters.p,
+                "i": parameters.i,
+                "d": parameters.d,
+            })
+            .to_string(),
+            true,
+        );
+    }
+}
+
+impl Drop for MqttClient {
+    fn drop(&mut self) {
+        self.publish(
+            availability_topic(&self.config.topic_prefix),
+            "offline".to_owned(),
+            true,
+        );
+        let _ = self.client.try_disconnect();
+    }
+}
+
+fn availability_topic(prefix: &str) -> String {
+    format!("{prefix}/availability")
+}
+
+fn parse_command(prefix: &str, topic: &str, payload: &[u8]) -> Option<Command> {
+    let name = topic.strip_prefix(prefix)?.strip_prefix("/set/")?;
+    let payload = std::str::from_utf8(payload).ok()?.trim();
+    if name == "enabled" {
+        return match payload {
+            "ON" => Some(Command::Enable(true)),
+            "OFF" => Some(Command::Enable(false)),
+            _ => None,
+        };
+    }
+    let value: f32 = payload.parse().ok()?;
+    match name {
+        "offset" => Some(Command::Offset(value)),
+        "p" => Some(Command::P(value)),
+        "i" => Some(Command::I(value)),
+        "d" => Some(Command::D(value)),
+        _ => None,
+    }
+}
+
+/// Home Assistant discovery topics and their retained config payloads.
+fn discovery_messages(prefix: &str, discovery_prefix: &str, node_id: &str) -> Vec<(String, Value)> {
+    let device = json!({
+        "identifiers": [node_id],
+        "name": "Cryo Cooler",
+        "model": "Cryo Cooler",
+    });
+    let entity = |component: &str, object_id: &str, name: &str, mut config: Value| {
+        config["name"] = json!(name);
+        config["unique_id"] = json!(format!("{node_id}_{object_id}"));
+        config["device"] = device.clone();
+        config["availability_topic"] = json!(availability_topic(prefix));
+        (
+            format!("{discovery_prefix}/{component}/{node_id}/{object_id}/config"),
+            config,
+        )
+    };
+
+    let mut messages: Vec<_> = [
+        (
+            "tec_temperature",
+            "TEC Temperature",
+            Some("temperature"),
+            "°C",
+        ),
+        (
+            "board_temperature",
+            "Board Temperature",
+            Some("temperature"),
+            "°C",
+        ),
+        ("humidity", "Humidity", Some("humidity"), "%"),
+        ("dew_point", "Dew Point", Some("temperature"), "°C"),
+        ("voltage", "TEC Voltage", Some("voltage"), "V"),
+        ("current", "TEC Current", Some("current"), "A"),
+        ("power_level", "TEC Power Level", None, "%"),
+    ]
+    .into_iter()
+    .map(|(field, name, device_class, unit)| {
+        entity(
+            "sensor",
+            field,
+            name,
+            json!({
+                "state_topic": format!("{prefix}/monitoring"),
+                "value_template": format!("{{{{ value_json.{field} }}}}"),
+                "device_class": device_class,
+                "unit_of_measurement": unit,
+                "state_class": "measurement",
+            }),
+        )
+    })
+    .collect();
+
+    // The offset written to the cooler, which follows the dew point settings, read-only next to
+    // the configured offset of the number entity.
+    messages.push(entity(
+        "sensor",
+        "applied_offset",
+        "Applied Dew Point Offset",
+        json!({
+            "state_topic": format!("{prefix}/parameters"),
+            "value_template": "{{ value_json.applied_offset }}",
+            "device_class": "temperature",
+            "unit_of_measurement": "°C",
+        }),
+    ));
+    messages.push(entity(
+        "switch",
+        "enabled",
+        "TEC",
+        json!({
+            "state_topic": format!("{prefix}/status"),
+            "value_template": "{{ 'ON' if value_json.enabled else 'OFF' }}",
+            "command_topic": format!("{prefix}/set/enabled"),
+        }),
+    ));
+    for (field, name, min, max, step) in [
+        ("offset", "Dew Point Offset", -10.0, 30.0, 0.5),
+        ("p", "P Coefficient", 0.0, 1000.0, 1.0),
+        ("i", "I Coefficient", 0.0, 1000.0, 0.1),
+        ("d", "D Coefficient", 0.0, 1000.0, 0.1),
+    ] {
+        messages.push(entity(
+            "number",
+            field,
+            name,
+            json!({
+                "state_topic": format!("{prefix}/parameters"),
+                "value_template": format!("{{{{ value_json.{field} }}}}"),
+                "command_topic": format!("{prefix}/set/{field}"),
+                "min": min,
+                "max": max,
+                "step": step,
+                "mode": "box",
+            }),
+        ));
+    }
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands() {
+        assert_eq!(
+            parse_command("cooler", "cooler/set/enabled", b"ON"),
+            Some(Command::Enable(true))
+        );
+        assert_eq!(
+            parse_command("cooler", "cooler/set/offset", b" 3.5\n"),
+            Some(Command::Offset(3.5))
+        );
+        assert_eq!(
+            parse_command("cooler", "cooler/set/p", b"80"),
+            Some(Command::P(80.0))
+        );
+        assert_eq!(parse_command("cooler", "cooler/set/offset", b"warm"), None);
+        assert_eq!(parse_command("cooler", "cooler/set/power", b"1"), None);
+        assert_eq!(parse_command("cooler", "other/set/p", b"1"), None);
+    }
+
+    #[test]
+    fn discovery() {
+        let messages = discovery_messages("cooler", "homeassistant", "node");
+        let (topic, config) = messages
+            .iter()
+            .find(|(topic, _)| topic.contains("/switch/"))
+            .cloned()
+            .unwrap_or_default();
+        assert_eq!(topic, "homeassistant/switch/node/enabled/config");
+        assert_eq!(config["command_topic"], "cooler/set/enabled");
+        assert_eq!(config["unique_id"], "node_enabled");
+        assert_eq!(config["availability_topic"], "cooler/availability");
+        assert_eq!(
+            messages
+                .iter()
+                .filter(|(topic, _)| topic.contains("/sensor/"))
+                .count(),
+            8
+        );
+        assert!(messages.iter().any(|(topic, config)| topic
+            == "homeassistant/number/node/offset/config"
+            && config["command_topic"] == "cooler/set/offset"));
+    }
+}
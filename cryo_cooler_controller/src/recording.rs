@@ -0,0 +1,194 @@
+//! Logs recorded earlier, opened without a connected cooler.
+
+use std::io::Read;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use cryo_cooler_controller_lib::{MonitoringData, StatusEvent, StatusWord};
+
+use crate::controller::UserAction;
+use crate::csv_log;
+use crate::history::{HistoryDb, HistoryPoint, Quantity};
+
+const SQLITE_MAGIC: &[u8] = b"SQLite format 3\0";
+
+pub enum Recording {
+    /// Samples of a CSV log, oldest first.
+    Csv(Vec<(MonitoringData, StatusWord)>),
+    History(HistoryDb),
+}
+
+fn is_sqlite(path: &Path) -> std::io::Result<bool> {
+    let mut magic = [0; SQLITE_MAGIC.len()];
+    let mut file = std::fs::File::open(path)?;
+    Ok(file.read_exact(&mut magic).is_ok() && magic == SQLITE_MAGIC)
+}
+
+/// Combines the samples between `from` and `to` to at most `max_points` buckets of equal length.
+fn decimate(
+    samples: &[(MonitoringData, StatusWord)],
+    quantity: Quantity,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    max_points: usize,
+) -> Vec<HistoryPoint> {
+    let start = samples.partition_point(|(data, _)| data.timestamp < from);
+    let end = samples.partition_point(|(data, _)| data.timestamp <= to);
+    let samples = &samples[start..end];
+    if samples.len() <= max_points {
+        return samples
+            .iter()
+            .map(|(data, _)| {
+                let value = quantity.value(data);
+                HistoryPoint {
+                    timestamp: data.timestamp,
+                    min: value,
+                    avg: value,
+                    max: value,
+                }
+            })
+            .collect();
+    }
+    let bucket_ms = ((to - from).num_milliseconds() / max_points as i64).max(1);
+    let mut points: Vec<HistoryPoint> = Vec::with_capacity(max_points);
+    let mut count = 0;
+    for (data, _) in samples {
+        let value = quantity.value(data);
+        let offset = (data.timestamp - from).num_milliseconds() / bucket_ms * bucket_ms;
+        let timestamp = from + chrono::Duration::milliseconds(offset);
+        match points.last_mut() {
+            Some(point) if point.timestamp == timestamp => {
+                count += 1;
+                point.min = point.min.min(value);
+                point.max = point.max.max(value);
+                point.avg += (value - point.avg) / count as f32;
+            }
+            _ => {
+                count = 1;
+                points.push(HistoryPoint {
+                    timestamp,
+                    min: value,
+                    avg: value,
+                    max: value,
+                });
+            }
+        }
+    }
+    points
+}
+
+impl Recording {
+    /// Opens a history database or a CSV log, detected by the content.
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        if is_sqlite(path)? {
+            HistoryDb::open_read_only(path).map(Recording::History)
+        } else {
+            let mut samples = csv_log::read(path)?;
+            samples.sort_by_key(|(data, _)| data.timestamp);
+            Ok(Recording::Csv(samples))
+        }
+    }
+
+    /// Time of the oldest and the newest value, `None` if the recording is empty.
+    pub fn span(&self) -> std::io::Result<Option<(DateTime<Utc>, DateTime<Utc>)>> {
+        match self {
+            Recording::Csv(samples) => Ok(samples
+                .first()
+                .zip(samples.last())
+                .map(|(first, last)| (first.0.timestamp, last.0.timestamp))),
+            Recording::History(history) => history.span(),
+        }
+    }
+
+    pub fn query(
+        &self,
+        quantity: Quantity,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        max_points: usize,
+    ) -> std::io::Result<Vec<HistoryPoint>> {
+        match self {
+            Recording::Csv(samples) => Ok(decimate(samples, quantity, from, to, max_points)),
+            Recording::History(history) => history.query(quantity, from, to, max_points),
+        }
+    }
+
+    /// Status flag changes, oldest first.
+    pub fn status_events(&self) -> std::io::Result<Vec<StatusEvent>> {
+        match self {
+            Recording::Csv(samples) => Ok(samples
+                .windows(2)
+                .flat_map(|pair| pair[0].1.diff(pair[1].1, pair[1].0.timestamp))
+                .collect()),
+            Recording::History(history) => history.status_events(),
+        }
+    }
+
+    /// User actions, oldest first, CSV logs do not contain them.
+    pub fn actions(&self) -> std::io::Result<Vec<UserAction>> {
+        match self {
+            Recording::Csv(_) => Ok(Vec::new()),
+            Recording::History(history) => history.actions(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn sample(second: u32, tec_temperature: f32, status_word: u32) -> (MonitoringData, StatusWord) {
+        (
+            MonitoringData {
+                timestamp: Utc.with_ymd_and_hms(2024, 3, 10, 12, 0, second).unwrap(),
+                tec_temperature,
+                pcb_temperature: 30.0,
+                humidity: 45.0,
+                dew_point_temperature: 9.0,
+                tec_voltage: 12.0,
+                tec_current: 3.0,
+                tec_power_level: 40,
+            },
+            StatusWord::new(status_word),
+        )
+    }
+
+    #[test]
+    fn csv() {
+        let dir = tempdir::TempDir::new("recording").unwrap();
+        let path = dir.path().join("log.csv");
+        let samples: Vec<_> = (0..40)
+            .map(|second| sample(second, second as f32, if second == 5 { 1 << 13 } else { 0 }))
+            .collect();
+        let mut content = format!("{}\n", csv_log::HEADER);
+        for (data, word) in &samples {
+            content += &format!("{}\n", csv_log::format_line(data, *word));
+        }
+        // Cut off like after a power loss.
+        content += "2024-03-10T12:00:40+00:00,1";
+        std::fs::write(&path, content).unwrap();
+
+        let recording = Recording::open(&path).unwrap();
+        let (from, to) = recording.span().unwrap().unwrap();
+        assert_eq!(from, samples[0].0.timestamp);
+        assert_eq!(to, samples[39].0.timestamp);
+        assert_eq!(recording.status_events().unwrap().len(), 2);
+
+        let all = recording
+            .query(Quantity::TecTemperature, from, to, 100)
+            .unwrap();
+        assert_eq!(all.len(), 40);
+        let to = from + chrono::Duration::seconds(40);
+        let buckets = recording
+            .query(Quantity::TecTemperature, from, to, 4)
+            .unwrap();
+        assert_eq!(buckets.len(), 4);
+        assert_eq!(buckets[1].timestamp, from + chrono::Duration::seconds(10));
+        assert_eq!(
+            (buckets[1].min, buckets[1].avg, buckets[1].max),
+            (10.0, 14.5, 19.0)
+        );
+    }
+}
@@ -1,29 +1,40 @@
-use std::time::Duration;
 use std::time::Instant;
 
+use chrono::Utc;
 use iced::{
     alignment,
-    widget::{horizontal_rule, horizontal_space, vertical_space, Column, Container, Row, Text},
+    widget::{horizontal_rule, horizontal_space, scrollable, Column, Container, Row, Text},
     Alignment, Command, Element, Length,
 };
 use iced_aw::NumberInput;
 
-use cryo_cooler_controller_lib::TecStatus;
+use cryo_cooler_controller_lib::{
+    DeviceParameters, OcpState, SelfTest, Severity, TecStatus, VerifyConfig, Watchdog,
+};
+
+use crate::controller::{CalibrationQuantity, Controller};
+use crate::services::Services;
+use crate::settings::{self, OcpResponse, RecoveryAction, SetpointMode};
+use crate::{
+    charts::{ChartGroup, ChartRange, ChartWindow},
+    Message,
+};
+
+/// Height of the event log, the left column scrolls as a whole.
+const EVENT_LOG_HEIGHT: f32 = 200.0;
 
-use crate::settings;
-use crate::{charts::ChartGroup, Message};
+struct CalibrationDialog {
+    quantity: CalibrationQuantity,
+    reading: f32,
+}
 
 pub struct RunningState {
-    last_sample_time: Instant,
-    tec: cryo_cooler_controller_lib::Tec,
-    tec_status: TecStatus,
-    firmware_version_major: u8,
-    firmware_version_minor: u8,
-    hardware_version: u32,
+    controller: Controller,
+    calibration_dialog: Option<CalibrationDialog>,
+    show_diagnostics: bool,
+    show_advanced: bool,
     chart: ChartGroup,
-    error_text: Option<String>,
-    update_intervall: Duration,
-    app_settings: settings::AppSettings,
+    services: Services,
 }
 
 impl RunningState {
@@ -34,119 +45,216 @@ impl RunningState {
     where
         T: AsRef<std::path::Path> + std::fmt::Debug,
     {
-        let mut tec = cryo_cooler_controller_lib::Tec::new(&serial_port.as_ref().as_os_str())?;
-        let fw_version = tec.fw_version()?;
-        let firmware_version_major = fw_version.0;
-        let firmware_version_minor = fw_version.1;
-        let hardware_version = tec.hw_version()?;
-        let tec_status = tec.heart_beat()?;
-        let mut error_text = None;
-        if app_settings.get_enable_on_startup(){
-            if let Err(err) = tec.enable(
-                app_settings.get_p_coef(),
-                app_settings.get_i_coef(),
-                app_settings.get_d_coef(),
-                app_settings.get_max_power(),
-                app_settings.get_set_point(),
-            ) {
-                error_text = Some(format!("Failed to enable TEC ({err})"));
-            }
-        }
-        Ok(RunningState {
-            last_sample_time: Instant::now(),
-            tec,
-            tec_status,
-            firmware_version_major,
-            firmware_version_minor,
-            hardware_version,
+        let mut running_state = RunningState {
+            controller: Controller::new(serial_port, app_settings)?,
+            calibration_dialog: None,
+            show_diagnostics: false,
+            show_advanced: false,
             chart: Default::default(),
-            error_text,
-            update_intervall: Duration::from_millis(500),
-            app_settings,
-        })
-    }
-    #[inline]
-    pub fn should_update(&self) -> bool {
-        self.last_sample_time.elapsed() > self.update_intervall
+            services: Default::default(),
+        };
+        let settings = running_state.controller.settings();
+        let memory = settings.get_chart_memory();
+        let window = chrono::Duration::seconds(settings.get_chart_window_secs() as i64);
+        running_state.chart.set_memory_limit(memory);
+        running_state.chart.set_window(window);
+        running_state.services.sync(&mut running_state.controller);
+        Ok(running_state)
     }
 
     pub fn update(&mut self, message: Message) -> Command<Message> {
+        let controller = &mut self.controller;
+        // Failed actions are shown from `Controller::error_text`, their results are not needed.
         match message {
             Message::Tick => {
-                if !self.should_update() {
-                    return Command::none();
-                }
-
-                match self.tec.heart_beat() {
-                    Ok(status) => self.tec_status = status,
-                    Err(err) => {
-                        if let Err(e) = self.tec.reset_connection() {
-                            self.error_text =
-                                Some(format!("Failed to communicate with coooler ({e:?})"));
-                            return Command::none();
-                        } else {
-                            self.error_text =
-                                Some(format!("Failed to communicate with coooler ({err:?})"))
-                        }
-                    }
+                self.services.poll(controller);
+                if let Some(data) = controller.tick(Instant::now()) {
+                    self.services.publish(controller, &data);
+                    self.chart.update(data);
                 }
-                self.last_sample_time = Instant::now();
-                match self.tec.monitor() {
-                    Ok(data) => self.chart.update(data),
-                    Err(err) => {
-                        self.error_text = Some(format!("Failed to get data from coooler ({err})"));
-                    }
+                if self.chart.needs_history() {
+                    self.load_history();
                 }
             }
             Message::Enable => {
-                if let Err(err) = self.tec.enable(
-                    self.app_settings.get_p_coef(),
-                    self.app_settings.get_i_coef(),
-                    self.app_settings.get_d_coef(),
-                    self.app_settings.get_max_power(),
-                    self.app_settings.get_set_point(),
-                ) {
-                    self.error_text = Some(format!("Failed to enable TEC ({err})"));
-                }
+                let _ = controller.enable();
             }
             Message::Disable => {
-                if let Err(err) = self.tec.disable() {
-                    self.error_text = Some(format!("Failed to disable TEC ({err})"));
-                }
+                let _ = controller.disable();
+            }
+            Message::UpdatePCoef(input) => controller.edit_parameters(|s| s.set_p_coef(input)),
+            Message::UpdateICoef(input) => controller.edit_parameters(|s| s.set_i_coef(input)),
+            Message::UpdateDCoef(input) => controller.edit_parameters(|s| s.set_d_coef(input)),
+            Message::UpdateSetpoint(input) => {
+                controller.edit_parameters(|s| s.set_set_point(input))
+            }
+            Message::SetpointModeSelected(mode) => {
+                controller.edit_parameters(|s| s.set_setpoint_mode(mode))
+            }
+            Message::UpdateAbsoluteTarget(input) => {
+                controller.edit_parameters(|s| s.set_absolute_target(input))
+            }
+            Message::UpdateSafetyMargin(input) => {
+                controller.edit_parameters(|s| s.set_safety_margin(input))
+            }
+            Message::UpdateMaxPower(input) => {
+                let _ = controller.update_settings(|s| s.set_max_power(input));
+            }
+            Message::CloseModal => {
+                controller.take_error();
+            }
+            Message::ApplyStartupCheckboxToggled(checked) => {
+                let _ = controller.update_settings(|s| s.set_enable_on_startup(checked));
+            }
+            Message::UpdateHeartbeatInterval(input) => {
+                let _ = controller.update_settings(|s| s.set_heartbeat_interval_ms(input));
+            }
+            Message::UpdateWatchdogTimeout(input) => {
+                let _ = controller.update_settings(|s| s.set_watchdog_timeout_ms(input));
+            }
+            Message::DiagnosticsToggled(checked) => {
+                self.show_diagnostics = checked;
+            }
+            Message::AdvancedToggled(checked) => {
+                self.show_advanced = checked;
+            }
+            Message::OpenCalibration => {
+                self.calibration_dialog = Some(CalibrationDialog {
+                    quantity: CalibrationQuantity::Voltage,
+                    reading: 0.0,
+                });
             }
-            Message::UpdatePCoef(input) => {
-                if let Err(e) = self.app_settings.set_p_coef(input) {
-                    self.error_text = Some(format!("Failed to save settings ({e})"));
+            Message::CloseCalibration => {
+                self.calibration_dialog = None;
+            }
+            Message::CalibrationQuantitySelected(quantity) => {
+                if let Some(dialog) = &mut self.calibration_dialog {
+                    dialog.quantity = quantity;
                 }
             }
-            Message::UpdateICoef(input) => {
-                if let Err(e) = self.app_settings.set_i_coef(input) {
-                    self.error_text = Some(format!("Failed to save settings ({e})"));
+            Message::UpdateCalibrationReading(input) => {
+                if let Some(dialog) = &mut self.calibration_dialog {
+                    dialog.reading = input;
                 }
             }
-            Message::UpdateDCoef(input) => {
-                if let Err(e) = self.app_settings.set_d_coef(input) {
-                    self.error_text = Some(format!("Failed to save settings ({e})"));
+            Message::UpdateCalibrationOffset(input) => {
+                if let Some(dialog) = &self.calibration_dialog {
+                    controller.set_calibration_offset(dialog.quantity, input);
                 }
             }
-            Message::UpdateSetpoint(input) => {
-                if let Err(e) = self.app_settings.set_set_point(input) {
-                    self.error_text = Some(format!("Failed to save settings ({e})"));
+            Message::ApplyCalibration => {
+                if let Some(dialog) = &self.calibration_dialog {
+                    if controller.calibrate(dialog.quantity, dialog.reading) {
+                        self.calibration_dialog = None;
+                    }
                 }
             }
-            Message::UpdateMaxPower(input) => {
-                if let Err(e) = self.app_settings.set_max_power(input) {
-                    self.error_text = Some(format!("Failed to save settings ({e})"));
+            Message::ResetCalibration => controller.reset_calibration(),
+            Message::ApplyParameters => {
+                let _ = controller.apply_parameters();
+            }
+            Message::LoadParametersFromDevice => controller.load_parameters_from_device(),
+            Message::LiveApplyToggled(checked) => {
+                let _ = controller.update_settings(|s| s.set_live_apply(checked));
+            }
+            Message::UndoLiveApply => controller.undo_live_apply(),
+            Message::StartSelfTest => controller.start_self_test(),
+            Message::AbortSelfTest => controller.abort_self_test(),
+            Message::SaveSelfTestReport => controller.save_self_test_report(),
+            Message::CloseSelfTest => controller.close_self_test(),
+            Message::UpdateOcpDebounce(input) => {
+                let _ = controller.update_settings(|s| s.set_ocp_debounce_ms(input));
+            }
+            Message::UpdateOcpThreshold(input) => {
+                let _ = controller.update_settings(|s| s.set_ocp_current_threshold(input));
+            }
+            Message::UpdateOcpResponse(response) => {
+                let _ = controller.update_settings(|s| s.set_ocp_response(response));
+            }
+            Message::UpdateDewPointTolerance(input) => {
+                let _ = controller.update_settings(|s| s.set_dew_point_tolerance(input));
+            }
+            Message::UseHostDewPointToggled(checked) => {
+                let _ = controller.update_settings(|s| s.set_use_host_dew_point(checked));
+            }
+            Message::VerifyWritesToggled(checked) => {
+                let _ = controller.update_settings(|s| s.set_verify_writes(checked));
+            }
+            Message::HttpApiToggled(checked) => {
+                let _ = controller.update_settings(|s| s.set_http_api_enabled(checked));
+                self.services.sync(controller);
+            }
+            Message::MetricsToggled(checked) => {
+                let _ = controller.update_settings(|s| s.set_metrics_enabled(checked));
+                self.services.sync(controller);
+            }
+            Message::MqttToggled(checked) => {
+                let _ = controller.update_settings(|s| s.set_mqtt_enabled(checked));
+                self.services.sync(controller);
+            }
+            Message::ScriptingToggled(checked) => {
+                let _ = controller.update_settings(|s| s.set_scripting_enabled(checked));
+                self.services.sync(controller);
+            }
+            Message::ReloadScripts => self.services.reload_scripts(controller),
+            Message::LoggingToggled(enabled) => {
+                let _ = controller.update_settings(|s| s.set_csv_log_enabled(enabled));
+                self.services.sync(controller);
+            }
+            Message::HistoryToggled(checked) => {
+                let _ = controller.update_settings(|s| s.set_history_enabled(checked));
+                self.services.sync(controller);
+                if !checked {
+                    self.chart.set_range(ChartRange::Live);
                 }
             }
-            Message::CloseModal => {
-                self.error_text = None;
+            Message::ChartRangeSelected(range) => {
+                self.chart.set_range(range);
+                self.load_history();
             }
-            Message::ApplyStartupCheckboxToggled(checked) => {
-                if let Err(e) = self.app_settings.set_enable_on_startup(checked) {
-                    self.error_text = Some(format!("Failed to save settings ({e})"));
+            Message::ChartWindowSelected(window) => {
+                let _ = controller.update_settings(|s| s.set_chart_window_secs(window.secs()));
+                self.chart
+                    .set_window(chrono::Duration::seconds(window.secs() as i64));
+            }
+            // History ranges are reloaded on a tick once zooming or panning stopped.
+            Message::ChartZoom(factor, anchor) => self.chart.zoom(factor, anchor),
+            Message::ChartPan(fraction) => self.chart.pan(fraction),
+            Message::FollowLiveToggled(checked) => {
+                self.chart.set_following(checked);
+                self.load_history();
+            }
+            Message::ExportTargetSelected(target) => self.chart.set_export_target(target),
+            Message::ExportCharts(format) => {
+                let header = export_header(controller);
+                match self
+                    .chart
+                    .export(&controller.settings().get_export_dir(), format, &header)
+                {
+                    Ok(path) => controller.event_log_mut().push(
+                        Utc::now(),
+                        Severity::Info,
+                        format!("Exported charts to {}", path.display()),
+                    ),
+                    Err(e) => controller.set_error(format!("Failed to export charts ({e})")),
                 }
             }
+            Message::DbusServiceToggled(checked) => {
+                let _ = controller.update_settings(|s| s.set_dbus_service(checked));
+                self.services.sync(controller);
+            }
+            Message::ControlSocketToggled(checked) => {
+                let _ = controller.update_settings(|s| s.set_control_socket(checked));
+                self.services.sync(controller);
+            }
+            Message::UpdateOcpSetpointStep(input) => {
+                let _ = controller.update_settings(|s| s.set_ocp_setpoint_step(input));
+            }
+            Message::UpdateRecoveryAction(action) => {
+                let _ = controller.update_settings(|s| s.set_recovery_action(action));
+            }
+            Message::RecoveryReEnable => controller.answer_recovery(true),
+            Message::RecoveryKeepDisabled => controller.answer_recovery(false),
             _ => {}
         }
         Command::none()
@@ -177,7 +285,11 @@ impl RunningState {
             .width(Length::Fixed(110.0))
         };
 
-        let en_button = if !self.tec_status.contains(TecStatus::LOW_POWER_MODE_ACTIVE) {
+        let en_button = if !self
+            .controller
+            .tec_status()
+            .contains(TecStatus::LOW_POWER_MODE_ACTIVE)
+        {
             button("Disable TEC")
                 .style(iced::theme::Button::Secondary)
                 .on_press(Message::Disable)
@@ -187,6 +299,16 @@ impl RunningState {
                 .on_press(Message::Enable)
         };
 
+        let logging = self.controller.settings().get_csv_log_enabled();
+        let log_button = button(if logging {
+            "Stop Logging"
+        } else {
+            "Start Logging"
+        })
+        .style(iced::theme::Button::Secondary)
+        .on_press(Message::LoggingToggled(!logging))
+        .width(Length::Fixed(150.0));
+
         let hide_button = button("Hide Window")
             .style(iced::theme::Button::Primary)
             .on_press(Message::Hide)
@@ -195,6 +317,7 @@ impl RunningState {
         let content = Column::new()
             .spacing(5)
             .width(Length::Fixed(280.0))
+            .padding([0, 12, 0, 0])
             .push(
                 Row::new()
                     .push(
@@ -203,7 +326,8 @@ impl RunningState {
                                 Row::new().push(
                                     Text::new(format!(
                                         "Firmware Version: {:X}.{:X}",
-                                        self.firmware_version_major, self.firmware_version_minor
+                                        self.controller.firmware_version().0,
+                                        self.controller.firmware_version().1
                                     ))
                                     .size(28),
                                 ),
@@ -212,7 +336,7 @@ impl RunningState {
                                 Row::new().push(
                                     Text::new(format!(
                                         "Hardware Version: {}",
-                                        self.hardware_version
+                                        self.controller.hardware_version()
                                     ))
                                     .size(28),
                                 ),
@@ -221,30 +345,22 @@ impl RunningState {
                     .padding(15),
             )
             .push(horizontal_rule(20))
-            .push(
-                Row::new()
-                    .push(Text::new("Offset"))
-                    .push(horizontal_space(Length::Fill))
-                    .push(
-                        NumberInput::new(
-                            self.app_settings.get_set_point(),
-                            50.0,
-                            Message::UpdateSetpoint,
-                        )
-                        .style(iced_aw::style::NumberInputStyles::Default)
-                        .step(1.0)
-                        .min(-50.0),
-                    )
-                    .padding(5)
-                    .spacing(5),
-            )
+            .push(labeled_row(
+                "Mode",
+                iced::widget::pick_list(
+                    SetpointMode::ALL,
+                    Some(self.controller.settings().get_setpoint_mode()),
+                    Message::SetpointModeSelected,
+                ),
+            ))
+            .push(self.view_setpoint_inputs())
             .push(
                 Row::new()
                     .push(Text::new("Max. Power"))
                     .push(horizontal_space(Length::Fill))
                     .push(
                         NumberInput::new(
-                            self.app_settings.get_max_power(),
+                            self.controller.settings().get_max_power(),
                             100,
                             Message::UpdateMaxPower,
                         )
@@ -269,7 +385,7 @@ impl RunningState {
                     .push(horizontal_space(Length::Fill))
                     .push(
                         NumberInput::new(
-                            self.app_settings.get_p_coef(),
+                            self.controller.settings().get_p_coef(),
                             1000.0,
                             Message::UpdatePCoef,
                         )
@@ -286,7 +402,7 @@ impl RunningState {
                     .push(horizontal_space(Length::Fill))
                     .push(
                         NumberInput::new(
-                            self.app_settings.get_i_coef(),
+                            self.controller.settings().get_i_coef(),
                             1000.0,
                             Message::UpdateICoef,
                         )
@@ -303,7 +419,7 @@ impl RunningState {
                     .push(horizontal_space(Length::Fill))
                     .push(
                         NumberInput::new(
-                            self.app_settings.get_d_coef(),
+                            self.controller.settings().get_d_coef(),
                             1000.0,
                             Message::UpdateDCoef,
                         )
@@ -314,17 +430,32 @@ impl RunningState {
                     .padding(5)
                     .spacing(5),
             )
+            .push(self.view_device_parameters())
             .push(horizontal_rule(20))
-            .push(view_badges(&self.tec_status))
-            .push(vertical_space(Length::Fill))
+            .push(view_badges(
+                self.controller.tec_status(),
+                self.controller.status_word().unknown_bits(),
+                self.controller.watchdog(),
+                self.controller.ocp_state(),
+                self.controller.sensor_disagreement(),
+            ))
+            .push(self.view_diagnostics())
+            .push(horizontal_rule(20))
+            .push(
+                self.controller
+                    .event_log()
+                    .view(Length::Fixed(EVENT_LOG_HEIGHT)),
+            )
             .push(horizontal_rule(20))
+            .push(self.view_advanced_settings())
             .push(
                 Column::new()
                     .push(iced::widget::checkbox(
                         "Enable TEC on Startup",
-                        self.app_settings.get_enable_on_startup(),
+                        self.controller.settings().get_enable_on_startup(),
                         Message::ApplyStartupCheckboxToggled,
                     ))
+                    .push(log_button)
                     .push(hide_button)
                     .padding(15)
                     .spacing(15)
@@ -332,12 +463,95 @@ impl RunningState {
                     .width(Length::Fill),
             );
 
+        // The settings do not fit the window height, especially with the advanced settings shown.
+        let content = iced_aw::Modal::new(
+            self.calibration_dialog.is_some(),
+            scrollable(content).height(Length::Fill),
+            iced_aw::Card::new(Text::new("Calibration"), self.view_calibration_dialog())
+                .foot(
+                    Row::new()
+                        .padding(5)
+                        .spacing(10)
+                        .width(Length::Fill)
+                        .push(
+                            iced::widget::Button::new(
+                                Text::new("Apply")
+                                    .horizontal_alignment(alignment::Horizontal::Center),
+                            )
+                            .width(Length::Fixed(80.0))
+                            .on_press(Message::ApplyCalibration),
+                        )
+                        .push(
+                            iced::widget::Button::new(
+                                Text::new("Reset")
+                                    .horizontal_alignment(alignment::Horizontal::Center),
+                            )
+                            .width(Length::Fixed(80.0))
+                            .style(iced::theme::Button::Secondary)
+                            .on_press(Message::ResetCalibration),
+                        )
+                        .push(
+                            iced::widget::Button::new(
+                                Text::new("Close")
+                                    .horizontal_alignment(alignment::Horizontal::Center),
+                            )
+                            .width(Length::Fixed(80.0))
+                            .style(iced::theme::Button::Secondary)
+                            .on_press(Message::CloseCalibration),
+                        ),
+                )
+                .max_width(360.0)
+                .on_close(Message::CloseCalibration),
+        );
+
+        let content = iced_aw::Modal::new(
+            self.controller.self_test().is_some(),
+            content,
+            iced_aw::Card::new(Text::new("Self-Test"), self.view_self_test())
+                .foot(self.view_self_test_buttons())
+                .max_width(420.0),
+        );
+
+        let content = iced_aw::Modal::new(
+            self.controller.recovery_prompt(),
+            content,
+            iced_aw::Card::new(
+                Text::new("Cooler Recovered"),
+                Text::new(
+                    "The cooler recovered from a fault. Do you want to enable the TEC again?",
+                ),
+            )
+            .foot(
+                Row::new()
+                    .padding(5)
+                    .spacing(10)
+                    .width(Length::Fill)
+                    .push(
+                        iced::widget::Button::new(
+                            Text::new("Enable").horizontal_alignment(alignment::Horizontal::Center),
+                        )
+                        .width(Length::Fixed(100.0))
+                        .on_press(Message::RecoveryReEnable),
+                    )
+                    .push(
+                        iced::widget::Button::new(
+                            Text::new("Keep Disabled")
+                                .horizontal_alignment(alignment::Horizontal::Center),
+                        )
+                        .width(Length::Fixed(130.0))
+                        .style(iced::theme::Button::Secondary)
+                        .on_press(Message::RecoveryKeepDisabled),
+                    ),
+            )
+            .max_width(300.0),
+        );
+
         iced_aw::Modal::new(
-            self.error_text.is_some(),
+            self.controller.error_text().is_some(),
             content,
             iced_aw::Card::new(
                 Text::new("Error"),
-                Text::new(self.error_text.clone().unwrap_or_else(|| "".to_owned())),
+                Text::new(self.controller.error_text().unwrap_or_default()),
             )
             .foot(
                 Column::new().padding(5).width(Length::Fill).push(
@@ -356,21 +570,660 @@ impl RunningState {
         .into()
     }
 
-    pub fn view_right_column(&self) -> Element<'_, Message> {
+    pub fn view_setpoint_inputs(&self) -> Element<'_, Message> {
+        let col = Column::new().spacing(5).width(Length::Fill);
+        let col = match self.controller.settings().get_setpoint_mode() {
+            SetpointMode::Offset => col.push(
+                Row::new()
+                    .push(Text::new("Offset"))
+                    .push(horizontal_space(Length::Fill))
+                    .push(
+                        NumberInput::new(
+                            self.controller.settings().get_set_point(),
+                            50.0,
+                            Message::UpdateSetpoint,
+                        )
+                        .style(iced_aw::style::NumberInputStyles::Default)
+                        .step(1.0)
+                        .min(-50.0),
+                    )
+                    .padding(5)
+                    .spacing(5),
+            ),
+            SetpointMode::Absolute => col
+                .push(labeled_row(
+                    "Target (C)",
+                    NumberInput::new(
+                        self.controller.settings().get_absolute_target(),
+                        50.0,
+                        Message::UpdateAbsoluteTarget,
+                    )
+                    .style(iced_aw::style::NumberInputStyles::Default)
+                    .step(1.0)
+                    .min(-20.0),
+                ))
+                .push(labeled_row(
+                    "Safety Margin",
+                    NumberInput::new(
+                        self.controller.settings().get_safety_margin(),
+                        20.0,
+                        Message::UpdateSafetyMargin,
+                    )
+                    .style(iced_aw::style::NumberInputStyles::Default)
+                    .step(0.5)
+                    .min(0.0),
+                )),
+        };
+        match self.controller.sent_offset() {
+            Some(offset)
+                if !self
+                    .controller
+                    .tec_status()
+                    .contains(TecStatus::LOW_POWER_MODE_ACTIVE) =>
+            {
+                col.push(Text::new(format!("Applied Offset: {offset:.1} C")).size(16))
+                    .into()
+            }
+            _ => col.into(),
+        }
+    }
+
+    pub fn view_advanced_settings(&self) -> Element<'_, Message> {
+        let mut col = Column::new()
+            .spacing(5)
+            .width(Length::Fill)
+            .push(iced::widget::checkbox(
+                "Show Advanced Settings",
+                self.show_advanced,
+                Message::AdvancedToggled,
+            ));
+        if !self.show_advanced {
+            return col.into();
+        }
+
+        col = col
+            .push(labeled_row(
+                "Heartbeat (ms)",
+                NumberInput::new(
+                    self.controller.settings().get_heartbeat_interval_ms(),
+                    10_000,
+                    Message::UpdateHeartbeatInterval,
+                )
+                .style(iced_aw::style::NumberInputStyles::Default)
+                .step(100)
+                .min(100),
+            ))
+            .push(labeled_row(
+                "Watchdog (ms)",
+                NumberInput::new(
+                    self.controller.settings().get_watchdog_timeout_ms(),
+                    60_000,
+                    Message::UpdateWatchdogTimeout,
+                )
+                .style(iced_aw::style::NumberInputStyles::Default)
+                .step(500)
+                .min(1000),
+            ))
+            .push(labeled_row(
+                "On Recovery",
+                iced::widget::pick_list(
+                    RecoveryAction::ALL,
+                    Some(self.controller.settings().get_recovery_action()),
+                    Message::UpdateRecoveryAction,
+                ),
+            ))
+            .push(labeled_row(
+                "OCP Debounce (ms)",
+                NumberInput::new(
+                    self.controller.settings().get_ocp_debounce_ms(),
+                    60_000,
+                    Message::UpdateOcpDebounce,
+                )
+                .style(iced_aw::style::NumberInputStyles::Default)
+                .step(500)
+                .min(0),
+            ))
+            .push(labeled_row(
+                "OCP Current (A)",
+                NumberInput::new(
+                    self.controller.settings().get_ocp_current_threshold(),
+                    50.0,
+                    Message::UpdateOcpThreshold,
+                )
+                .style(iced_aw::style::NumberInputStyles::Default)
+                .step(0.5)
+                .min(0.0),
+            ))
+            .push(labeled_row(
+                "On Over Current",
+                iced::widget::pick_list(
+                    OcpResponse::ALL,
+                    Some(self.controller.settings().get_ocp_response()),
+                    Message::UpdateOcpResponse,
+                ),
+            ))
+            .push(labeled_row(
+                "Setpoint Step",
+                NumberInput::new(
+                    self.controller.settings().get_ocp_setpoint_step(),
+                    20.0,
+                    Message::UpdateOcpSetpointStep,
+                )
+                .style(iced_aw::style::NumberInputStyles::Default)
+                .step(0.5)
+                .min(0.0),
+            ))
+            .push(labeled_row(
+                "Dew Point Tolerance",
+                NumberInput::new(
+                    self.controller.settings().get_dew_point_tolerance(),
+                    20.0,
+                    Message::UpdateDewPointTolerance,
+                )
+                .style(iced_aw::style::NumberInputStyles::Default)
+                .step(0.5)
+                .min(0.0),
+            ))
+            .push(
+                Row::new()
+                    .push(iced::widget::checkbox(
+                        "Use Host Dew Point",
+                        self.controller.settings().get_use_host_dew_point(),
+                        Message::UseHostDewPointToggled,
+                    ))
+                    .padding(5),
+            )
+            .push(
+                Row::new()
+                    .push(iced::widget::checkbox(
+                        "Verify Writes",
+                        self.controller.settings().get_verify_writes(),
+                        Message::VerifyWritesToggled,
+                    ))
+                    .padding(5),
+            );
+        if cfg!(unix) {
+            col = col.push(
+                Row::new()
+                    .push(iced::widget::checkbox(
+                        "Control Socket",
+                        self.controller.settings().get_control_socket(),
+                        Message::ControlSocketToggled,
+                    ))
+                    .padding(5),
+            );
+        }
+        col = col.push(
+            Row::new()
+                .push(iced::widget::checkbox(
+                    format!(
+                        "HTTP API ({})",
+                        self.controller.settings().get_http_api_address()
+                    ),
+                    self.controller.settings().get_http_api_enabled(),
+                    Message::HttpApiToggled,
+                ))
+                .padding(5),
+        );
+        col = col.push(
+            Row::new()
+                .push(iced::widget::checkbox(
+                    format!(
+                        "Prometheus Metrics ({})",
+                        self.controller.settings().get_metrics_address()
+                    ),
+                    self.controller.settings().get_metrics_enabled(),
+                    Message::MetricsToggled,
+                ))
+                .padding(5),
+        );
+        col = col.push(
+            Row::new()
+                .push(iced::widget::checkbox(
+                    "MQTT",
+                    self.controller.settings().get_mqtt_enabled(),
+                    Message::MqttToggled,
+                ))
+                .padding(5),
+        );
+        if cfg!(target_os = "linux") {
+            col = col.push(
+                Row::new()
+                    .push(iced::widget::checkbox(
+                        "D-Bus Service",
+                        self.controller.settings().get_dbus_service(),
+                        Message::DbusServiceToggled,
+                    ))
+                    .padding(5),
+            );
+        }
+        let scripting = self.controller.settings().get_scripting_enabled();
+        let mut scripting_row = Row::new()
+            .push(iced::widget::checkbox(
+                "Scripts",
+                scripting,
+                Message::ScriptingToggled,
+            ))
+            .push(horizontal_space(Length::Fill))
+            .padding(5);
+        if scripting {
+            scripting_row = scripting_row.push(
+                iced::widget::button(Text::new("Reload"))
+                    .on_press(Message::ReloadScripts)
+                    .style(iced::theme::Button::Secondary),
+            );
+        }
+        col = col.push(scripting_row);
+        col = col.push(
+            Row::new()
+                .push(iced::widget::checkbox(
+                    "History Database",
+                    self.controller.settings().get_history_enabled(),
+                    Message::HistoryToggled,
+                ))
+                .padding(5),
+        );
+        let mut self_test_button = iced::widget::button(
+            Text::new("Self-Test").horizontal_alignment(alignment::Horizontal::Center),
+        )
+        .padding(10)
+        .width(Length::Fixed(150.0))
+        .style(iced::theme::Button::Secondary);
+        // The self-test needs the TEC disabled.
+        if self
+            .controller
+            .tec_status()
+            .contains(TecStatus::LOW_POWER_MODE_ACTIVE)
+        {
+            self_test_button = self_test_button.on_press(Message::StartSelfTest);
+        }
+        col = col.push(
+            Column::new()
+                .push(
+                    iced::widget::button(
+                        Text::new("Calibrate").horizontal_alignment(alignment::Horizontal::Center),
+                    )
+                    .padding(10)
+                    .width(Length::Fixed(150.0))
+                    .style(iced::theme::Button::Secondary)
+                    .on_press(Message::OpenCalibration),
+                )
+                .push(self_test_button)
+                .spacing(10)
+                .padding(5)
+                .align_items(Alignment::Center)
+                .width(Length::Fill),
+        );
+        col.into()
+    }
+
+    fn view_calibration_dialog(&self) -> Element<'_, Message> {
+        let Some(dialog) = &self.calibration_dialog else {
+            return Text::new("").into();
+        };
+        let calibration = self.controller.calibration();
+        let (factor, offset, value) = match dialog.quantity {
+            CalibrationQuantity::Voltage => (
+                calibration.voltage_factor,
+                calibration.voltage_offset,
+                self.controller.last_data().map(|data| data.tec_voltage),
+            ),
+            CalibrationQuantity::Current => (
+                calibration.current_factor,
+                calibration.current_offset,
+                self.controller.last_data().map(|data| data.tec_current),
+            ),
+        };
+        let unit = dialog.quantity.unit();
+
         Column::new()
+            .spacing(5)
+            .push(labeled_row(
+                "Quantity",
+                iced::widget::pick_list(
+                    CalibrationQuantity::ALL,
+                    Some(dialog.quantity),
+                    Message::CalibrationQuantitySelected,
+                ),
+            ))
+            .push(Text::new(match value {
+                Some(value) => format!("Cooler Reading: {value:.3} {unit}"),
+                None => "Cooler Reading: -".to_owned(),
+            }))
+            .push(Text::new(format!("Factor: {factor:.4}")))
+            .push(labeled_row(
+                "Offset",
+                NumberInput::new(offset, 100.0, Message::UpdateCalibrationOffset)
+                    .style(iced_aw::style::NumberInputStyles::Default)
+                    .step(0.01)
+                    .min(-100.0),
+            ))
+            .push(labeled_row(
+                "Multimeter Reading",
+                NumberInput::new(dialog.reading, 100.0, Message::UpdateCalibrationReading)
+                    .style(iced_aw::style::NumberInputStyles::Default)
+                    .step(0.01)
+                    .min(0.0),
+            ))
+            .push(Text::new(
+                "Measure the value with a multimeter while the TEC is running and enter it above.",
+            ))
+            .into()
+    }
+
+    fn view_device_parameters(&self) -> Element<'_, Message> {
+        let configured = self.controller.configured_parameters();
+        let mismatches = self.controller.device_parameters().map(|device| {
+            DeviceParameters::mismatches(&configured, &device, &VerifyConfig::default())
+        });
+        let warning = iced::Color::from_rgb(0xE8 as f32 / 255.0, 0xA3 as f32 / 255.0, 0.0);
+
+        let mut col = Column::new()
+            .spacing(2)
+            .padding(5)
+            .width(Length::Fill)
+            .push(
+                Row::new()
+                    .push(Text::new("Parameter").size(14).width(Length::Fill))
+                    .push(Text::new("Configured").size(14).width(Length::Fill))
+                    .push(Text::new("Device").size(14).width(Length::Fill)),
+            );
+        for &parameter in cryo_cooler_controller_lib::Parameter::ALL {
+            let out_of_sync = mismatches
+                .as_ref()
+                .is_some_and(|mismatches| mismatches.contains(&parameter));
+            let color = if out_of_sync {
+                warning
+            } else {
+                iced::Color::WHITE
+            };
+            let device = match self.controller.device_parameters() {
+                Some(device) => format!("{:.2}", device.get(parameter)),
+                None => "-".to_owned(),
+            };
+            col = col.push(
+                Row::new()
+                    .push(
+                        Text::new(parameter.to_string())
+                            .size(14)
+                            .width(Length::Fill),
+                    )
+                    .push(
+                        Text::new(format!("{:.2}", configured.get(parameter)))
+                            .size(14)
+                            .width(Length::Fill),
+                    )
+                    .push(Text::new(device).size(14).style(color).width(Length::Fill)),
+            );
+        }
+        if mismatches.is_some_and(|mismatches| !mismatches.is_empty()) {
+            col = col.push(Text::new("OUT OF SYNC").size(14).style(warning));
+        }
+
+        let mut load_button = iced::widget::button(Text::new("Load from Device"))
+            .style(iced::theme::Button::Secondary);
+        if self.controller.device_parameters().is_some() {
+            load_button = load_button.on_press(Message::LoadParametersFromDevice);
+        }
+        let mut undo_button =
+            iced::widget::button(Text::new("Undo")).style(iced::theme::Button::Secondary);
+        if self.controller.can_undo_live_apply() {
+            undo_button = undo_button.on_press(Message::UndoLiveApply);
+        }
+        col.push(
+            Row::new()
+                .spacing(10)
+                .padding(5)
+                .push(
+                    iced::widget::button(Text::new("Apply"))
+                        .style(iced::theme::Button::Secondary)
+                        .on_press(Message::ApplyParameters),
+                )
+                .push(load_button)
+                .push(undo_button),
+        )
+        .push(
+            Row::new()
+                .spacing(10)
+                .padding(5)
+                .push(iced::widget::checkbox(
+                    "Apply Live",
+                    self.controller.settings().get_live_apply(),
+                    Message::LiveApplyToggled,
+                ))
+                .push(Text::new(if self.controller.live_apply_pending() {
+                    "Pending..."
+                } else {
+                    ""
+                })),
+        )
+        .into()
+    }
+
+    fn view_self_test(&self) -> Element<'_, Message> {
+        let Some(self_test) = self.controller.self_test() else {
+            return Text::new("").into();
+        };
+        let report = self_test.report();
+        let mut col = Column::new().spacing(5);
+        if !self_test.is_finished() {
+            col = col
+                .push(Text::new(format!("Running: {:?}", self_test.step())))
+                .push(iced::widget::progress_bar(
+                    0.0..=1.0,
+                    self_test.progress(Instant::now()),
+                ));
+        } else {
+            col = col.push(Text::new(match (report.finished, report.passed()) {
+                (None, _) => "Result: ABORTED",
+                (Some(_), true) => "Result: PASS",
+                (Some(_), false) => "Result: FAIL",
+            }));
+        }
+        for check in &report.checks {
+            let color = if check.passed {
+                iced::Color::from_rgb(
+                    0x3F as f32 / 255.0,
+                    0xA3 as f32 / 255.0,
+                    0x4D as f32 / 255.0,
+                )
+            } else {
+                iced::Color::from_rgb(
+                    0xC3 as f32 / 255.0,
+                    0x42 as f32 / 255.0,
+                    0x3F as f32 / 255.0,
+                )
+            };
+            col = col.push(
+                Text::new(format!(
+                    "[{}] {}: {}",
+                    if check.passed { "PASS" } else { "FAIL" },
+                    check.name,
+                    check.detail
+                ))
+                .size(14)
+                .style(color),
+            );
+        }
+        col.into()
+    }
+
+    fn view_self_test_buttons(&self) -> Element<'_, Message> {
+        let row = Row::new().padding(5).spacing(10).width(Length::Fill);
+        if self
+            .controller
+            .self_test()
+            .is_some_and(SelfTest::is_finished)
+        {
+            row.push(
+                iced::widget::Button::new(
+                    Text::new("Save").horizontal_alignment(alignment::Horizontal::Center),
+                )
+                .width(Length::Fixed(80.0))
+                .on_press(Message::SaveSelfTestReport),
+            )
+            .push(
+                iced::widget::Button::new(
+                    Text::new("Close").horizontal_alignment(alignment::Horizontal::Center),
+                )
+                .width(Length::Fixed(80.0))
+                .style(iced::theme::Button::Secondary)
+                .on_press(Message::CloseSelfTest),
+            )
+            .into()
+        } else {
+            row.push(
+                iced::widget::Button::new(
+                    Text::new("Abort").horizontal_alignment(alignment::Horizontal::Center),
+                )
+                .width(Length::Fixed(80.0))
+                .style(iced::theme::Button::Destructive)
+                .on_press(Message::AbortSelfTest),
+            )
+            .into()
+        }
+    }
+
+    pub fn view_diagnostics(&self) -> Element<'_, Message> {
+        let mut col = Column::new()
+            .spacing(5)
+            .width(Length::Fill)
+            .push(iced::widget::checkbox(
+                "Show Diagnostics",
+                self.show_diagnostics,
+                Message::DiagnosticsToggled,
+            ));
+        if self.show_diagnostics {
+            let raw = self.controller.status_word().raw();
+            let bits = (0..8)
+                .rev()
+                .map(|nibble| format!("{:04b}", (raw >> (nibble * 4)) & 0xF))
+                .collect::<Vec<_>>()
+                .join(" ");
+            col = col
+                .push(Text::new(format!("Raw Status: {raw:#010X}")).size(16))
+                .push(Text::new(bits).size(16))
+                .push(
+                    Text::new(format!(
+                        "Unknown Bits: {:#010X}",
+                        self.controller.status_word().unknown_bits()
+                    ))
+                    .size(16),
+                );
+            if let Some(data) = self.controller.last_data() {
+                if let Some(host_dew_point) = data.host_dew_point() {
+                    col = col.push(
+                        Text::new(format!(
+                            "Dew Point: {host_dew_point:.1} C (Cooler {:.1} C)",
+                            data.dew_point_temperature
+                        ))
+                        .size(16),
+                    );
+                }
+            }
+        }
+        col.into()
+    }
+
+    /// Shows the selected chart range from the history database.
+    fn load_history(&mut self) {
+        let Some(history) = self.services.history() else {
+            return;
+        };
+        if let Err(e) = self.chart.load_history(history) {
+            self.controller
+                .set_error(format!("Failed to load history ({e})"));
+            self.chart.set_range(ChartRange::Live);
+        }
+    }
+
+    pub fn view_right_column(&self) -> Element<'_, Message> {
+        let col = Column::new()
             .spacing(5)
             .align_items(Alignment::Start)
             .width(Length::Fill)
             .height(Length::Fill)
-            .push(iced::widget::vertical_space(Length::Fixed(5.0)))
-            .push(self.chart.view())
-            .into()
+            .push(iced::widget::vertical_space(Length::Fixed(5.0)));
+        let mut controls = Row::new()
+            .push(Text::new("Export"))
+            .push(self.chart.view_export_controls())
+            .push(horizontal_space(Length::Fill))
+            .push(iced::widget::checkbox(
+                "Follow Live",
+                self.chart.following(),
+                Message::FollowLiveToggled,
+            ))
+            .padding([0, 10])
+            .spacing(5)
+            .align_items(Alignment::Center);
+        if self.chart.range() == ChartRange::Live {
+            controls = controls
+                .push(Text::new("Window"))
+                .push(iced::widget::pick_list(
+                    ChartWindow::ALL,
+                    self.chart.window(),
+                    Message::ChartWindowSelected,
+                ));
+        }
+        if self.services.history().is_some() {
+            controls = controls
+                .push(Text::new("Range"))
+                .push(iced::widget::pick_list(
+                    ChartRange::ALL,
+                    Some(self.chart.range()),
+                    Message::ChartRangeSelected,
+                ));
+        }
+        col.push(controls).push(self.chart.view()).into()
     }
 }
 
+/// Device versions and control parameters written above exported charts.
+fn export_header(controller: &Controller) -> Vec<String> {
+    let settings = controller.settings();
+    let (major, minor) = controller.firmware_version();
+    let setpoint = match settings.get_setpoint_mode() {
+        SetpointMode::Offset => format!("Setpoint Offset {} C", settings.get_set_point()),
+        SetpointMode::Absolute => format!(
+            "Target {} C, Safety Margin {} C",
+            settings.get_absolute_target(),
+            settings.get_safety_margin()
+        ),
+    };
+    vec![
+        format!(
+            "Cryo Cooler Controller {}, Firmware Version {major:X}.{minor:X}, Hardware Version {}, Port {}",
+            env!("CARGO_PKG_VERSION"),
+            controller.hardware_version(),
+            controller.port_path().display()
+        ),
+        format!(
+            "{setpoint}, PID {} / {} / {}, Max. Power {} %",
+            settings.get_p_coef(),
+            settings.get_i_coef(),
+            settings.get_d_coef(),
+            settings.get_max_power()
+        ),
+        format!("Exported {}", Utc::now().format("%Y-%m-%d %H:%M:%S UTC")),
+    ]
+}
+
+fn labeled_row<'a>(
+    label: &'static str,
+    input: impl Into<Element<'a, Message>>,
+) -> Row<'a, Message> {
+    Row::new()
+        .push(Text::new(label))
+        .push(horizontal_space(Length::Fill))
+        .push(input)
+        .padding(5)
+        .spacing(5)
+        .align_items(Alignment::Center)
+}
+
 fn add_badge_if_flag_missing<'a, T>(
     mut column: Column<'a, Message, iced::Renderer<T>>,
-    status: &'a TecStatus,
+    status: TecStatus,
     flag: TecStatus,
     text: &'static str,
 ) -> Column<'a, Message, iced::Renderer<T>>
@@ -390,7 +1243,7 @@ where
 
 fn add_badge_if_flag_set<'a, T>(
     mut column: Column<'a, Message, iced::Renderer<T>>,
-    status: &'a TecStatus,
+    status: TecStatus,
     flag: TecStatus,
     text: &'static str,
 ) -> Column<'a, Message, iced::Renderer<T>>
@@ -408,19 +1261,60 @@ where
     column
 }
 
-pub fn view_badges(status: &TecStatus) -> Element<'_, Message> {
+pub fn view_badges<'a>(
+    status: TecStatus,
+    unknown_bits: u32,
+    watchdog: &Watchdog,
+    ocp_state: OcpState,
+    sensor_disagreement: bool,
+) -> Element<'a, Message> {
     let mut col = Column::new()
         .spacing(12)
         .align_items(Alignment::Center)
         .width(Length::Fill);
 
+    if watchdog.is_heartbeat_missed() {
+        col = col.push(
+            iced_aw::Badge::new(Text::new("NO HEARTBEAT").size(20).width(Length::Fill))
+                .style(iced_aw::style::BadgeStyles::Danger),
+        )
+    }
+
     col = add_badge_if_flag_missing(col, status, TecStatus::POWER_OK, "TEC NO POWER");
     col = add_badge_if_flag_missing(col, status, TecStatus::TEMP_SENSE_OK, "TEMP SENSOR ERROR");
     col = add_badge_if_flag_missing(col, status, TecStatus::HUM_SENSE_OK, "HUM SENSOR ERROR");
 
     col = add_badge_if_flag_set(col, status, TecStatus::PID_OUT_OF_RANGE, "PID OUT OF RANGE");
     col = add_badge_if_flag_set(col, status, TecStatus::PID_INVALID, "PID INVALID");
-    col = add_badge_if_flag_set(col, status, TecStatus::OCP_ACTIVE, "OCP ACTIVE");
+    if ocp_state == OcpState::Sustained {
+        col = col.push(
+            iced_aw::Badge::new(Text::new("OCP ACTIVE").size(20).width(Length::Fill))
+                .style(iced_aw::style::BadgeStyles::Danger),
+        )
+    }
+    col = add_badge_if_flag_set(col, status, TecStatus::FAILSAFE_ACTIVE, "FAILSAFE ACTIVE");
+
+    if sensor_disagreement {
+        col = col.push(
+            iced_aw::Badge::new(
+                Text::new("SENSOR DISAGREEMENT")
+                    .size(20)
+                    .width(Length::Fill),
+            )
+            .style(iced_aw::style::BadgeStyles::Warning),
+        )
+    }
+
+    if unknown_bits != 0 {
+        col = col.push(
+            iced_aw::Badge::new(
+                Text::new("UNKNOWN STATUS BITS")
+                    .size(20)
+                    .width(Length::Fill),
+            )
+            .style(iced_aw::style::BadgeStyles::Warning),
+        )
+    }
 
     if status.contains(TecStatus::LOW_POWER_MODE_ACTIVE) {
         col = col.push(
@@ -0,0 +1,470 @@
+//! Control policies written in Rhai, loaded from the `scripts` directory next to the settings.
+//!
+//! Every `*.rhai` file has to define `fn on_sample(sample, flags)`, which is called for every new
+//! sample. `sample` is a map with the measurements, `flags` an array with the names of the set
+//! status flags and `this` a map that keeps its values between calls. Scripts can call `now()`,
+//! `load_average()`, `enable()`, `disable()`, `set_offset(offset)` and `set_pid(p, i, d)`.
+//! They have no access to files or other programs, changes are clamped and rate limited by the
+//! host.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use cryo_cooler_controller_lib::{MonitoringData, Severity, TecStatus};
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Array, CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope, AST};
+use serde_json::{json, Value};
+
+use crate::api;
+use crate::controller::Controller;
+use crate::settings::SetpointMode;
+
+const MAX_OPERATIONS: u64 = 100_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptLimits {
+    pub min_action_interval: Duration,
+    pub min_offset: f32,
+    pub max_offset: f32,
+    pub max_pid: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Action {
+    Enable,
+    Disable,
+    SetOffset(f32),
+    SetPid(f32, f32, f32),
+}
+
+impl Action {
+    /// Limits the values to `limits`, returns whether they had to be changed.
+    fn clamp(self, limits: &ScriptLimits) -> (Action, bool) {
+        let clamp_pid = |value: f32| value.clamp(0.0, limits.max_pid.max(0.0));
+        let clamped = match self {
+            Action::SetOffset(offset) => Action::SetOffset(
+                offset.clamp(limits.min_offset, limits.max_offset.max(limits.min_offset)),
+            ),
+            Action::SetPid(p, i, d) => Action::SetPid(clamp_pid(p), clamp_pid(i), clamp_pid(d)),
+            action => action,
+        };
+        (clamped, clamped != self)
+    }
+
+    /// Whether executing the action would not change anything.
+    fn is_noop(self, controller: &Controller) -> bool {
+        let settings = controller.settings();
+        let enabled = controller.sent_offset().is_some()
+            && !controller
+                .tec_status()
+                .contains(TecStatus::LOW_POWER_MODE_ACTIVE);
+        match self {
+            Action::Enable => enabled,
+            Action::Disable => !enabled,
+            Action::SetOffset(offset) => {
+                settings.get_setpoint_mode() == SetpointMode::Offset
+                    && settings.get_set_point() == offset
+            }
+            Action::SetPid(p, i, d) => {
+                settings.get_p_coef() == p
+                    && settings.get_i_coef() == i
+                    && settings.get_d_coef() == d
+            }
+        }
+    }
+
+    fn request(self) -> (&'static str, Value) {
+        match self {
+            Action::Enable => ("enable", Value::Null),
+            Action::Disable => ("disable", Value::Null),
+            Action::SetOffset(offset) => ("set_offset", json!({ "offset": offset })),
+            Action::SetPid(p, i, d) => ("set_pid", json!({ "p": p, "i": i, "d": d })),
+        }
+    }
+}
+
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Action::Enable => write!(f, "enable TEC"),
+            Action::Disable => write!(f, "disable TEC"),
+            Action::SetOffset(offset) => write!(f, "set offset to {offset}"),
+            Action::SetPid(p, i, d) => write!(f, "set PID to {p}, {i}, {d}"),
+        }
+    }
+}
+
+struct Script {
+    name: String,
+    ast: AST,
+    /// Bound to `this` when the script is called.
+    state: Dynamic,
+    last_action: Option<Instant>,
+    /// Whether dropped actions were already reported since the last executed one.
+    rate_limited: bool,
+    /// Set after a runtime error, the script is not called again.
+    stopped: bool,
+}
+
+pub struct ScriptHost {
+    engine: Engine,
+    scripts: Vec<Script>,
+    limits: ScriptLimits,
+    /// Actions requested by the running script.
+    requested: Arc<Mutex<Vec<Action>>>,
+    /// Output of `print` and `debug` of the running script.
+    output: Arc<Mutex<Vec<String>>>,
+    /// Actions waiting for [`ScriptHost::poll`], with the index of the script.
+    queue: Vec<(usize, Action)>,
+    /// Entries waiting for [`ScriptHost::poll`] to be added to the event log.
+    log: Vec<(Severity, String)>,
+}
+
+fn number(value: &Dynamic) -> Result<f32, Box<EvalAltResult>> {
+    value
+        .as_float()
+        .or_else(|_| value.as_int().map(|value| value as f64))
+        .map(|value| value as f32)
+        .map_err(|_| format!("Expected a number, got {}", value.type_name()).into())
+}
+
+fn load_average() -> Dynamic {
+    std::fs::read_to_string("/proc/loadavg")
+        .ok()
+        .and_then(|text| text.split_whitespace().next()?.parse::<f64>().ok())
+        .map_or(Dynamic::UNIT, Dynamic::from_float)
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl ScriptHost {
+    /// Compiles all scripts in `dir`, the directory is created if it does not exist.
+    ///
+    /// Scripts that fail to compile are reported in the event log and skipped.
+    pub fn load(dir: &Path, limits: ScriptLimits) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let mut paths: Vec<_> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "rhai")
+            })
+            .collect();
+        paths.sort();
+
+        let mut host = Self::new(limits);
+        for path in paths {
+            let name = path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            match std::fs::read_to_string(&path) {
+                Ok(source) => host.add(name, &source),
+                Err(e) => host.log.push((
+                    Severity::Warning,
+                    format!("Failed to read script {name} ({e})"),
+                )),
+            }
+        }
+        Ok(host)
+    }
+
+    fn new(limits: ScriptLimits) -> Self {
+        let requested = Arc::new(Mutex::new(Vec::new()));
+        let output = Arc::new(Mutex::new(Vec::new()));
+
+        let mut engine = Engine::new();
+        engine
+            .set_module_resolver(DummyModuleResolver::new())
+            .set_max_operations(MAX_OPERATIONS)
+            .set_max_call_levels(32)
+            .set_max_expr_depths(64, 32)
+            .set_max_string_size(4096)
+            .set_max_array_size(1024)
+            .set_max_map_size(256);
+        engine.disable_symbol("eval");
+
+        let sink = Arc::clone(&output);
+        engine.on_print(move |text| lock(&sink).push(text.to_owned()));
+        let sink = Arc::clone(&output);
+        engine.on_debug(move |text, _, _| lock(&sink).push(text.to_owned()));
+
+        engine.register_fn("now", || Utc::now().timestamp_millis() as f64 / 1000.0);
+        engine.register_fn("load_average", load_average);
+        let actions = Arc::clone(&requested);
+        engine.register_fn("enable", move || lock(&actions).push(Action::Enable));
+        let actions = Arc::clone(&requested);
+        engine.register_fn("disable", move || lock(&actions).push(Action::Disable));
+        let actions = Arc::clone(&requested);
+        engine.register_fn(
+            "set_offset",
+            move |offset: Dynamic| -> Result<(), Box<EvalAltResult>> {
+                lock(&actions).push(Action::SetOffset(number(&offset)?));
+                Ok(())
+            },
+        );
+        let actions = Arc::clone(&requested);
+        engine.register_fn(
+            "set_pid",
+            move |p: Dynamic, i: Dynamic, d: Dynamic| -> Result<(), Box<EvalAltResult>> {
+                lock(&actions).push(Action::SetPid(number(&p)?, number(&i)?, number(&d)?));
+                Ok(())
+            },
+        );
+
+        Self {
+            engine,
+            scripts: Vec::new(),
+            limits,
+            requested,
+            output,
+            queue: Vec::new(),
+            log: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, name: String, source: &str) {
+        let ast = match self.engine.compile(source) {
+            Ok(ast) => ast,
+            Err(e) => {
+                self.log
+                    .push((Severity::Warning, format!("Script {name} is invalid ({e})")));
+                return;
+            }
+        };
+        if !ast
+            .iter_functions()
+            .any(|function| function.name == "on_sample" && function.params.len() == 2)
+        {
+            self.log.push((
+                Severity::Warning,
+                format!("Script {name} does not define on_sample(sample, flags)"),
+            ));
+            return;
+        }
+        self.scripts.push(Script {
+            name,
+            ast,
+            state: Dynamic::from_map(Map::new()),
+            last_action: None,
+            rate_limited: false,
+            stopped: false,
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.scripts.len()
+    }
+
+    /// Calls the scripts with a new sample, their actions are executed by [`ScriptHost::poll`].
+    pub fn publish(&mut self, controller: &Controller, data: &MonitoringData) {
+        self.run(data, controller.tec_status());
+    }
+
+    fn run(&mut self, data: &MonitoringData, status: TecStatus) {
+        let mut sample = Map::new();
+        for (key, value) in [
+            ("tec_temperature", data.tec_temperature),
+            ("board_temperature", data.pcb_temperature),
+            ("humidity", data.humidity),
+            ("dew_point", data.dew_point_temperature),
+            ("voltage", data.tec_voltage),
+            ("current", data.tec_current),
+        ] {
+            sample.insert(key.into(), Dynamic::from_float(f64::from(value)));
+        }
+        sample.insert(
+            "power_level".into(),
+            Dynamic::from_int(i64::from(data.tec_power_level)),
+        );
+        sample.insert(
+            "timestamp".into(),
+            Dynamic::from_float(data.timestamp.timestamp_millis() as f64 / 1000.0),
+        );
+        let flags: Array = status
+            .iter_names()
+            .map(|(name, _)| Dynamic::from(name.to_owned()))
+            .collect();
+
+        for (index, script) in self.scripts.iter_mut().enumerate() {
+            if script.stopped {
+                continue;
+            }
+            let options = CallFnOptions::new()
+                .eval_ast(false)
+                .bind_this_ptr(&mut script.state);
+            let result = self.engine.call_fn_with_options::<Dynamic>(
+                options,
+                &mut Scope::new(),
+                &script.ast,
+                "on_sample",
+                (sample.clone(), flags.clone()),
+            );
+            for text in lock(&self.output).drain(..) {
+                self.log
+                    .push((Severity::Info, format!("Script {}: {text}", script.name)));
+            }
+            let actions: Vec<_> = lock(&self.requested).drain(..).collect();
+            match result {
+                Ok(_) => self
+                    .queue
+                    .extend(actions.into_iter().map(|action| (index, action))),
+                Err(e) => {
+                    script.stopped = true;
+                    self.log.push((
+                        Severity::Warning,
+                        format!("Script {} stopped ({e})", script.name),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Executes the actions requested by the scripts within the configured limits.
+    ///
+    /// Has to be called periodically by the owner of the controller.
+    pub fn poll(&mut self, controller: &mut Controller) {
+        let now = Instant::now();
+        for (index, action) in std::mem::take(&mut self.queue) {
+            let script = &mut self.scripts[index];
+            let (action, clamped) = action.clamp(&self.limits);
+            if action.is_noop(controller) {
+                continue;
+            }
+            if script
+                .last_action
+                .is_some_and(|last| now.duration_since(last) < self.limits.min_action_interval)
+            {
+                if !script.rate_limited {
+                    script.rate_limited = true;
+                    self.log.push((
+                        Severity::Warning,
+                        format!(
+                            "Script {} changes too often, skipped: {action}",
+                            script.name
+                        ),
+                    ));
+                }
+                continue;
+            }
+            script.last_action = Some(now);
+            script.rate_limited = false;
+            if clamped {
+                self.log.push((
+                    Severity::Warning,
+                    format!("Script {} exceeded the limits", script.name),
+                ));
+            }
+            self.log
+                .push((Severity::Info, format!("Script {}: {action}", script.name)));
+            let (method, params) = action.request();
+            // Failures are reported by the controller.
+            let _ = api::call(controller, method, params);
+        }
+        for (severity, text) in self.log.drain(..) {
+            controller.event_log_mut().push(Utc::now(), severity, text);
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn limits() -> ScriptLimits {
+        ScriptLimits {
+            min_action_interval: Duration::from_secs(5),
+            min_offset: 0.0,
+            max_offset: 20.0,
+            max_pid: 1000.0,
+        }
+    }
+
+    fn sample(tec_temperature: f32) -> MonitoringData {
+        MonitoringData {
+            timestamp: Utc::now(),
+            tec_temperature,
+            pcb_temperature: 30.0,
+            humidity: 50.0,
+            dew_point_temperature: 10.0,
+            tec_voltage: 12.0,
+            tec_current: 3.0,
+            tec_power_level: 50,
+        }
+    }
+
+    #[test]
+    fn scripts() {
+        let dir = tempdir::TempDir::new("scripts").unwrap();
+        std::fs::write(
+            dir.path().join("offset.rhai"),
+            r#"
+            fn on_sample(sample, flags) {
+                this.calls = (this.calls ?? 0) + 1;
+                if sample.tec_temperature > 20.0 && !flags.contains("OCP_ACTIVE") {
+                    set_offset(this.calls);
+                }
+                if sample.power_level == 50 {
+                    set_pid(100, 1.5, -3);
+                }
+            }
+            "#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("loop.rhai"),
+            "fn on_sample(sample, flags) { loop {} }",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("import.rhai"),
+            r#"fn on_sample(sample, flags) { import "other" as other; }"#,
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("missing.rhai"), "let x = 1;").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "not a script").unwrap();
+
+        let mut host = ScriptHost::load(dir.path(), limits()).unwrap();
+        assert_eq!(host.len(), 3);
+        assert_eq!(host.log.len(), 1);
+
+        host.run(&sample(25.0), TecStatus::POWER_OK);
+        host.run(&sample(25.0), TecStatus::POWER_OK | TecStatus::OCP_ACTIVE);
+        assert_eq!(
+            host.queue,
+            vec![
+                (2, Action::SetOffset(1.0)),
+                (2, Action::SetPid(100.0, 1.5, -3.0)),
+                (2, Action::SetPid(100.0, 1.5, -3.0)),
+            ]
+        );
+        // The import and the endless loop are stopped by the sandbox.
+        assert!(host.scripts[0].stopped);
+        assert!(host.scripts[1].stopped);
+        assert!(!host.scripts[2].stopped);
+    }
+
+    #[test]
+    fn clamp() {
+        assert_eq!(
+            Action::SetOffset(-4.0).clamp(&limits()),
+            (Action::SetOffset(0.0), true)
+        );
+        assert_eq!(
+            Action::SetPid(100.0, 1.5, -3.0).clamp(&limits()),
+            (Action::SetPid(100.0, 1.5, 0.0), true)
+        );
+        assert_eq!(
+            Action::SetOffset(4.0).clamp(&limits()),
+            (Action::SetOffset(4.0), false)
+        );
+    }
+}
@@ -0,0 +1,276 @@
+//! Interfaces for other programs, shared by the GUI and the daemon.
+
+use chrono::Utc;
+use cryo_cooler_controller_lib::{MonitoringData, Severity};
+
+#[cfg(unix)]
+use crate::control_socket::{self, ControlServer};
+use crate::controller::Controller;
+use crate::csv_log::CsvLogger;
+#[cfg(target_os = "linux")]
+use crate::dbus::{self, DbusService};
+use crate::history::HistoryDb;
+use crate::hooks::HookRunner;
+use crate::http_api::HttpServer;
+use crate::metrics::MetricsServer;
+use crate::mqtt::MqttClient;
+use crate::scripting::ScriptHost;
+
+#[derive(Default)]
+pub struct Services {
+    #[cfg(unix)]
+    control_server: Option<ControlServer>,
+    http_server: Option<HttpServer>,
+    metrics_server: Option<MetricsServer>,
+    mqtt_client: Option<MqttClient>,
+    #[cfg(target_os = "linux")]
+    dbus_service: Option<DbusService>,
+    hooks: HookRunner,
+    script_host: Option<ScriptHost>,
+    csv_logger: Option<CsvLogger>,
+    history: Option<HistoryDb>,
+}
+
+impl Services {
+    /// Starts and stops the services as configured.
+    ///
+    /// Has to be called after the settings changed.
+    pub fn sync(&mut self, controller: &mut Controller) {
+        self.hooks
+            .set_config(controller.settings().get_hook_config());
+
+        #[cfg(unix)]
+        if !controller.settings().get_control_socket() {
+            self.control_server = None;
+        } else if self.control_server.is_none() {
+            match ControlServer::start(control_socket::socket_path(controller.settings())) {
+                Ok(server) => {
+                    controller.event_log_mut().push(
+                        Utc::now(),
+                        Severity::Info,
+                        format!("Control socket listening on {}", server.path().display()),
+                    );
+                    self.control_server = Some(server);
+                }
+                Err(e) => controller.set_error(format!("Failed to start control socket ({e})")),
+            }
+        }
+
+        let address = controller.settings().get_http_api_address().to_owned();
+        if !controller.settings().get_http_api_enabled() {
+            self.http_server = None;
+        } else if self
+            .http_server
+            .as_ref()
+            .is_none_or(|server| server.address() != address)
+        {
+            self.http_server = None;
+            match HttpServer::start(&address) {
+                Ok(server) => {
+                    controller.event_log_mut().push(
+                        Utc::now(),
+                        Severity::Info,
+                        format!("HTTP API listening on {address}"),
+                    );
+                    self.http_server = Some(server);
+                }
+                Err(e) => controller.set_error(format!("Failed to start HTTP API ({e})")),
+            }
+        }
+
+        let address = controller.settings().get_metrics_address().to_owned();
+        if !controller.settings().get_metrics_enabled() {
+            self.metrics_server = None;
+        } else if self
+            .metrics_server
+            .as_ref()
+            .is_none_or(|server| server.address() != address)
+        {
+            self.metrics_server = None;
+            match MetricsServer::start(&address) {
+                Ok(server) => {
+                    controller.event_log_mut().push(
+                        Utc::now(),
+                        Severity::Info,
+                        format!("Metrics available on http://{address}/metrics"),
+                    );
+                    self.metrics_server = Some(server);
+                }
+                Err(e) => controller.set_error(format!("Failed to start metrics exporter ({e})")),
+            }
+        }
+
+        let config = controller.settings().get_mqtt_config();
+        if !controller.settings().get_mqtt_enabled() {
+            self.mqtt_client = None;
+        } else if self
+            .mqtt_client
+            .as_ref()
+            .is_none_or(|client| client.config() != &config)
+        {
+            self.mqtt_client = None;
+            controller.event_log_mut().push(
+                Utc::now(),
+                Severity::Info,
+                format!("Publishing to MQTT broker {}:{}", config.host, config.port),
+            );
+            self.mqtt_client = Some(MqttClient::start(config));
+        }
+
+        #[cfg(target_os = "linux")]
+        if !controller.settings().get_dbus_service() {
+            self.dbus_service = None;
+        } else if self.dbus_service.is_none() {
+            match DbusService::start() {
+                Ok(service) => {
+                    controller.event_log_mut().push(
+                        Utc::now(),
+                        Severity::Info,
+                        format!("Registered {} on the session bus", dbus::SERVICE_NAME),
+                    );
+                    self.dbus_service = Some(service);
+                }
+                Err(e) => controller.set_error(format!("Failed to start D-Bus service ({e})")),
+            }
+        }
+
+        if !controller.settings().get_csv_log_enabled() {
+            self.csv_logger = None;
+        } else if self.csv_logger.is_none() {
+            match CsvLogger::new(
+                controller.settings().get_csv_log_dir(),
+                controller.settings().get_csv_log_retention_days(),
+            ) {
+                Ok(logger) => {
+                    controller.event_log_mut().push(
+                        Utc::now(),
+                        Severity::Info,
+                        format!("Logging to {}", logger.dir().display()),
+                    );
+                    self.csv_logger = Some(logger);
+                }
+                Err(e) => controller.set_error(format!("Failed to start logging ({e})")),
+            }
+        }
+
+        if !controller.settings().get_history_enabled() {
+            self.history = None;
+        } else if self.history.is_none() {
+            let path = controller.settings().get_history_path();
+            match HistoryDb::open(&path, controller.settings().get_history_retention()) {
+                Ok(history) => {
+                    controller.event_log_mut().push(
+                        Utc::now(),
+                        Severity::Info,
+                        format!("Recording history to {}", path.display()),
+                    );
+                    self.history = Some(history);
+                }
+                Err(e) => controller.set_error(format!("Failed to open history database ({e})")),
+            }
+        }
+
+        if !controller.settings().get_scripting_enabled() {
+            self.script_host = None;
+        } else if self.script_host.is_none() {
+            let dir = controller.settings().get_scripts_dir();
+            match ScriptHost::load(&dir, controller.settings().get_script_limits()) {
+                Ok(host) => {
+                    controller.event_log_mut().push(
+                        Utc::now(),
+                        Severity::Info,
+                        format!("Loaded {} scripts from {}", host.len(), dir.display()),
+                    );
+                    self.script_host = Some(host);
+                }
+                Err(e) => controller.set_error(format!("Failed to load scripts ({e})")),
+            }
+        }
+    }
+
+    /// The history database if it is enabled.
+    pub fn history(&self) -> Option<&HistoryDb> {
+        self.history.as_ref()
+    }
+
+    /// Loads the control scripts again if scripting is enabled.
+    pub fn reload_scripts(&mut self, controller: &mut Controller) {
+        self.script_host = None;
+        self.sync(controller);
+    }
+
+    /// Answers pending requests, has to be called periodically.
+    pub fn poll(&mut self, controller: &mut Controller) {
+        self.hooks.check(controller);
+        #[cfg(unix)]
+        if let Some(server) = &mut self.control_server {
+            server.poll(controller);
+        }
+        if let Some(server) = &mut self.http_server {
+            server.poll(controller, self.history.as_ref());
+        }
+        if let Some(server) = &mut self.metrics_server {
+            server.poll(controller);
+        }
+        if let Some(client) = &mut self.mqtt_client {
+            client.poll(controller);
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(service) = &mut self.dbus_service {
+            service.poll(controller);
+        }
+        if let Some(host) = &mut self.script_host {
+            host.poll(controller);
+        }
+        let actions = controller.take_user_actions();
+        if let Some(history) = &self.history {
+            for action in &actions {
+                if let Err(e) = history.record_action(action) {
+                    controller.set_error(format!("Failed to record action ({e})"));
+                }
+            }
+            if let Some(e) = history.take_error() {
+                controller.event_log_mut().push(
+                    Utc::now(),
+                    Severity::Warning,
+                    format!("History downsampling failed ({e})"),
+                );
+            }
+        }
+    }
+
+    /// Forwards a new sample to the subscribers.
+    pub fn publish(&mut self, controller: &mut Controller, data: &MonitoringData) {
+        #[cfg(unix)]
+        if let Some(server) = &mut self.control_server {
+            server.publish(data);
+        }
+        if let Some(server) = &mut self.http_server {
+            server.publish(data);
+        }
+        if let Some(client) = &mut self.mqtt_client {
+            client.publish_sample(controller, data);
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(service) = &mut self.dbus_service {
+            service.publish(controller, data);
+        }
+        if let Some(host) = &mut self.script_host {
+            host.publish(controller, data);
+        }
+        if let Some(logger) = &mut self.csv_logger {
+            if let Err(e) = logger.log(data, controller.status_word()) {
+                self.csv_logger = None;
+                let _ = controller.update_settings(|s| s.set_csv_log_enabled(false));
+                controller.set_error(format!("Logging stopped ({e})"));
+            }
+        }
+        if let Some(history) = &mut self.history {
+            if let Err(e) = history.record_sample(data, controller.status_word()) {
+                self.history = None;
+                let _ = controller.update_settings(|s| s.set_history_enabled(false));
+                controller.set_error(format!("History recording stopped ({e})"));
+            }
+        }
+    }
+}
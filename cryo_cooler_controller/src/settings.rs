@@ -1,14 +1,41 @@
 use dirs::config_dir;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SetpointMode {
+    /// `set_point` is an offset relative to the dew point.
+    #[default]
+    Offset,
+    /// `absolute_target` is the TEC temperature, limited by the dew point and `safety_margin`.
+    Absolute,
+}
+
+impl SetpointMode {
+    pub const ALL: &[SetpointMode] = &[SetpointMode::Offset, SetpointMode::Absolute];
+}
+
+impl std::fmt::Display for SetpointMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SetpointMode::Offset => write!(f, "Dew Point Offset"),
+            SetpointMode::Absolute => write!(f, "Absolute"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
 struct TecInputs {
     p_coef: f32,
     i_coef: f32,
     d_coef: f32,
     set_point: f32,
     max_power: u8,
+    setpoint_mode: SetpointMode,
+    absolute_target: f32,
+    safety_margin: f32,
 }
 
 impl Default for TecInputs {
@@ -19,6 +46,352 @@ impl Default for TecInputs {
             d_coef: 1.0,
             set_point: 2.0,
             max_power: 100,
+            setpoint_mode: SetpointMode::default(),
+            absolute_target: 15.0,
+            safety_margin: 2.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecoveryAction {
+    /// Enable the TEC again with the stored inputs if it was enabled before the fault.
+    ReEnable,
+    /// Make sure the TEC stays disabled.
+    #[default]
+    StayDisabled,
+    /// Ask the user whether the TEC should be enabled again.
+    AskUser,
+}
+
+impl RecoveryAction {
+    pub const ALL: &[RecoveryAction] = &[
+        RecoveryAction::ReEnable,
+        RecoveryAction::StayDisabled,
+        RecoveryAction::AskUser,
+    ];
+}
+
+impl std::fmt::Display for RecoveryAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RecoveryAction::ReEnable => write!(f, "Re-enable"),
+            RecoveryAction::StayDisabled => write!(f, "Stay disabled"),
+            RecoveryAction::AskUser => write!(f, "Ask"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+struct WatchdogSettings {
+    heartbeat_interval_ms: u64,
+    timeout_ms: u64,
+    recovery_action: RecoveryAction,
+}
+
+impl Default for WatchdogSettings {
+    fn default() -> Self {
+        Self {
+            heartbeat_interval_ms: 500,
+            timeout_ms: 5000,
+            recovery_action: RecoveryAction::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OcpResponse {
+    /// Only show a warning.
+    #[default]
+    Warn,
+    /// Raise the offset by the configured step to reduce the cooling load.
+    ReduceSetpoint,
+    /// Disable the TEC.
+    Disable,
+}
+
+impl OcpResponse {
+    pub const ALL: &[OcpResponse] = &[
+        OcpResponse::Warn,
+        OcpResponse::ReduceSetpoint,
+        OcpResponse::Disable,
+    ];
+}
+
+impl std::fmt::Display for OcpResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            OcpResponse::Warn => write!(f, "Warn"),
+            OcpResponse::ReduceSetpoint => write!(f, "Reduce setpoint"),
+            OcpResponse::Disable => write!(f, "Disable TEC"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+struct OcpSettings {
+    debounce_ms: u64,
+    current_threshold: f32,
+    response: OcpResponse,
+    setpoint_step: f32,
+}
+
+impl Default for OcpSettings {
+    fn default() -> Self {
+        Self {
+            debounce_ms: 2000,
+            current_threshold: 6.0,
+            response: OcpResponse::default(),
+            setpoint_step: 2.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+struct DewPointSettings {
+    /// Maximum difference in C between the host computed and the reported dew point.
+    tolerance: f32,
+    use_host_dew_point: bool,
+}
+
+impl Default for DewPointSettings {
+    fn default() -> Self {
+        Self {
+            tolerance: 2.0,
+            use_host_dew_point: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+struct HttpApiSettings {
+    enabled: bool,
+    /// Address the server binds to, only reachable from this computer by default.
+    address: String,
+}
+
+impl Default for HttpApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "127.0.0.1:8383".to_owned(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+struct MetricsSettings {
+    enabled: bool,
+    /// Address of the Prometheus endpoint, only reachable from this computer by default.
+    address: String,
+}
+
+impl Default for MetricsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "127.0.0.1:9383".to_owned(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+struct MqttSettings {
+    enabled: bool,
+    host: String,
+    port: u16,
+    client_id: String,
+    username: Option<String>,
+    password: Option<String>,
+    topic_prefix: String,
+    /// Announce the cooler to Home Assistant.
+    discovery: bool,
+    discovery_prefix: String,
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: "localhost".to_owned(),
+            port: 1883,
+            client_id: "cryo_cooler_controller".to_owned(),
+            username: None,
+            password: None,
+            topic_prefix: "cryo_cooler".to_owned(),
+            discovery: true,
+            discovery_prefix: "homeassistant".to_owned(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+struct CsvLogSettings {
+    enabled: bool,
+    /// Log files older than this are deleted, 0 keeps them forever.
+    retention_days: u32,
+}
+
+impl Default for CsvLogSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            retention_days: 30,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+struct HistorySettings {
+    enabled: bool,
+    /// Raw samples are replaced by their one minute aggregates after this time.
+    raw_retention_hours: u32,
+    /// One minute aggregates are replaced by their one hour aggregates after this time.
+    minute_retention_days: u32,
+    /// Hourly aggregates, status transitions and actions are deleted after this time, 0 keeps
+    /// them forever.
+    hour_retention_days: u32,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            raw_retention_hours: 48,
+            minute_retention_days: 30,
+            hour_retention_days: 0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+struct ChartSettings {
+    /// Time range shown by the live charts.
+    window_secs: u64,
+    /// Live data is kept this long, independent of the shown range.
+    memory_hours: u32,
+}
+
+impl Default for ChartSettings {
+    fn default() -> Self {
+        Self {
+            window_secs: 300,
+            memory_hours: 24,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+struct ScriptingSettings {
+    enabled: bool,
+    /// Minimum time between two changes made by the same script.
+    min_action_interval_ms: u64,
+    /// Range the offset set by scripts is clamped to.
+    min_offset: f32,
+    max_offset: f32,
+    /// P, I and D set by scripts are clamped to `0..=max_pid`.
+    max_pid: f32,
+}
+
+impl Default for ScriptingSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_action_interval_ms: 5000,
+            min_offset: 0.0,
+            max_offset: 20.0,
+            max_pid: 1000.0,
+        }
+    }
+}
+
+/// Event that runs a hook command.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    TecEnabled,
+    TecDisabled,
+    /// A status flag was set, restricted to one flag with `flag`.
+    StatusRaised,
+    /// A status flag was cleared, restricted to one flag with `flag`.
+    StatusCleared,
+    ConnectionLost,
+    ConnectionRestored,
+    /// The TEC temperature fell below the dew point plus the condensation margin.
+    CondensationMarginBreached,
+    /// The TEC temperature rose above `threshold`.
+    TemperatureAbove,
+    /// The TEC temperature fell below `threshold`.
+    TemperatureBelow,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct HookCommandSettings {
+    event: HookEvent,
+    /// Name of the status flag, e.g. `OCP_ACTIVE`.
+    #[serde(default)]
+    flag: Option<String>,
+    /// TEC temperature in C.
+    #[serde(default)]
+    threshold: Option<f32>,
+    command: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+struct HookSettings {
+    /// Hook commands still running after this time are killed.
+    timeout_ms: u64,
+    /// Distance in C between TEC temperature and dew point below which condensation is likely.
+    condensation_margin: f32,
+    commands: Vec<HookCommandSettings>,
+}
+
+impl Default for HookSettings {
+    fn default() -> Self {
+        Self {
+            timeout_ms: 10_000,
+            condensation_margin: 1.0,
+            commands: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct CalibrationSettings {
+    voltage_factor: f32,
+    voltage_offset: f32,
+    current_factor: f32,
+    current_offset: f32,
+}
+
+impl From<CalibrationSettings> for cryo_cooler_controller_lib::Calibration {
+    fn from(value: CalibrationSettings) -> Self {
+        Self {
+            voltage_factor: value.voltage_factor,
+            voltage_offset: value.voltage_offset,
+            current_factor: value.current_factor,
+            current_offset: value.current_offset,
+        }
+    }
+}
+
+impl From<cryo_cooler_controller_lib::Calibration> for CalibrationSettings {
+    fn from(value: cryo_cooler_controller_lib::Calibration) -> Self {
+        Self {
+            voltage_factor: value.voltage_factor,
+            voltage_offset: value.voltage_offset,
+            current_factor: value.current_factor,
+            current_offset: value.current_offset,
         }
     }
 }
@@ -40,6 +413,46 @@ struct Settings {
     open_port_on_startup: bool,
     tec_inputs: TecInputs,
     enable_on_startup: bool,
+    #[serde(default)]
+    watchdog: WatchdogSettings,
+    #[serde(default)]
+    ocp: OcpSettings,
+    /// Calibration per serial port, ports without an entry use the default calibration.
+    #[serde(default)]
+    calibrations: BTreeMap<PathBuf, CalibrationSettings>,
+    #[serde(default)]
+    dew_point: DewPointSettings,
+    /// Read back the setpoint offset and P/I/D after writing them to the cooler.
+    #[serde(default)]
+    verify_writes: bool,
+    /// Write edited PID and setpoint values to the cooler while the TEC is running.
+    #[serde(default)]
+    live_apply: bool,
+    /// Serve the JSON-RPC control socket in the config directory.
+    #[serde(default)]
+    control_socket: bool,
+    #[serde(default)]
+    http_api: HttpApiSettings,
+    #[serde(default)]
+    metrics: MetricsSettings,
+    #[serde(default)]
+    mqtt: MqttSettings,
+    /// Register the service on the session D-Bus.
+    #[serde(default)]
+    dbus_service: bool,
+    #[serde(default)]
+    hooks: HookSettings,
+    #[serde(default)]
+    scripting: ScriptingSettings,
+    #[serde(default)]
+    csv_log: CsvLogSettings,
+    #[serde(default)]
+    history: HistorySettings,
+    #[serde(default)]
+    charts: ChartSettings,
+    /// Named copies of the TEC inputs, switched to over the remote control interfaces.
+    #[serde(default)]
+    profiles: BTreeMap<String, TecInputs>,
 }
 
 impl Default for Settings {
@@ -49,6 +462,23 @@ impl Default for Settings {
             open_port_on_startup: false,
             tec_inputs: TecInputs::default(),
             enable_on_startup: false,
+            watchdog: WatchdogSettings::default(),
+            ocp: OcpSettings::default(),
+            calibrations: BTreeMap::new(),
+            dew_point: DewPointSettings::default(),
+            verify_writes: false,
+            live_apply: false,
+            control_socket: false,
+            http_api: HttpApiSettings::default(),
+            metrics: MetricsSettings::default(),
+            mqtt: MqttSettings::default(),
+            dbus_service: false,
+            hooks: HookSettings::default(),
+            scripting: ScriptingSettings::default(),
+            csv_log: CsvLogSettings::default(),
+            history: HistorySettings::default(),
+            charts: ChartSettings::default(),
+            profiles: BTreeMap::new(),
         }
     }
 }
@@ -145,6 +575,11 @@ impl AppSettings {
         AppSettings::load_settings(AppSettings::determine_settings_dir_path())
     }
 
+    /// Directory the settings are stored in, also used for other files of the application.
+    pub fn get_config_dir_path(&self) -> &Path {
+        &self.config_dir_path
+    }
+
     pub fn get_last_port_ident(&self) -> &Option<PathBuf> {
         &self.settings.last_port_ident
     }
@@ -201,6 +636,30 @@ impl AppSettings {
         set_value!(self, value, settings.tec_inputs.max_power);
     }
 
+    pub fn get_setpoint_mode(&self) -> SetpointMode {
+        self.settings.tec_inputs.setpoint_mode
+    }
+
+    pub fn set_setpoint_mode(&mut self, value: SetpointMode) -> std::io::Result<()> {
+        set_value!(self, value, settings.tec_inputs.setpoint_mode);
+    }
+
+    pub fn get_absolute_target(&self) -> f32 {
+        self.settings.tec_inputs.absolute_target
+    }
+
+    pub fn set_absolute_target(&mut self, value: f32) -> std::io::Result<()> {
+        set_value!(self, value, settings.tec_inputs.absolute_target);
+    }
+
+    pub fn get_safety_margin(&self) -> f32 {
+        self.settings.tec_inputs.safety_margin
+    }
+
+    pub fn set_safety_margin(&mut self, value: f32) -> std::io::Result<()> {
+        set_value!(self, value, settings.tec_inputs.safety_margin);
+    }
+
     pub fn get_enable_on_startup(&self) -> bool {
         self.settings.enable_on_startup
     }
@@ -209,6 +668,333 @@ impl AppSettings {
         set_value!(self, value, settings.enable_on_startup);
     }
 
+    pub fn get_watchdog_config(&self) -> cryo_cooler_controller_lib::WatchdogConfig {
+        cryo_cooler_controller_lib::WatchdogConfig {
+            heartbeat_interval: std::time::Duration::from_millis(
+                self.settings.watchdog.heartbeat_interval_ms,
+            ),
+            timeout: std::time::Duration::from_millis(self.settings.watchdog.timeout_ms),
+        }
+    }
+
+    pub fn get_heartbeat_interval_ms(&self) -> u64 {
+        self.settings.watchdog.heartbeat_interval_ms
+    }
+
+    pub fn set_heartbeat_interval_ms(&mut self, value: u64) -> std::io::Result<()> {
+        set_value!(self, value, settings.watchdog.heartbeat_interval_ms);
+    }
+
+    pub fn get_watchdog_timeout_ms(&self) -> u64 {
+        self.settings.watchdog.timeout_ms
+    }
+
+    pub fn set_watchdog_timeout_ms(&mut self, value: u64) -> std::io::Result<()> {
+        set_value!(self, value, settings.watchdog.timeout_ms);
+    }
+
+    pub fn get_recovery_action(&self) -> RecoveryAction {
+        self.settings.watchdog.recovery_action
+    }
+
+    pub fn set_recovery_action(&mut self, value: RecoveryAction) -> std::io::Result<()> {
+        set_value!(self, value, settings.watchdog.recovery_action);
+    }
+
+    pub fn get_ocp_config(&self) -> cryo_cooler_controller_lib::OcpConfig {
+        cryo_cooler_controller_lib::OcpConfig {
+            debounce: std::time::Duration::from_millis(self.settings.ocp.debounce_ms),
+            current_threshold: self.settings.ocp.current_threshold,
+        }
+    }
+
+    pub fn get_ocp_debounce_ms(&self) -> u64 {
+        self.settings.ocp.debounce_ms
+    }
+
+    pub fn set_ocp_debounce_ms(&mut self, value: u64) -> std::io::Result<()> {
+        set_value!(self, value, settings.ocp.debounce_ms);
+    }
+
+    pub fn get_ocp_current_threshold(&self) -> f32 {
+        self.settings.ocp.current_threshold
+    }
+
+    pub fn set_ocp_current_threshold(&mut self, value: f32) -> std::io::Result<()> {
+        set_value!(self, value, settings.ocp.current_threshold);
+    }
+
+    pub fn get_ocp_response(&self) -> OcpResponse {
+        self.settings.ocp.response
+    }
+
+    pub fn set_ocp_response(&mut self, value: OcpResponse) -> std::io::Result<()> {
+        set_value!(self, value, settings.ocp.response);
+    }
+
+    pub fn get_ocp_setpoint_step(&self) -> f32 {
+        self.settings.ocp.setpoint_step
+    }
+
+    pub fn set_ocp_setpoint_step(&mut self, value: f32) -> std::io::Result<()> {
+        set_value!(self, value, settings.ocp.setpoint_step);
+    }
+
+    pub fn get_calibration(&self, port: &Path) -> cryo_cooler_controller_lib::Calibration {
+        self.settings
+            .calibrations
+            .get(port)
+            .cloned()
+            .map(Into::into)
+            .unwrap_or_default()
+    }
+
+    pub fn set_calibration(
+        &mut self,
+        port: &Path,
+        value: cryo_cooler_controller_lib::Calibration,
+    ) -> std::io::Result<()> {
+        let value = CalibrationSettings::from(value);
+        if self.settings.calibrations.get(port) != Some(&value) {
+            self.settings.calibrations.insert(port.to_path_buf(), value);
+            return self.write_to_disk();
+        }
+        Ok(())
+    }
+
+    pub fn reset_calibration(&mut self, port: &Path) -> std::io::Result<()> {
+        if self.settings.calibrations.remove(port).is_some() {
+            return self.write_to_disk();
+        }
+        Ok(())
+    }
+
+    pub fn get_dew_point_tolerance(&self) -> f32 {
+        self.settings.dew_point.tolerance
+    }
+
+    pub fn set_dew_point_tolerance(&mut self, value: f32) -> std::io::Result<()> {
+        set_value!(self, value, settings.dew_point.tolerance);
+    }
+
+    pub fn get_use_host_dew_point(&self) -> bool {
+        self.settings.dew_point.use_host_dew_point
+    }
+
+    pub fn set_use_host_dew_point(&mut self, value: bool) -> std::io::Result<()> {
+        set_value!(self, value, settings.dew_point.use_host_dew_point);
+    }
+
+    pub fn get_verify_writes(&self) -> bool {
+        self.settings.verify_writes
+    }
+
+    pub fn set_verify_writes(&mut self, value: bool) -> std::io::Result<()> {
+        set_value!(self, value, settings.verify_writes);
+    }
+
+    pub fn get_live_apply(&self) -> bool {
+        self.settings.live_apply
+    }
+
+    pub fn set_live_apply(&mut self, value: bool) -> std::io::Result<()> {
+        set_value!(self, value, settings.live_apply);
+    }
+
+    pub fn get_control_socket(&self) -> bool {
+        self.settings.control_socket
+    }
+
+    pub fn set_control_socket(&mut self, value: bool) -> std::io::Result<()> {
+        set_value!(self, value, settings.control_socket);
+    }
+
+    pub fn get_dbus_service(&self) -> bool {
+        self.settings.dbus_service
+    }
+
+    pub fn set_dbus_service(&mut self, value: bool) -> std::io::Result<()> {
+        set_value!(self, value, settings.dbus_service);
+    }
+
+    pub fn get_http_api_enabled(&self) -> bool {
+        self.settings.http_api.enabled
+    }
+
+    pub fn set_http_api_enabled(&mut self, value: bool) -> std::io::Result<()> {
+        set_value!(self, value, settings.http_api.enabled);
+    }
+
+    pub fn get_http_api_address(&self) -> &str {
+        &self.settings.http_api.address
+    }
+
+    pub fn get_metrics_enabled(&self) -> bool {
+        self.settings.metrics.enabled
+    }
+
+    pub fn set_metrics_enabled(&mut self, value: bool) -> std::io::Result<()> {
+        set_value!(self, value, settings.metrics.enabled);
+    }
+
+    pub fn get_metrics_address(&self) -> &str {
+        &self.settings.metrics.address
+    }
+
+    pub fn get_mqtt_enabled(&self) -> bool {
+        self.settings.mqtt.enabled
+    }
+
+    pub fn set_mqtt_enabled(&mut self, value: bool) -> std::io::Result<()> {
+        set_value!(self, value, settings.mqtt.enabled);
+    }
+
+    pub fn get_mqtt_config(&self) -> crate::mqtt::MqttConfig {
+        let mqtt = &self.settings.mqtt;
+        crate::mqtt::MqttConfig {
+            host: mqtt.host.clone(),
+            port: mqtt.port,
+            client_id: mqtt.client_id.clone(),
+            credentials: mqtt.username.clone().zip(mqtt.password.clone()),
+            topic_prefix: mqtt.topic_prefix.clone(),
+            discovery_prefix: mqtt.discovery.then(|| mqtt.discovery_prefix.clone()),
+        }
+    }
+
+    pub fn get_csv_log_enabled(&self) -> bool {
+        self.settings.csv_log.enabled
+    }
+
+    pub fn set_csv_log_enabled(&mut self, value: bool) -> std::io::Result<()> {
+        set_value!(self, value, settings.csv_log.enabled);
+    }
+
+    pub fn get_csv_log_retention_days(&self) -> u32 {
+        self.settings.csv_log.retention_days
+    }
+
+    /// Directory the CSV logs are written to.
+    pub fn get_csv_log_dir(&self) -> PathBuf {
+        self.config_dir_path.join("logs")
+    }
+
+    pub fn get_history_enabled(&self) -> bool {
+        self.settings.history.enabled
+    }
+
+    pub fn set_history_enabled(&mut self, value: bool) -> std::io::Result<()> {
+        set_value!(self, value, settings.history.enabled);
+    }
+
+    pub fn get_history_retention(&self) -> crate::history::Retention {
+        let history = &self.settings.history;
+        let days = |days: u32| std::time::Duration::from_secs(u64::from(days) * 24 * 3600);
+        crate::history::Retention {
+            raw: std::time::Duration::from_secs(u64::from(history.raw_retention_hours) * 3600),
+            minute: days(history.minute_retention_days),
+            hour: (history.hour_retention_days > 0).then(|| days(history.hour_retention_days)),
+        }
+    }
+
+    pub fn get_history_path(&self) -> PathBuf {
+        self.config_dir_path.join("history.sqlite3")
+    }
+
+    pub fn get_chart_window_secs(&self) -> u64 {
+        self.settings.charts.window_secs
+    }
+
+    pub fn set_chart_window_secs(&mut self, value: u64) -> std::io::Result<()> {
+        set_value!(self, value, settings.charts.window_secs);
+    }
+
+    pub fn get_chart_memory(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(u64::from(self.settings.charts.memory_hours) * 3600)
+    }
+
+    pub fn has_profile(&self, name: &str) -> bool {
+        self.settings.profiles.contains_key(name)
+    }
+
+    /// Stores the current TEC inputs as profile `name`, replacing an existing one.
+    pub fn save_profile(&mut self, name: &str) -> std::io::Result<()> {
+        let value = self.settings.tec_inputs.clone();
+        if self.settings.profiles.get(name) != Some(&value) {
+            self.settings.profiles.insert(name.to_owned(), value);
+            return self.write_to_disk();
+        }
+        Ok(())
+    }
+
+    /// Replaces the TEC inputs with the ones of profile `name`.
+    pub fn switch_profile(&mut self, name: &str) -> std::io::Result<()> {
+        let Some(value) = self.settings.profiles.get(name).cloned() else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("No profile named {name}"),
+            ));
+        };
+        set_value!(self, value, settings.tec_inputs);
+    }
+
+    pub fn get_scripting_enabled(&self) -> bool {
+        self.settings.scripting.enabled
+    }
+
+    pub fn set_scripting_enabled(&mut self, value: bool) -> std::io::Result<()> {
+        set_value!(self, value, settings.scripting.enabled);
+    }
+
+    pub fn get_script_limits(&self) -> crate::scripting::ScriptLimits {
+        let scripting = &self.settings.scripting;
+        crate::scripting::ScriptLimits {
+            min_action_interval: std::time::Duration::from_millis(scripting.min_action_interval_ms),
+            min_offset: scripting.min_offset,
+            max_offset: scripting.max_offset,
+            max_pid: scripting.max_pid,
+        }
+    }
+
+    /// Directory the control scripts are loaded from.
+    pub fn get_scripts_dir(&self) -> PathBuf {
+        self.config_dir_path.join("scripts")
+    }
+
+    pub fn get_export_dir(&self) -> PathBuf {
+        self.config_dir_path.join("exports")
+    }
+
+    pub fn get_hook_config(&self) -> crate::hooks::HookConfig {
+        let hooks = &self.settings.hooks;
+        crate::hooks::HookConfig {
+            timeout: std::time::Duration::from_millis(hooks.timeout_ms),
+            condensation_margin: hooks.condensation_margin,
+            hooks: hooks
+                .commands
+                .iter()
+                .map(|hook| crate::hooks::Hook {
+                    event: hook.event,
+                    flag: hook.flag.clone(),
+                    threshold: hook.threshold,
+                    command: hook.command.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// All settings as they are stored on disk, with the MQTT password replaced by `"********"`.
+    pub fn get_settings_json(&self) -> serde_json::Value {
+        let mut json = serde_json::to_value(&self.settings).unwrap_or_default();
+        if let Some(password) = json.pointer_mut("/mqtt/password").filter(|p| !p.is_null()) {
+            *password = serde_json::Value::from("********");
+        }
+        json
+    }
+
+    pub fn get_verify_config(&self) -> Option<cryo_cooler_controller_lib::VerifyConfig> {
+        self.settings.verify_writes.then(Default::default)
+    }
+
     fn write_to_disk(&mut self) -> std::io::Result<()> {
         let _ = std::fs::rename(
             self.config_dir_path.join(SETTINGS_FILE),
@@ -253,7 +1039,8 @@ mod tests {
     use std::io::{Read, Write};
 
     use super::*;
-    const DEFAULT_SETTING_PRETTY: &str = "{\n  \"version\": 1,\n  \"data\": {\n    \"last_port_ident\": null,\n    \"open_port_on_startup\": false,\n    \"tec_inputs\": {\n      \"p_coef\": 100.0,\n      \"i_coef\": 1.0,\n      \"d_coef\": 1.0,\n      \"set_point\": 2.0,\n      \"max_power\": 100\n    },\n    \"enable_on_startup\": false\n  }\n}";
+    const DEFAULT_SETTING_PRETTY: &str = "{\n  \"version\": 1,\n  \"data\": {\n    \"last_port_ident\": null,\n    \"open_port_on_startup\": false,\n    \"tec_inputs\": {\n      \"p_coef\": 100.0,\n      \"i_coef\": 1.0,\n      \"d_coef\": 1.0,\n      \"set_point\": 2.0,\n      \"max_power\": 100,\n      \"setpoint_mode\": \"Offset\",\n      \"absolute_target\": 15.0,\n      \"safety_margin\": 2.0\n    },\n    \"enable_on_startup\": false,\n    \"watchdog\": {\n      \"heartbeat_interval_ms\": 500,\n      \"timeout_ms\": 5000,\n      \"recovery_action\": \"StayDisabled\"\n    },\n    \"ocp\": {\n      \"debounce_ms\": 2000,\n      \"current_threshold\": 6.0,\n      \"response\": \"Warn\",\n      \"setpoint_step\": 2.0\n    },\n    \"calibrations\": {},\n    \"dew_point\": {\n      \"tolerance\": 2.0,\n      \"use_host_dew_point\": false\n    },\n    \"verify_writes\": false,\n    \"live_apply\": false,\n    \"control_socket\": false,\n    \"http_api\": {\n      \"enabled\": false,\n      \"address\": \"127.0.0.1:8383\"\n    },\n    \"metrics\": {\n      \"enabled\": false,\n      \"address\": \"127.0.0.1:9383\"\n    },\n    \"mqtt\": {\n      \"enabled\": false,\n      \"host\": \"localhost\",\n      \"port\": 1883,\n      \"client_id\": \"cryo_cooler_controller\",\n      \"username\": null,\n      \"password\": null,\n      \"topic_prefix\": \"cryo_cooler\",\n      \"discovery\": true,\n      \"discovery_prefix\": \"homeassistant\"\n    },\n    \"dbus_service\": false,\n    \"hooks\": {\n      \"timeout_ms\": 10000,\n      \"condensation_margin\": 1.0,\n      \"commands\": []\n    },\n    \"scripting\": {\n      \"enabled\": false,\n      \"min_action_interval_ms\": 5000,\n      \"min_offset\": 0.0,\n      \"max_offset\": 20.0,\n      \"max_pid\": 1000.0\n    },\n    \"csv_log\": {\n      \"enabled\": false,\n      \"retention_days\": 30\n    },\n    \"history\": {\n      \"enabled\": false,\n      \"raw_retention_hours\": 48,\n      \"minute_retention_days\": 30,\n      \"hour_retention_days\": 0\n    },\n    \"charts\": {\n      \"window_secs\": 300,\n      \"memory_hours\": 24\n    },\n    \"profiles\": {}\n  }\n}";
+    const WITHOUT_WATCHDOG_SETTING_PRETTY: &str = "{\n  \"version\": 1,\n  \"data\": {\n    \"last_port_ident\": null,\n    \"open_port_on_startup\": false,\n    \"tec_inputs\": {\n      \"p_coef\": 50.0,\n      \"i_coef\": 1.0,\n      \"d_coef\": 1.0,\n      \"set_point\": 2.0,\n      \"max_power\": 100\n    },\n    \"enable_on_startup\": false\n  }\n}";
     const INVALID_SETTING_PRETTY: &str = "{\n  \"version\": 1,\n  \"data\": \"invalid\"\n}";
     const OUTDATED_SETTING_PRETTY: &str = "{\n  \"version\": 0,\n  \"data\": {\n    \"last_port_ident\": null,\n    \"open_port_on_startup\": false,\n    \"tec_inputs\": {\n      \"p_coef\": 100.0,\n      \"i_coef\": 1.0,\n      \"d_coef\": 1.0,\n      \"set_point\": 2.0,\n      \"max_power\": 100\n    },\n    \"enable_on_startup\": false\n  }\n}";
 
@@ -283,6 +1070,102 @@ mod tests {
         }
     }
 
+    #[test]
+    fn missing_watchdog() {
+        let test_dir = tempdir::TempDir::new("test").unwrap();
+        {
+            let mut file = std::fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(test_dir.path().join(SETTINGS_FILE))
+                .unwrap();
+            file.write_all(WITHOUT_WATCHDOG_SETTING_PRETTY.as_bytes());
+        }
+
+        let settings = AppSettings::load_settings(test_dir.path().into());
+        assert_eq!(settings.get_p_coef(), 50.0);
+        assert_eq!(settings.get_watchdog_timeout_ms(), 5000);
+        assert_eq!(settings.get_recovery_action(), RecoveryAction::StayDisabled);
+    }
+
+    #[test]
+    fn redacted_password() {
+        let test_dir = tempdir::TempDir::new("test").unwrap();
+        let content = DEFAULT_SETTING_PRETTY.replace(
+            "\"username\": null,\n      \"password\": null",
+            "\"username\": \"user\",\n      \"password\": \"secret\"",
+        );
+        std::fs::write(test_dir.path().join(SETTINGS_FILE), content).unwrap();
+
+        let settings = AppSettings::load_settings(test_dir.path().into());
+        let json = settings.get_settings_json();
+        assert_eq!(json["mqtt"]["username"], "user");
+        assert_eq!(json["mqtt"]["password"], "********");
+        assert!(!json.to_string().contains("secret"));
+    }
+
+    #[test]
+    fn profiles() {
+        let test_dir = tempdir::TempDir::new("test").unwrap();
+        let mut settings = AppSettings::load_settings(test_dir.path().into());
+        settings.set_p_coef(50.0).unwrap();
+        settings.save_profile("slow").unwrap();
+        settings.set_p_coef(200.0).unwrap();
+        assert!(settings.has_profile("slow"));
+        assert!(settings.switch_profile("fast").is_err());
+        assert_eq!(settings.get_p_coef(), 200.0);
+
+        let mut settings = AppSettings::load_settings(test_dir.path().into());
+        settings.switch_profile("slow").unwrap();
+        assert_eq!(settings.get_p_coef(), 50.0);
+    }
+
+    #[test]
+    fn partial_sections() {
+        let test_dir = tempdir::TempDir::new("test").unwrap();
+        let content = DEFAULT_SETTING_PRETTY
+            .replace(
+                "\"heartbeat_interval_ms\": 500,\n      \"timeout_ms\": 5000,",
+                "\"timeout_ms\": 8000,",
+            )
+            .replace("\"debounce_ms\": 2000,\n      ", "")
+            .replace("\"tolerance\": 2.0,\n      ", "");
+        std::fs::write(test_dir.path().join(SETTINGS_FILE), content).unwrap();
+
+        let settings = AppSettings::load_settings(test_dir.path().into());
+        assert_eq!(settings.get_watchdog_timeout_ms(), 8000);
+        assert_eq!(settings.get_heartbeat_interval_ms(), 500);
+        assert_eq!(settings.get_ocp_debounce_ms(), 2000);
+        assert_eq!(settings.get_ocp_response(), OcpResponse::Warn);
+        assert_eq!(settings.get_dew_point_tolerance(), 2.0);
+    }
+
+    #[test]
+    fn calibration() {
+        let test_dir = tempdir::TempDir::new("test").unwrap();
+        let port = Path::new("/dev/ttyUSB0");
+        let calibration = cryo_cooler_controller_lib::Calibration {
+            voltage_factor: 20.0,
+            ..Default::default()
+        };
+        {
+            let mut settings = AppSettings::load_settings(test_dir.path().into());
+            assert_eq!(settings.get_calibration(port), Default::default());
+            settings.set_calibration(port, calibration).unwrap();
+        }
+        {
+            let mut settings = AppSettings::load_settings(test_dir.path().into());
+            assert_eq!(settings.get_calibration(port), calibration);
+            assert_eq!(
+                settings.get_calibration(Path::new("/dev/ttyUSB1")),
+                Default::default()
+            );
+            settings.reset_calibration(port).unwrap();
+            assert_eq!(settings.get_calibration(port), Default::default());
+        }
+    }
+
     #[test]
     fn invalid() {
         let test_dir = tempdir::TempDir::new("test").unwrap();
@@ -0,0 +1,222 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use cryo_cooler_controller_lib::Severity;
+use iced::{
+    alignment,
+    widget::{horizontal_rule, horizontal_space, Column, Container, Row, Text},
+    Alignment, Color, Command, Element, Length,
+};
+
+use crate::{
+    charts::ChartGroup, event_log::EventLog, recording::Recording, settings::AppSettings, Message,
+};
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Shows a recorded log in the charts of the running view, without a connected cooler.
+pub struct ViewerState {
+    path: PathBuf,
+    recording: Recording,
+    /// Oldest and newest value of the recording.
+    span: (DateTime<Utc>, DateTime<Utc>),
+    chart: ChartGroup,
+    event_log: EventLog,
+    error_text: Option<String>,
+    /// Result of the last export.
+    export_text: Option<String>,
+    app_settings: AppSettings,
+}
+
+impl ViewerState {
+    pub fn new(path: &Path, app_settings: AppSettings) -> Result<Self, std::io::Error> {
+        let recording = Recording::open(path)?;
+        let span = recording.span()?.ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "the log contains no data")
+        })?;
+
+        let mut entries: Vec<_> = recording
+            .status_events()?
+            .into_iter()
+            .map(|event| (event.timestamp, event.severity(), event.description()))
+            .chain(
+                recording
+                    .actions()?
+                    .into_iter()
+                    .map(|action| (action.timestamp, Severity::Info, action.description)),
+            )
+            .collect();
+        entries.sort_by_key(|(timestamp, _, _)| *timestamp);
+        let mut event_log = EventLog::default();
+        for (timestamp, severity, text) in entries {
+            event_log.push(timestamp, severity, text);
+        }
+
+        let mut viewer = ViewerState {
+            path: path.to_path_buf(),
+            recording,
+            span,
+            chart: Default::default(),
+            event_log,
+            error_text: None,
+            export_text: None,
+            app_settings,
+        };
+        viewer.chart.set_bounds(span.0, span.1);
+        viewer.load();
+        Ok(viewer)
+    }
+
+    pub fn settings(&self) -> &AppSettings {
+        &self.app_settings
+    }
+
+    pub fn update(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::Tick => {
+                if self.chart.needs_reload() {
+                    self.load();
+                }
+            }
+            Message::ChartZoom(factor, anchor) => self.chart.zoom(factor, anchor),
+            Message::ChartPan(fraction) => self.chart.pan(fraction),
+            Message::ViewerFullRange => {
+                self.chart.set_bounds(self.span.0, self.span.1);
+                self.load();
+            }
+            Message::ExportTargetSelected(target) => self.chart.set_export_target(target),
+            Message::ExportCharts(format) => {
+                let (from, to) = self.chart.view_range();
+                let header = [
+                    format!("Recorded log {}", self.path.display()),
+                    format!(
+                        "Shown {} to {} UTC",
+                        from.format(TIME_FORMAT),
+                        to.format(TIME_FORMAT)
+                    ),
+                    format!("Exported {}", Utc::now().format("%Y-%m-%d %H:%M:%S UTC")),
+                ];
+                self.export_text = Some(
+                    match self
+                        .chart
+                        .export(&self.app_settings.get_export_dir(), format, &header)
+                    {
+                        Ok(path) => format!("Exported to {}", path.display()),
+                        Err(e) => format!("Failed to export charts ({e})"),
+                    },
+                );
+            }
+            _ => {}
+        }
+        Command::none()
+    }
+
+    fn load(&mut self) {
+        let recording = &self.recording;
+        self.error_text = self
+            .chart
+            .show_recorded(|quantity, from, to, max_points| {
+                recording.query(quantity, from, to, max_points)
+            })
+            .err()
+            .map(|e| format!("Failed to read the log ({e})"));
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let content = Row::new().spacing(20).push(self.view_left_column()).push(
+            Column::new()
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .push(iced::widget::vertical_space(Length::Fixed(5.0)))
+                .push(self.chart.view()),
+        );
+
+        Container::new(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .padding(2)
+            .center_x()
+            .center_y()
+            .into()
+    }
+
+    fn view_left_column(&self) -> Element<'_, Message> {
+        let button = |label, message| {
+            iced::widget::button(
+                iced::widget::text(label).horizontal_alignment(alignment::Horizontal::Center),
+            )
+            .width(Length::Fixed(60.0))
+            .style(iced::theme::Button::Secondary)
+            .on_press(message)
+        };
+        let file_name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let (from, to) = self.chart.view_range();
+
+        let mut col = Column::new()
+            .spacing(5)
+            .width(Length::Fixed(280.0))
+            .push(Text::new(file_name).size(24))
+            .push(Text::new(format!(
+                "From {}",
+                self.span.0.format(TIME_FORMAT)
+            )))
+            .push(Text::new(format!("To {}", self.span.1.format(TIME_FORMAT))))
+            .push(horizontal_rule(20))
+            .push(Text::new("Shown"))
+            .push(Text::new(from.format(TIME_FORMAT).to_string()).size(14))
+            .push(Text::new(to.format(TIME_FORMAT).to_string()).size(14))
+            .push(
+                Row::new()
+                    .spacing(5)
+                    .padding(5)
+                    .align_items(Alignment::Center)
+                    .push(button("<", Message::ChartPan(0.5)))
+                    .push(button("-", Message::ChartZoom(2.0, 0.5)))
+                    .push(button("+", Message::ChartZoom(0.5, 0.5)))
+                    .push(button(">", Message::ChartPan(-0.5))),
+            )
+            .push(
+                Row::new()
+                    .padding(5)
+                    .push(horizontal_space(Length::Fill))
+                    .push(
+                        iced::widget::button(Text::new("Full Range"))
+                            .style(iced::theme::Button::Secondary)
+                            .on_press(Message::ViewerFullRange),
+                    )
+                    .push(horizontal_space(Length::Fill)),
+            )
+            .push(horizontal_rule(20))
+            .push(Text::new("Export"))
+            .push(self.chart.view_export_controls().padding(5));
+        if let Some(text) = &self.export_text {
+            col = col.push(Text::new(text).size(14));
+        }
+        if let Some(error) = &self.error_text {
+            col = col.push(Text::new(error).style(Color::from_rgb(
+                0xC3 as f32 / 255.0,
+                0x42 as f32 / 255.0,
+                0x3F as f32 / 255.0,
+            )));
+        }
+        col.push(horizontal_rule(20))
+            .push(self.event_log.view(Length::Fill))
+            .push(
+                Column::new()
+                    .push(
+                        iced::widget::button("Close Log")
+                            .style(iced::theme::Button::Primary)
+                            .on_press(Message::CloseViewer)
+                            .width(Length::Fixed(150.0)),
+                    )
+                    .padding(15)
+                    .align_items(Alignment::Center)
+                    .width(Length::Fill),
+            )
+            .into()
+    }
+}
@@ -0,0 +1,71 @@
+/// Conversion from the raw ADC readings of the cooler to volt and ampere.
+///
+/// `value = raw / factor + offset`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    pub voltage_factor: f32,
+    pub voltage_offset: f32,
+    pub current_factor: f32,
+    pub current_offset: f32,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self {
+            voltage_factor: 21.1,
+            voltage_offset: 0.0,
+            current_factor: 4.6545,
+            current_offset: 0.0,
+        }
+    }
+}
+
+impl Calibration {
+    pub fn voltage(&self, raw: u32) -> f32 {
+        raw as f32 / self.voltage_factor + self.voltage_offset
+    }
+
+    pub fn current(&self, raw: u32) -> f32 {
+        raw as f32 / self.current_factor + self.current_offset
+    }
+
+    /// Computes the factor that maps `raw` to the externally `measured` value.
+    ///
+    /// Returns `None` if no positive factor satisfies the measurement.
+    pub fn factor_for(raw: u32, measured: f32, offset: f32) -> Option<f32> {
+        let factor = raw as f32 / (measured - offset);
+        (raw != 0 && factor.is_finite() && factor > 0.0).then_some(factor)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_matches_previous_constants() {
+        let calibration = Calibration::default();
+        assert_eq!(calibration.voltage(211), 211.0 / 21.1);
+        assert_eq!(calibration.current(46545), 46545.0 / 4.6545);
+    }
+
+    #[test]
+    fn factor_for() {
+        let factor = Calibration::factor_for(250, 12.5, 0.0).unwrap();
+        assert_eq!(factor, 20.0);
+        let calibration = Calibration {
+            voltage_factor: factor,
+            ..Default::default()
+        };
+        assert_eq!(calibration.voltage(250), 12.5);
+
+        assert_eq!(
+            Calibration::factor_for(250, 12.5, 0.5).unwrap(),
+            250.0 / 12.0
+        );
+        assert_eq!(Calibration::factor_for(0, 12.5, 0.0), None);
+        assert_eq!(Calibration::factor_for(250, 0.0, 0.0), None);
+        assert_eq!(Calibration::factor_for(250, 1.0, 2.0), None);
+    }
+}
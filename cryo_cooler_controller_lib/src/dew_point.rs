@@ -0,0 +1,62 @@
+use crate::MonitoringData;
+
+// Magnus formula coefficients for water, valid from -45 C to 60 C.
+const MAGNUS_A: f32 = 17.62;
+const MAGNUS_B: f32 = 243.12;
+
+/// Computes the dew point in C from a temperature in C and a relative humidity in %.
+pub fn dew_point(temperature: f32, relative_humidity: f32) -> Option<f32> {
+    if !(relative_humidity > 0.0 && relative_humidity <= 100.0) {
+        return None;
+    }
+    let gamma =
+        (relative_humidity / 100.0).ln() + MAGNUS_A * temperature / (MAGNUS_B + temperature);
+    let dew_point = MAGNUS_B * gamma / (MAGNUS_A - gamma);
+    dew_point.is_finite().then_some(dew_point)
+}
+
+/// Offset relative to `device_dew_point` for the cooler to regulate to the absolute `target`.
+///
+/// The resulting target never drops below `reference_dew_point + safety_margin`.
+pub fn offset_for_target(
+    target: f32,
+    reference_dew_point: f32,
+    device_dew_point: f32,
+    safety_margin: f32,
+) -> f32 {
+    target.max(reference_dew_point + safety_margin) - device_dew_point
+}
+
+impl MonitoringData {
+    /// Dew point computed from the humidity and the board temperature.
+    pub fn host_dew_point(&self) -> Option<f32> {
+        dew_point(self.pcb_temperature, self.humidity)
+    }
+
+    /// Difference between the host computed and the reported dew point.
+    pub fn dew_point_deviation(&self) -> Option<f32> {
+        Some(self.host_dew_point()? - self.dew_point_temperature)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn magnus() {
+        assert!((dew_point(25.0, 50.0).unwrap() - 13.85).abs() < 0.05);
+        assert!((dew_point(20.0, 100.0).unwrap() - 20.0).abs() < 0.01);
+        assert!((dew_point(30.0, 80.0).unwrap() - 26.17).abs() < 0.05);
+        assert_eq!(dew_point(25.0, 0.0), None);
+        assert_eq!(dew_point(25.0, f32::NAN), None);
+    }
+
+    #[test]
+    fn target_offset() {
+        assert_eq!(offset_for_target(20.0, 12.0, 12.0, 2.0), 8.0);
+        assert_eq!(offset_for_target(10.0, 12.0, 12.0, 2.0), 2.0);
+        assert_eq!(offset_for_target(10.0, 13.0, 12.0, 2.0), 3.0);
+    }
+}
@@ -0,0 +1,321 @@
+use chrono::{DateTime, Utc};
+
+use crate::TecStatus;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Fault,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Severity::Info => write!(f, "INFO"),
+            Severity::Warning => write!(f, "WARNING"),
+            Severity::Fault => write!(f, "FAULT"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FlagChange {
+    Set,
+    Cleared,
+}
+
+/// A single flag of [`TecStatus`] changing between two heartbeats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatusEvent {
+    pub timestamp: DateTime<Utc>,
+    pub flag: TecStatus,
+    pub change: FlagChange,
+}
+
+struct FlagInfo {
+    flag: TecStatus,
+    severity: Severity,
+    /// Whether the set flag describes the normal operating state.
+    healthy_when_set: bool,
+    set_text: &'static str,
+    cleared_text: &'static str,
+}
+
+const FLAG_INFO: &[FlagInfo] = &[
+    FlagInfo {
+        flag: TecStatus::BOARD_INIT,
+        severity: Severity::Warning,
+        healthy_when_set: true,
+        set_text: "Board initialized",
+        cleared_text: "Board is no longer initialized",
+    },
+    FlagInfo {
+        flag: TecStatus::POWER_OK,
+        severity: Severity::Fault,
+        healthy_when_set: true,
+        set_text: "TEC power restored",
+        cleared_text: "TEC lost power",
+    },
+    FlagInfo {
+        flag: TecStatus::TEMP_SENSE_OK,
+        severity: Severity::Fault,
+        healthy_when_set: true,
+        set_text: "Temperature sensor recovered",
+        cleared_text: "Temperature sensor error",
+    },
+    FlagInfo {
+        flag: TecStatus::HUM_SENSE_OK,
+        severity: Severity::Fault,
+        healthy_when_set: true,
+        set_text: "Humidity sensor recovered",
+        cleared_text: "Humidity sensor error",
+    },
+    FlagInfo {
+        flag: TecStatus::LAST_CMD_OK,
+        severity: Severity::Info,
+        healthy_when_set: true,
+        set_text: "Last command accepted",
+        cleared_text: "Last command not accepted",
+    },
+    FlagInfo {
+        flag: TecStatus::LAST_CMD_BAD_CRC,
+        severity: Severity::Warning,
+        healthy_when_set: false,
+        set_text: "Last command had a bad CRC",
+        cleared_text: "Bad CRC condition cleared",
+    },
+    FlagInfo {
+        flag: TecStatus::LAST_CMD_INCOMPLETE,
+        severity: Severity::Warning,
+        healthy_when_set: false,
+        set_text: "Last command was incomplete",
+        cleared_text: "Incomplete command condition cleared",
+    },
+    FlagInfo {
+        flag: TecStatus::FAILSAFE_ACTIVE,
+        severity: Severity::Fault,
+        healthy_when_set: false,
+        set_text: "Failsafe activated",
+        cleared_text: "Failsafe cleared",
+    },
+    FlagInfo {
+        flag: TecStatus::PID_READY,
+        severity: Severity::Info,
+        healthy_when_set: true,
+        set_text: "PID ready",
+        cleared_text: "PID not ready",
+    },
+    FlagInfo {
+        flag: TecStatus::PID_INVALID,
+        severity: Severity::Fault,
+        healthy_when_set: false,
+        set_text: "PID values invalid",
+        cleared_text: "PID values valid again",
+    },
+    FlagInfo {
+        flag: TecStatus::PID_OUT_OF_RANGE,
+        severity: Severity::Warning,
+        healthy_when_set: false,
+        set_text: "PID values out of range",
+        cleared_text: "PID values back in range",
+    },
+    FlagInfo {
+        flag: TecStatus::PID_DEFAULT,
+        severity: Severity::Info,
+        healthy_when_set: false,
+        set_text: "PID uses default values",
+        cleared_text: "PID uses custom values",
+    },
+    FlagInfo {
+        flag: TecStatus::PID_RUNNING,
+        severity: Severity::Info,
+        healthy_when_set: true,
+        set_text: "PID started",
+        cleared_text: "PID stopped",
+    },
+    FlagInfo {
+        flag: TecStatus::OCP_ACTIVE,
+        severity: Severity::Fault,
+        healthy_when_set: false,
+        set_text: "Over current protection active",
+        cleared_text: "Over current protection cleared",
+    },
+    FlagInfo {
+        flag: TecStatus::BOARD_TEMP_OK,
+        severity: Severity::Fault,
+        healthy_when_set: true,
+        set_text: "Board temperature normal",
+        cleared_text: "Board temperature too high",
+    },
+    FlagInfo {
+        flag: TecStatus::TEC_CONN_OK,
+        severity: Severity::Fault,
+        healthy_when_set: true,
+        set_text: "TEC connected",
+        cleared_text: "TEC disconnected",
+    },
+    FlagInfo {
+        flag: TecStatus::LOW_POWER_MODE_ACTIVE,
+        severity: Severity::Info,
+        healthy_when_set: false,
+        set_text: "TEC disabled",
+        cleared_text: "TEC enabled",
+    },
+    FlagInfo {
+        flag: TecStatus::TEMP_MODE,
+        severity: Severity::Info,
+        healthy_when_set: false,
+        set_text: "Temperature mode active",
+        cleared_text: "Temperature mode inactive",
+    },
+];
+
+fn flag_info(flag: TecStatus) -> Option<&'static FlagInfo> {
+    FLAG_INFO.iter().find(|info| info.flag == flag)
+}
+
+impl TecStatus {
+    /// Severity of a single flag being in its abnormal state.
+    pub fn severity(self) -> Severity {
+        flag_info(self).map_or(Severity::Warning, |info| info.severity)
+    }
+
+    /// Whether a single flag describes the normal operating state while it is set.
+    pub fn is_healthy_when_set(self) -> bool {
+        flag_info(self).is_some_and(|info| info.healthy_when_set)
+    }
+
+    /// Highest severity of all flags that are in their abnormal state.
+    ///
+    /// Returns `None` if no flag above [`Severity::Info`] is abnormal.
+    pub fn worst_severity(self) -> Option<Severity> {
+        FLAG_INFO
+            .iter()
+            .filter(|info| self.contains(info.flag) != info.healthy_when_set)
+            .map(|info| info.severity)
+            .filter(|&severity| severity > Severity::Info)
+            .max()
+    }
+
+    /// Returns one event for every flag that differs between `self` and `current`.
+    pub fn diff(self, current: TecStatus, timestamp: DateTime<Utc>) -> Vec<StatusEvent> {
+        (self ^ current)
+            .iter()
+            .map(|flag| StatusEvent {
+                timestamp,
+                flag,
+                change: if current.contains(flag) {
+                    FlagChange::Set
+                } else {
+                    FlagChange::Cleared
+                },
+            })
+            .collect()
+    }
+}
+
+impl StatusEvent {
+    /// Whether the flag changed into its abnormal state.
+    pub fn is_fault_transition(&self) -> bool {
+        (self.change == FlagChange::Set) != self.flag.is_healthy_when_set()
+    }
+
+    /// Severity of the flag if it entered its abnormal state, [`Severity::Info`] otherwise.
+    pub fn severity(&self) -> Severity {
+        if self.is_fault_transition() {
+            self.flag.severity()
+        } else {
+            Severity::Info
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match (flag_info(self.flag), self.change) {
+            (Some(info), FlagChange::Set) => info.set_text.to_owned(),
+            (Some(info), FlagChange::Cleared) => info.cleared_text.to_owned(),
+            (None, FlagChange::Set) => format!("Unknown status bits {:#x} set", self.flag.bits()),
+            (None, FlagChange::Cleared) => {
+                format!("Unknown status bits {:#x} cleared", self.flag.bits())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::StatusWord;
+
+    #[test]
+    fn every_flag_described() {
+        for flag in TecStatus::all().iter() {
+            assert!(flag_info(flag).is_some());
+        }
+    }
+
+    #[test]
+    fn diff() {
+        let now = Utc::now();
+        let previous = TecStatus::POWER_OK | TecStatus::PID_RUNNING;
+        let current = TecStatus::PID_RUNNING | TecStatus::OCP_ACTIVE;
+        let events = previous.diff(current, now);
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].flag, TecStatus::POWER_OK);
+        assert_eq!(events[0].change, FlagChange::Cleared);
+        assert_eq!(events[0].severity(), Severity::Fault);
+        assert_eq!(events[1].flag, TecStatus::OCP_ACTIVE);
+        assert_eq!(events[1].change, FlagChange::Set);
+        assert_eq!(events[1].severity(), Severity::Fault);
+
+        let recovered = current.diff(previous, now);
+        assert!(recovered.iter().all(|e| e.severity() == Severity::Info));
+        assert!(previous.diff(previous, now).is_empty());
+    }
+
+    #[test]
+    fn worst_severity() {
+        let healthy = TecStatus::BOARD_INIT
+            | TecStatus::POWER_OK
+            | TecStatus::TEMP_SENSE_OK
+            | TecStatus::HUM_SENSE_OK
+            | TecStatus::LAST_CMD_OK
+            | TecStatus::PID_READY
+            | TecStatus::PID_RUNNING
+            | TecStatus::BOARD_TEMP_OK
+            | TecStatus::TEC_CONN_OK;
+        assert_eq!(healthy.worst_severity(), None);
+        assert_eq!(
+            (healthy | TecStatus::LOW_POWER_MODE_ACTIVE).worst_severity(),
+            None
+        );
+        assert_eq!(
+            (healthy | TecStatus::PID_OUT_OF_RANGE).worst_severity(),
+            Some(Severity::Warning)
+        );
+        assert_eq!(
+            ((healthy - TecStatus::POWER_OK) | TecStatus::PID_OUT_OF_RANGE).worst_severity(),
+            Some(Severity::Fault)
+        );
+    }
+
+    #[test]
+    fn unknown_bits() {
+        let now = Utc::now();
+        let previous = StatusWord::new(TecStatus::POWER_OK.bits());
+        let current = StatusWord::new(TecStatus::POWER_OK.bits() | 1 << 20 | 1 << 31);
+        assert_eq!(current.status(), TecStatus::POWER_OK);
+        assert_eq!(current.unknown_bits(), 1 << 20 | 1 << 31);
+
+        let events = previous.diff(current, now);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].change, FlagChange::Set);
+        assert_eq!(events[0].severity(), Severity::Warning);
+        assert_eq!(
+            events[0].description(),
+            "Unknown status bits 0x80100000 set"
+        );
+    }
+}
@@ -18,18 +18,49 @@ use std::{
     convert::TryInto,
     io::{Read, Write},
 };
+
+mod calibration;
+mod dew_point;
+mod events;
+mod ocp;
+mod self_test;
+mod stats;
+mod verify;
+mod watchdog;
+pub use calibration::Calibration;
+pub use dew_point::{dew_point, offset_for_target};
+pub use events::{FlagChange, Severity, StatusEvent};
+pub use ocp::{OcpConfig, OcpEvent, OcpFilter, OcpState};
+pub use self_test::{CheckResult, SelfTest, SelfTestConfig, SelfTestReport, SelfTestStep};
+pub use stats::{LatencyHistogram, ProtocolStats, LATENCY_BUCKETS};
+pub use verify::{DeviceParameters, Parameter, VerifyConfig, VerifyError};
+pub use watchdog::{Watchdog, WatchdogConfig, WatchdogEvent};
+
 const CRC_16_XMODEM: crc::Crc<u16> = crc::Crc::<u16>::new(&crc::CRC_16_XMODEM);
 pub struct Tec {
     port: serial::SystemPort,
     port_ident: std::ffi::OsString,
+    calibration: Calibration,
+    verify: Option<VerifyConfig>,
+    stats: ProtocolStats,
 }
 
 impl Tec {
     fn send_cmd(&mut self, request: &Request) -> Result<Response, std::io::Error> {
-        self.port.write_all(&request.as_bytes())?;
+        self.stats.commands_sent += 1;
+        let sent = std::time::Instant::now();
         let mut buffer = [0u8; 8];
-        self.port.read_exact(&mut buffer)?;
+        if let Err(e) = self
+            .port
+            .write_all(&request.as_bytes())
+            .and_then(|()| self.port.read_exact(&mut buffer))
+        {
+            self.stats.io_errors += 1;
+            return Err(e);
+        }
+        self.stats.latency.observe(sent.elapsed());
         if buffer[1] != { request.op_code + 127 } {
+            self.stats.op_code_mismatches += 1;
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
                 "Response contained incorrect op code",
@@ -41,6 +72,7 @@ impl Tec {
         if response.crc == crc {
             Ok(response)
         } else {
+            self.stats.crc_errors += 1;
             Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
                 "Response contained incorrect crc",
@@ -53,13 +85,66 @@ impl Tec {
         Ok(())
     }
 
-    fn set_pid(&mut self, p: f32, i: f32, d: f32) -> Result<(), std::io::Error> {
-        self.send_cmd(&Request::new(commands::set::P_COEFFICIENT, p.to_le_bytes()))?;
-        self.send_cmd(&Request::new(commands::set::I_COEFFICIENT, i.to_le_bytes()))?;
-        self.send_cmd(&Request::new(commands::set::D_COEFFICIENT, d.to_le_bytes()))?;
+    /// Writes the PID coefficients without changing whether the TEC is enabled.
+    pub fn set_pid(&mut self, p: f32, i: f32, d: f32) -> Result<(), std::io::Error> {
+        self.set_parameter(Parameter::P, p)?;
+        self.set_parameter(Parameter::I, i)?;
+        self.set_parameter(Parameter::D, d)?;
+
+        if self.verify.is_some() {
+            VerifyError::check_pid_flags(self.heart_beat()?)?;
+        }
 
         Ok(())
     }
+
+    pub fn parameter(&mut self, parameter: Parameter) -> Result<f32, std::io::Error> {
+        match parameter {
+            Parameter::SetpointOffset => self.setpoint_offset(),
+            Parameter::P => self.p_coefficient(),
+            Parameter::I => self.i_coefficient(),
+            Parameter::D => self.d_coefficient(),
+        }
+    }
+
+    /// Writes a single value without changing whether the TEC is enabled.
+    ///
+    /// If verification is enabled, the value is read back until it matches.
+    pub fn set_parameter(
+        &mut self,
+        parameter: Parameter,
+        value: f32,
+    ) -> Result<(), std::io::Error> {
+        let op_code = match parameter {
+            Parameter::SetpointOffset => commands::set::POINT_OFFSET,
+            Parameter::P => commands::set::P_COEFFICIENT,
+            Parameter::I => commands::set::I_COEFFICIENT,
+            Parameter::D => commands::set::D_COEFFICIENT,
+        };
+        let request = Request::new(op_code, value.to_le_bytes());
+        self.send_cmd(&request)?;
+
+        let Some(verify) = self.verify else {
+            return Ok(());
+        };
+        let mut attempt = 0;
+        loop {
+            let read = self.parameter(parameter)?;
+            if verify.accepts(parameter, value, read) {
+                return Ok(());
+            }
+            if attempt >= verify.retries {
+                return Err(VerifyError::Mismatch {
+                    parameter,
+                    written: value,
+                    read,
+                }
+                .into());
+            }
+            attempt += 1;
+            self.send_cmd(&request)?;
+        }
+    }
 }
 
 fn open_serial_port<T: AsRef<std::ffi::OsStr>>(
@@ -78,6 +163,7 @@ fn open_serial_port<T: AsRef<std::ffi::OsStr>>(
 }
 impl Tec {
     pub fn reset_connection(&mut self) -> Result<(), std::io::Error> {
+        self.stats.reconnects += 1;
         self.port = open_serial_port(&self.port_ident)?;
         Ok(())
     }
@@ -87,6 +173,9 @@ impl Tec {
         let mut tec = Tec {
             port,
             port_ident: serial_port.into(),
+            calibration: Calibration::default(),
+            verify: None,
+            stats: ProtocolStats::default(),
         };
 
         let status = tec.heart_beat()?;
@@ -98,14 +187,13 @@ impl Tec {
     }
 
     pub fn heart_beat(&mut self) -> Result<TecStatus, std::io::Error> {
+        Ok(self.status_word()?.status())
+    }
+
+    /// Sends a heartbeat and returns the complete status word including unknown bits.
+    pub fn status_word(&mut self) -> Result<StatusWord, std::io::Error> {
         let response = self.send_cmd(&Request::new(commands::HEART_BEAT, [0; 4]))?;
-        let status_code = u32::from_le_bytes(response.data);
-        TecStatus::from_bits(status_code & 0b111111111111111111).ok_or_else(|| {
-            std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                format!("Tecstatus bit pattern invalid: {status_code:b} "),
-            )
-        })
+        Ok(StatusWord::new(u32::from_le_bytes(response.data)))
     }
 
     pub fn monitor(&mut self) -> Result<MonitoringData, std::io::Error> {
@@ -140,14 +228,50 @@ impl Tec {
         Ok(f32::from_le_bytes(response.data))
     }
 
+    pub fn stats(&self) -> &ProtocolStats {
+        &self.stats
+    }
+
+    pub fn calibration(&self) -> Calibration {
+        self.calibration
+    }
+
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        self.calibration = calibration;
+    }
+
+    pub fn verify_config(&self) -> Option<VerifyConfig> {
+        self.verify
+    }
+
+    /// Enables reading back the setpoint offset and P/I/D after writing them.
+    ///
+    /// Failed verifications are returned as [`std::io::ErrorKind::InvalidData`]
+    /// containing a [`VerifyError`].
+    pub fn set_verify_config(&mut self, verify: Option<VerifyConfig>) {
+        self.verify = verify;
+    }
+
     pub fn tec_voltage(&mut self) -> Result<f32, std::io::Error> {
+        let raw = self.tec_voltage_raw()?;
+        Ok(self.calibration.voltage(raw))
+    }
+
+    /// Uncalibrated voltage reading, see [`Calibration`].
+    pub fn tec_voltage_raw(&mut self) -> Result<u32, std::io::Error> {
         let response = self.send_cmd(&Request::new(commands::get::TEC_VOLTAGE, [0; 4]))?;
-        Ok(u32::from_le_bytes(response.data) as f32 / 21.1)
+        Ok(u32::from_le_bytes(response.data))
     }
 
     pub fn tec_current(&mut self) -> Result<f32, std::io::Error> {
+        let raw = self.tec_current_raw()?;
+        Ok(self.calibration.current(raw))
+    }
+
+    /// Uncalibrated current reading, see [`Calibration`].
+    pub fn tec_current_raw(&mut self) -> Result<u32, std::io::Error> {
         let response = self.send_cmd(&Request::new(commands::get::TEC_CURRENT, [0; 4]))?;
-        Ok(u32::from_le_bytes(response.data) as f32 / 4.6545)
+        Ok(u32::from_le_bytes(response.data))
     }
 
     pub fn tec_power_level(&mut self) -> Result<u8, std::io::Error> {
@@ -171,12 +295,7 @@ impl Tec {
     }
 
     pub fn set_setpoint_offset(&mut self, setpoint: f32) -> Result<(), std::io::Error> {
-        self.send_cmd(&Request::new(
-            commands::set::POINT_OFFSET,
-            setpoint.to_le_bytes(),
-        ))?;
-
-        Ok(())
+        self.set_parameter(Parameter::SetpointOffset, setpoint)
     }
 
     pub fn setpoint_offset(&mut self) -> Result<f32, std::io::Error> {
@@ -184,6 +303,16 @@ impl Tec {
         Ok(u32::from_le_bytes(response.data) as f32)
     }
 
+    /// Reads the setpoint offset and P/I/D the cooler currently uses.
+    pub fn read_parameters(&mut self) -> Result<DeviceParameters, std::io::Error> {
+        Ok(DeviceParameters {
+            setpoint_offset: self.setpoint_offset()?,
+            p: self.p_coefficient()?,
+            i: self.i_coefficient()?,
+            d: self.d_coefficient()?,
+        })
+    }
+
     pub fn hw_version(&mut self) -> Result<u32, std::io::Error> {
         let response = self.send_cmd(&Request::new(commands::get::HW_VERSION, [0; 4]))?;
         Ok(u32::from_le_bytes(response.data))
@@ -261,6 +390,41 @@ bitflags::bitflags! {
     }
 }
 
+/// Raw status word reported by the heartbeat.
+///
+/// Newer firmware may set bits that are not known to [`TecStatus`], these are kept
+/// instead of being dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct StatusWord {
+    raw: u32,
+}
+
+impl StatusWord {
+    pub const fn new(raw: u32) -> Self {
+        StatusWord { raw }
+    }
+
+    pub const fn raw(self) -> u32 {
+        self.raw
+    }
+
+    /// Known flags of the status word.
+    pub const fn status(self) -> TecStatus {
+        TecStatus::from_bits_truncate(self.raw)
+    }
+
+    /// Bits that are set but not known to [`TecStatus`].
+    pub const fn unknown_bits(self) -> u32 {
+        self.raw & !TecStatus::all().bits()
+    }
+
+    /// Like [`TecStatus::diff`], unknown bits are reported as a combined flag.
+    pub fn diff(self, current: StatusWord, timestamp: chrono::DateTime<Utc>) -> Vec<StatusEvent> {
+        TecStatus::from_bits_retain(self.raw)
+            .diff(TecStatus::from_bits_retain(current.raw), timestamp)
+    }
+}
+
 #[repr(C)]
 #[derive(Debug)]
 pub struct Request {
@@ -355,6 +519,7 @@ mod commands {
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct MonitoringData {
     pub timestamp: chrono::DateTime<Utc>,
     pub tec_temperature: f32,
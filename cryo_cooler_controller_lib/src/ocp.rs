@@ -0,0 +1,195 @@
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OcpConfig {
+    /// How long [`crate::TecStatus::OCP_ACTIVE`] has to stay set before it is acted upon.
+    pub debounce: Duration,
+    /// Minimum TEC current in ampere that has to be measured while the flag is set
+    /// for the over current to be considered real.
+    pub current_threshold: f32,
+}
+
+impl Default for OcpConfig {
+    fn default() -> Self {
+        Self {
+            debounce: Duration::from_secs(2),
+            current_threshold: 6.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OcpState {
+    /// The flag is not set.
+    #[default]
+    Clear,
+    /// The flag is set but the debounce window has not passed yet.
+    Pending,
+    /// The flag is set but was not confirmed by the measured current.
+    Spurious,
+    /// The flag stayed set for the debounce window and the current confirmed it.
+    Sustained,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OcpEvent {
+    /// The flag was set without a matching over current. Reported once per occurrence.
+    Spurious { peak_current: f32 },
+    /// A real over current lasted for the debounce window.
+    SustainedStarted { peak_current: f32 },
+    /// The flag of a sustained over current was cleared.
+    SustainedCleared,
+}
+
+/// Filters the OCP flag reported by the cooler, which is known to be set occasionally
+/// while the cooler works as expected.
+#[derive(Debug, Clone)]
+pub struct OcpFilter {
+    config: OcpConfig,
+    state: OcpState,
+    flag_since: Option<Instant>,
+    peak_current: f32,
+    last_current: f32,
+}
+
+impl OcpFilter {
+    pub fn new(config: OcpConfig) -> Self {
+        Self {
+            config,
+            state: OcpState::Clear,
+            flag_since: None,
+            peak_current: 0.0,
+            last_current: 0.0,
+        }
+    }
+
+    pub fn config(&self) -> OcpConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: OcpConfig) {
+        self.config = config;
+    }
+
+    pub fn state(&self) -> OcpState {
+        self.state
+    }
+
+    /// Feeds the latest flag and current measurement into the filter.
+    pub fn update(&mut self, ocp_active: bool, tec_current: f32, now: Instant) -> Option<OcpEvent> {
+        //! The current measured right before the flag was set is taken into account,
+        //! because the cooler may already have limited the current when the flag is reported.
+        let previous_current = self.last_current;
+        self.last_current = tec_current;
+
+        if !ocp_active {
+            let event = match self.state {
+                OcpState::Pending => Some(OcpEvent::Spurious {
+                    peak_current: self.peak_current,
+                }),
+                OcpState::Sustained => Some(OcpEvent::SustainedCleared),
+                OcpState::Clear | OcpState::Spurious => None,
+            };
+            self.state = OcpState::Clear;
+            self.flag_since = None;
+            return event;
+        }
+
+        let since = match self.flag_since {
+            Some(since) => {
+                self.peak_current = self.peak_current.max(tec_current);
+                since
+            }
+            None => {
+                self.peak_current = previous_current.max(tec_current);
+                self.flag_since = Some(now);
+                now
+            }
+        };
+
+        match self.state {
+            OcpState::Clear | OcpState::Pending => {
+                if now.saturating_duration_since(since) < self.config.debounce {
+                    self.state = OcpState::Pending;
+                    None
+                } else if self.peak_current >= self.config.current_threshold {
+                    self.state = OcpState::Sustained;
+                    Some(OcpEvent::SustainedStarted {
+                        peak_current: self.peak_current,
+                    })
+                } else {
+                    self.state = OcpState::Spurious;
+                    Some(OcpEvent::Spurious {
+                        peak_current: self.peak_current,
+                    })
+                }
+            }
+            OcpState::Spurious => {
+                if self.peak_current >= self.config.current_threshold {
+                    self.state = OcpState::Sustained;
+                    Some(OcpEvent::SustainedStarted {
+                        peak_current: self.peak_current,
+                    })
+                } else {
+                    None
+                }
+            }
+            OcpState::Sustained => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transient_flag_is_spurious() {
+        let start = Instant::now();
+        let mut filter = OcpFilter::new(OcpConfig::default());
+        assert_eq!(filter.update(true, 8.0, start), None);
+        assert_eq!(filter.state(), OcpState::Pending);
+        assert_eq!(
+            filter.update(false, 8.0, start + Duration::from_millis(500)),
+            Some(OcpEvent::Spurious { peak_current: 8.0 })
+        );
+        assert_eq!(filter.state(), OcpState::Clear);
+    }
+
+    #[test]
+    fn low_current_is_spurious() {
+        let start = Instant::now();
+        let mut filter = OcpFilter::new(OcpConfig::default());
+        assert_eq!(filter.update(true, 1.0, start), None);
+        assert_eq!(
+            filter.update(true, 1.5, start + Duration::from_secs(3)),
+            Some(OcpEvent::Spurious { peak_current: 1.5 })
+        );
+        assert_eq!(
+            filter.update(true, 1.0, start + Duration::from_secs(4)),
+            None
+        );
+        assert_eq!(filter.state(), OcpState::Spurious);
+        assert_eq!(
+            filter.update(false, 1.0, start + Duration::from_secs(5)),
+            None
+        );
+    }
+
+    #[test]
+    fn sustained() {
+        let start = Instant::now();
+        let mut filter = OcpFilter::new(OcpConfig::default());
+        assert_eq!(filter.update(false, 9.0, start), None);
+        assert_eq!(filter.update(true, 0.5, start), None);
+        assert_eq!(
+            filter.update(true, 0.5, start + Duration::from_secs(2)),
+            Some(OcpEvent::SustainedStarted { peak_current: 9.0 })
+        );
+        assert_eq!(filter.state(), OcpState::Sustained);
+        assert_eq!(
+            filter.update(false, 0.5, start + Duration::from_secs(3)),
+            Some(OcpEvent::SustainedCleared)
+        );
+    }
+}
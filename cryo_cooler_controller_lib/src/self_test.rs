@@ -0,0 +1,351 @@
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+use crate::{MonitoringData, Tec, TecStatus};
+
+/// Flags that have to be set on a healthy cooler.
+const REQUIRED_FLAGS: &[(TecStatus, &str)] = &[
+    (TecStatus::POWER_OK, "Power"),
+    (TecStatus::TEMP_SENSE_OK, "Temperature sensor"),
+    (TecStatus::HUM_SENSE_OK, "Humidity sensor"),
+    (TecStatus::TEC_CONN_OK, "TEC connection"),
+    (TecStatus::BOARD_TEMP_OK, "Board temperature"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelfTestConfig {
+    pub p: f32,
+    pub i: f32,
+    pub d: f32,
+    pub power_level: u8,
+    pub setpoint: f32,
+    /// How long the TEC stays enabled during the test.
+    pub cycle_duration: Duration,
+    /// Minimum rise of the TEC current in ampere while enabled.
+    pub min_current_rise: f32,
+    /// Minimum drop of the TEC temperature in C while enabled.
+    pub min_temperature_drop: f32,
+}
+
+impl Default for SelfTestConfig {
+    fn default() -> Self {
+        Self {
+            p: 100.0,
+            i: 1.0,
+            d: 1.0,
+            power_level: 100,
+            setpoint: 2.0,
+            cycle_duration: Duration::from_secs(10),
+            min_current_rise: 0.5,
+            min_temperature_drop: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckResult {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+impl CheckResult {
+    fn new(name: impl Into<String>, passed: bool, detail: String) -> Self {
+        Self {
+            name: name.into(),
+            passed,
+            detail,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfTestReport {
+    pub started: DateTime<Utc>,
+    pub finished: Option<DateTime<Utc>>,
+    pub checks: Vec<CheckResult>,
+}
+
+impl SelfTestReport {
+    /// Whether the test finished and every check passed.
+    pub fn passed(&self) -> bool {
+        self.finished.is_some() && self.checks.iter().all(|check| check.passed)
+    }
+}
+
+impl std::fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "Cryo cooler self-test")?;
+        writeln!(f, "Started:  {}", self.started.to_rfc3339())?;
+        match self.finished {
+            Some(finished) => writeln!(f, "Finished: {}", finished.to_rfc3339())?,
+            None => writeln!(f, "Finished: aborted")?,
+        }
+        writeln!(f)?;
+        for check in &self.checks {
+            writeln!(
+                f,
+                "[{}] {}: {}",
+                if check.passed { "PASS" } else { "FAIL" },
+                check.name,
+                check.detail
+            )?;
+        }
+        writeln!(f)?;
+        write!(f, "Result: {}", if self.passed() { "PASS" } else { "FAIL" })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelfTestStep {
+    Heartbeat,
+    Enable,
+    Cycle,
+    Finished,
+}
+
+/// Self-test that is advanced step by step, so it can be driven by a periodic tick.
+///
+/// The TEC is disabled when the test finishes, fails to communicate or is aborted.
+#[derive(Debug, Clone)]
+pub struct SelfTest {
+    config: SelfTestConfig,
+    step: SelfTestStep,
+    report: SelfTestReport,
+    baseline: Option<MonitoringData>,
+    cycle_start: Option<Instant>,
+    peak_current: f32,
+    min_temperature: f32,
+}
+
+impl SelfTest {
+    pub fn new(config: SelfTestConfig) -> Self {
+        Self {
+            config,
+            step: SelfTestStep::Heartbeat,
+            report: SelfTestReport {
+                started: Utc::now(),
+                finished: None,
+                checks: Vec::new(),
+            },
+            baseline: None,
+            cycle_start: None,
+            peak_current: f32::MIN,
+            min_temperature: f32::MAX,
+        }
+    }
+
+    pub fn step(&self) -> SelfTestStep {
+        self.step
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.step == SelfTestStep::Finished
+    }
+
+    pub fn report(&self) -> &SelfTestReport {
+        &self.report
+    }
+
+    /// Progress of the running step as fraction between 0 and 1.
+    pub fn progress(&self, now: Instant) -> f32 {
+        match (self.step, self.cycle_start) {
+            (SelfTestStep::Heartbeat, _) => 0.0,
+            (SelfTestStep::Enable, _) => 0.1,
+            (SelfTestStep::Cycle, Some(start)) => {
+                let elapsed = now.saturating_duration_since(start).as_secs_f32();
+                0.1 + 0.9 * (elapsed / self.config.cycle_duration.as_secs_f32()).min(1.0)
+            }
+            (SelfTestStep::Cycle, None) => 0.1,
+            (SelfTestStep::Finished, _) => 1.0,
+        }
+    }
+
+    /// Runs the next part of the test.
+    pub fn advance(&mut self, tec: &mut Tec, now: Instant) -> Result<(), std::io::Error> {
+        let result = self.try_advance(tec, now);
+        if let Err(e) = &result {
+            self.report.checks.push(CheckResult::new(
+                "Communication",
+                false,
+                format!("Test aborted ({e})"),
+            ));
+            self.abort(tec);
+        }
+        result
+    }
+
+    /// Stops the test and disables the TEC, the report stays unfinished.
+    pub fn abort(&mut self, tec: &mut Tec) {
+        if self.step != SelfTestStep::Heartbeat {
+            let _ = tec.disable();
+        }
+        self.step = SelfTestStep::Finished;
+    }
+
+    fn try_advance(&mut self, tec: &mut Tec, now: Instant) -> Result<(), std::io::Error> {
+        match self.step {
+            SelfTestStep::Heartbeat => {
+                let status = tec.heart_beat()?;
+                self.report.checks.extend(check_flags(status));
+                self.step = SelfTestStep::Enable;
+            }
+            SelfTestStep::Enable => {
+                self.baseline = Some(tec.monitor()?);
+                self.step = SelfTestStep::Cycle;
+                let config = self.config;
+                tec.enable(
+                    config.p,
+                    config.i,
+                    config.d,
+                    config.power_level,
+                    config.setpoint,
+                )?;
+                let read = (
+                    tec.p_coefficient()?,
+                    tec.i_coefficient()?,
+                    tec.d_coefficient()?,
+                );
+                self.report
+                    .checks
+                    .extend(check_pid((config.p, config.i, config.d), read));
+                self.cycle_start = Some(now);
+            }
+            SelfTestStep::Cycle => {
+                let data = tec.monitor()?;
+                self.peak_current = self.peak_current.max(data.tec_current);
+                self.min_temperature = self.min_temperature.min(data.tec_temperature);
+                let start = *self.cycle_start.get_or_insert(now);
+                if now.saturating_duration_since(start) >= self.config.cycle_duration {
+                    tec.disable()?;
+                    if let Some(baseline) = &self.baseline {
+                        self.report.checks.extend(check_cycle(
+                            baseline,
+                            self.peak_current,
+                            self.min_temperature,
+                            &self.config,
+                        ));
+                    }
+                    self.report.finished = Some(Utc::now());
+                    self.step = SelfTestStep::Finished;
+                }
+            }
+            SelfTestStep::Finished => {}
+        }
+        Ok(())
+    }
+}
+
+fn check_flags(status: TecStatus) -> Vec<CheckResult> {
+    REQUIRED_FLAGS
+        .iter()
+        .map(|(flag, name)| {
+            let passed = status.contains(*flag);
+            CheckResult::new(
+                *name,
+                passed,
+                if passed { "OK" } else { "Flag not set" }.to_owned(),
+            )
+        })
+        .collect()
+}
+
+fn check_pid(written: (f32, f32, f32), read: (f32, f32, f32)) -> Vec<CheckResult> {
+    [
+        ("P readback", written.0, read.0),
+        ("I readback", written.1, read.1),
+        ("D readback", written.2, read.2),
+    ]
+    .into_iter()
+    .map(|(name, written, read)| {
+        let passed = (written - read).abs() <= written.abs() * 1e-4 + 1e-6;
+        CheckResult::new(name, passed, format!("wrote {written}, read {read}"))
+    })
+    .collect()
+}
+
+fn check_cycle(
+    baseline: &MonitoringData,
+    peak_current: f32,
+    min_temperature: f32,
+    config: &SelfTestConfig,
+) -> Vec<CheckResult> {
+    let current_rise = peak_current - baseline.tec_current;
+    let temperature_drop = baseline.tec_temperature - min_temperature;
+    vec![
+        CheckResult::new(
+            "Current rise",
+            current_rise >= config.min_current_rise,
+            format!(
+                "{:.2} A -> {:.2} A (min. rise {:.2} A)",
+                baseline.tec_current, peak_current, config.min_current_rise
+            ),
+        ),
+        CheckResult::new(
+            "Temperature drop",
+            temperature_drop >= config.min_temperature_drop,
+            format!(
+                "{:.2} C -> {:.2} C (min. drop {:.2} C)",
+                baseline.tec_temperature, min_temperature, config.min_temperature_drop
+            ),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn baseline() -> MonitoringData {
+        MonitoringData {
+            timestamp: Utc::now(),
+            tec_temperature: 20.0,
+            pcb_temperature: 25.0,
+            humidity: 40.0,
+            dew_point_temperature: 10.0,
+            tec_voltage: 0.0,
+            tec_current: 0.1,
+            tec_power_level: 0,
+        }
+    }
+
+    #[test]
+    fn flags() {
+        let checks = check_flags(TecStatus::all() - TecStatus::HUM_SENSE_OK);
+        assert_eq!(checks.len(), REQUIRED_FLAGS.len());
+        let failed: Vec<_> = checks.iter().filter(|c| !c.passed).collect();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].name, "Humidity sensor");
+    }
+
+    #[test]
+    fn pid() {
+        let checks = check_pid((100.0, 1.0, 1.0), (100.0, 1.0, 0.0));
+        assert!(checks[0].passed);
+        assert!(checks[1].passed);
+        assert!(!checks[2].passed);
+    }
+
+    #[test]
+    fn cycle() {
+        let config = SelfTestConfig::default();
+        let checks = check_cycle(&baseline(), 3.0, 15.0, &config);
+        assert!(checks.iter().all(|c| c.passed));
+        let checks = check_cycle(&baseline(), 0.2, 19.9, &config);
+        assert!(checks.iter().all(|c| !c.passed));
+    }
+
+    #[test]
+    fn report() {
+        let mut report = SelfTestReport {
+            started: Utc::now(),
+            finished: None,
+            checks: check_flags(TecStatus::all()),
+        };
+        assert!(!report.passed());
+        report.finished = Some(Utc::now());
+        assert!(report.passed());
+        assert!(report.to_string().ends_with("Result: PASS"));
+    }
+}
@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+/// Upper bounds of the latency buckets in seconds.
+pub const LATENCY_BUCKETS: &[f64] = &[0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0];
+
+/// Distribution of the time between sending a command and receiving its response.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LatencyHistogram {
+    /// Number of observations per bucket of [`LATENCY_BUCKETS`], the last entry counts the rest.
+    counts: [u64; LATENCY_BUCKETS.len() + 1],
+    sum: Duration,
+    count: u64,
+}
+
+impl LatencyHistogram {
+    pub fn observe(&mut self, latency: Duration) {
+        let seconds = latency.as_secs_f64();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|&bound| seconds <= bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.counts[bucket] += 1;
+        self.sum += latency;
+        self.count += 1;
+    }
+
+    /// Number of observations less than or equal to each bound of [`LATENCY_BUCKETS`].
+    pub fn cumulative_counts(&self) -> impl Iterator<Item = (f64, u64)> + '_ {
+        LATENCY_BUCKETS
+            .iter()
+            .zip(self.counts.iter().scan(0, |total, count| {
+                *total += count;
+                Some(*total)
+            }))
+            .map(|(&bound, count)| (bound, count))
+    }
+
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+/// Counters of the serial communication since the [`crate::Tec`] was created.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ProtocolStats {
+    pub commands_sent: u64,
+    /// Responses that failed the checksum.
+    pub crc_errors: u64,
+    /// Responses that did not belong to the sent command.
+    pub op_code_mismatches: u64,
+    /// Failed reads and writes on the serial port.
+    pub io_errors: u64,
+    pub reconnects: u64,
+    /// Latency of the commands that received a response.
+    pub latency: LatencyHistogram,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram() {
+        let mut histogram = LatencyHistogram::default();
+        histogram.observe(Duration::from_micros(500));
+        histogram.observe(Duration::from_millis(3));
+        histogram.observe(Duration::from_millis(3));
+        histogram.observe(Duration::from_secs(2));
+        let counts: Vec<_> = histogram.cumulative_counts().collect();
+        assert_eq!(counts.len(), LATENCY_BUCKETS.len());
+        assert_eq!(counts[0], (0.001, 1));
+        assert_eq!(counts[1], (0.0025, 1));
+        assert_eq!(counts[2], (0.005, 3));
+        assert_eq!(counts[LATENCY_BUCKETS.len() - 1], (1.0, 3));
+        assert_eq!(histogram.count(), 4);
+        assert_eq!(histogram.sum(), Duration::from_micros(2_006_500));
+    }
+}
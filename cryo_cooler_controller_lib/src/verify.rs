@@ -0,0 +1,229 @@
+use crate::TecStatus;
+
+/// Settings for reading back values after writing them to the cooler.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VerifyConfig {
+    /// Maximum relative deviation of the P/I/D coefficients.
+    pub tolerance: f32,
+    /// Maximum absolute deviation of the setpoint offset in C.
+    ///
+    /// The cooler reports the offset in whole degrees.
+    pub setpoint_tolerance: f32,
+    /// How often a rejected value is written again before giving up.
+    pub retries: u8,
+}
+
+impl Default for VerifyConfig {
+    fn default() -> Self {
+        Self {
+            tolerance: 0.001,
+            setpoint_tolerance: 1.0,
+            retries: 2,
+        }
+    }
+}
+
+impl VerifyConfig {
+    /// Whether `read` matches `written` within the tolerance of `parameter`.
+    pub fn accepts(&self, parameter: Parameter, written: f32, read: f32) -> bool {
+        let tolerance = match parameter {
+            Parameter::SetpointOffset => self.setpoint_tolerance,
+            Parameter::P | Parameter::I | Parameter::D => self.tolerance * written.abs(),
+        };
+        (written - read).abs() <= tolerance + f32::EPSILON
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parameter {
+    SetpointOffset,
+    P,
+    I,
+    D,
+}
+
+impl Parameter {
+    pub const ALL: &[Parameter] = &[
+        Parameter::SetpointOffset,
+        Parameter::P,
+        Parameter::I,
+        Parameter::D,
+    ];
+}
+
+impl std::fmt::Display for Parameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Parameter::SetpointOffset => write!(f, "setpoint offset"),
+            Parameter::P => write!(f, "P coefficient"),
+            Parameter::I => write!(f, "I coefficient"),
+            Parameter::D => write!(f, "D coefficient"),
+        }
+    }
+}
+
+/// Regulation parameters as used by the cooler.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeviceParameters {
+    pub setpoint_offset: f32,
+    pub p: f32,
+    pub i: f32,
+    pub d: f32,
+}
+
+impl DeviceParameters {
+    pub fn get(&self, parameter: Parameter) -> f32 {
+        match parameter {
+            Parameter::SetpointOffset => self.setpoint_offset,
+            Parameter::P => self.p,
+            Parameter::I => self.i,
+            Parameter::D => self.d,
+        }
+    }
+
+    /// Parameters of `device` that do not match the `configured` ones.
+    pub fn mismatches(
+        configured: &DeviceParameters,
+        device: &DeviceParameters,
+        verify: &VerifyConfig,
+    ) -> Vec<Parameter> {
+        Parameter::ALL
+            .iter()
+            .copied()
+            .filter(|&parameter| {
+                !verify.accepts(parameter, configured.get(parameter), device.get(parameter))
+            })
+            .collect()
+    }
+}
+
+/// Reason a verified write failed, carried inside the returned [`std::io::Error`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VerifyError {
+    /// The value read back differs from the written one after all retries.
+    Mismatch {
+        parameter: Parameter,
+        written: f32,
+        read: f32,
+    },
+    /// The cooler flagged the written PID values.
+    PidRejected { status: TecStatus },
+}
+
+impl std::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            VerifyError::Mismatch {
+                parameter,
+                written,
+                read,
+            } => write!(
+                f,
+                "Cooler rejected the {parameter}: wrote {written}, read back {read}"
+            ),
+            VerifyError::PidRejected { status } => {
+                let mut reasons = Vec::new();
+                if status.contains(TecStatus::PID_INVALID) {
+                    reasons.push("invalid");
+                }
+                if status.contains(TecStatus::PID_OUT_OF_RANGE) {
+                    reasons.push("out of range");
+                }
+                write!(
+                    f,
+                    "Cooler reports the PID values as {}",
+                    reasons.join(" and ")
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+impl From<VerifyError> for std::io::Error {
+    fn from(value: VerifyError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, value)
+    }
+}
+
+impl VerifyError {
+    /// Returns the verification failure if `error` was caused by one.
+    pub fn from_io(error: &std::io::Error) -> Option<&VerifyError> {
+        error.get_ref()?.downcast_ref()
+    }
+
+    pub(crate) fn check_pid_flags(status: TecStatus) -> Result<(), VerifyError> {
+        let rejected = status & (TecStatus::PID_INVALID | TecStatus::PID_OUT_OF_RANGE);
+        if rejected.is_empty() {
+            Ok(())
+        } else {
+            Err(VerifyError::PidRejected { status: rejected })
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tolerance() {
+        let config = VerifyConfig::default();
+        assert!(config.accepts(Parameter::P, 100.0, 100.05));
+        assert!(!config.accepts(Parameter::P, 100.0, 100.5));
+        assert!(config.accepts(Parameter::D, 0.0, 0.0));
+        assert!(!config.accepts(Parameter::D, 0.0, 0.1));
+        assert!(config.accepts(Parameter::SetpointOffset, 2.5, 2.0));
+        assert!(!config.accepts(Parameter::SetpointOffset, 2.5, 4.0));
+    }
+
+    #[test]
+    fn mismatches() {
+        let configured = DeviceParameters {
+            setpoint_offset: 2.0,
+            p: 100.0,
+            i: 1.0,
+            d: 1.0,
+        };
+        let device = DeviceParameters {
+            setpoint_offset: 2.0,
+            p: 50.0,
+            d: 0.0,
+            ..configured
+        };
+        assert!(
+            DeviceParameters::mismatches(&configured, &configured, &VerifyConfig::default())
+                .is_empty()
+        );
+        assert_eq!(
+            DeviceParameters::mismatches(&configured, &device, &VerifyConfig::default()),
+            vec![Parameter::P, Parameter::D]
+        );
+    }
+
+    #[test]
+    fn pid_flags() {
+        assert_eq!(
+            VerifyError::check_pid_flags(TecStatus::PID_READY | TecStatus::PID_RUNNING),
+            Ok(())
+        );
+        let err = VerifyError::check_pid_flags(TecStatus::PID_READY | TecStatus::PID_OUT_OF_RANGE)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            VerifyError::PidRejected {
+                status: TecStatus::PID_OUT_OF_RANGE
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Cooler reports the PID values as out of range"
+        );
+
+        let io_err = std::io::Error::from(err);
+        assert_eq!(VerifyError::from_io(&io_err), Some(&err));
+        assert_eq!(VerifyError::from_io(&std::io::Error::other("other")), None);
+    }
+}
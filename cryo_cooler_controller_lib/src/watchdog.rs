@@ -0,0 +1,184 @@
+use std::time::{Duration, Instant};
+
+use crate::TecStatus;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchdogConfig {
+    /// How often a heartbeat should be exchanged with the cooler.
+    pub heartbeat_interval: Duration,
+    /// Time without a successful heartbeat after which the cooler is considered lost.
+    pub timeout: Duration,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat_interval: Duration::from_millis(500),
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchdogEvent {
+    /// No heartbeat succeeded within the configured timeout.
+    HeartbeatMissed { since: Duration },
+    /// A heartbeat succeeded again after [`WatchdogEvent::HeartbeatMissed`].
+    HeartbeatRestored { outage: Duration },
+    /// The cooler reported [`TecStatus::FAILSAFE_ACTIVE`].
+    FailsafeActivated,
+    /// [`TecStatus::FAILSAFE_ACTIVE`] is no longer reported.
+    FailsafeCleared,
+}
+
+impl WatchdogEvent {
+    /// Whether the event marks the end of a fault.
+    pub fn is_recovery(&self) -> bool {
+        matches!(
+            self,
+            WatchdogEvent::HeartbeatRestored { .. } | WatchdogEvent::FailsafeCleared
+        )
+    }
+}
+
+/// Tracks the heartbeat exchange with the cooler.
+///
+/// The watchdog does not talk to the cooler itself, the caller reports each heartbeat
+/// attempt and periodically calls [`Watchdog::check`].
+#[derive(Debug, Clone)]
+pub struct Watchdog {
+    config: WatchdogConfig,
+    last_attempt: Option<Instant>,
+    last_heartbeat: Instant,
+    missed: bool,
+    failsafe_active: bool,
+}
+
+impl Watchdog {
+    pub fn new(config: WatchdogConfig, now: Instant) -> Self {
+        Self {
+            config,
+            last_attempt: None,
+            last_heartbeat: now,
+            missed: false,
+            failsafe_active: false,
+        }
+    }
+
+    pub fn config(&self) -> WatchdogConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: WatchdogConfig) {
+        self.config = config;
+    }
+
+    /// Returns true if the next heartbeat should be sent.
+    pub fn heartbeat_due(&self, now: Instant) -> bool {
+        match self.last_attempt {
+            Some(last) => now.saturating_duration_since(last) >= self.config.heartbeat_interval,
+            None => true,
+        }
+    }
+
+    /// Records that a heartbeat was sent, regardless of its outcome.
+    pub fn record_attempt(&mut self, now: Instant) {
+        self.last_attempt = Some(now);
+    }
+
+    /// Records a successful heartbeat and returns the resulting transitions.
+    pub fn record_heartbeat(&mut self, status: TecStatus, now: Instant) -> Vec<WatchdogEvent> {
+        let mut events = Vec::new();
+        if self.missed {
+            self.missed = false;
+            events.push(WatchdogEvent::HeartbeatRestored {
+                outage: now.saturating_duration_since(self.last_heartbeat),
+            });
+        }
+        self.last_heartbeat = now;
+
+        let failsafe_active = status.contains(TecStatus::FAILSAFE_ACTIVE);
+        if failsafe_active != self.failsafe_active {
+            self.failsafe_active = failsafe_active;
+            events.push(if failsafe_active {
+                WatchdogEvent::FailsafeActivated
+            } else {
+                WatchdogEvent::FailsafeCleared
+            });
+        }
+        events
+    }
+
+    /// Reports [`WatchdogEvent::HeartbeatMissed`] once the timeout expired.
+    pub fn check(&mut self, now: Instant) -> Option<WatchdogEvent> {
+        let since = now.saturating_duration_since(self.last_heartbeat);
+        if !self.missed && since > self.config.timeout {
+            self.missed = true;
+            return Some(WatchdogEvent::HeartbeatMissed { since });
+        }
+        None
+    }
+
+    pub fn is_heartbeat_missed(&self) -> bool {
+        self.missed
+    }
+
+    pub fn is_failsafe_active(&self) -> bool {
+        self.failsafe_active
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missed_and_restored() {
+        let start = Instant::now();
+        let mut watchdog = Watchdog::new(WatchdogConfig::default(), start);
+        assert_eq!(watchdog.check(start + Duration::from_secs(1)), None);
+
+        let late = start + Duration::from_secs(6);
+        assert!(matches!(
+            watchdog.check(late),
+            Some(WatchdogEvent::HeartbeatMissed { .. })
+        ));
+        assert_eq!(watchdog.check(late), None);
+
+        let events = watchdog.record_heartbeat(TecStatus::BOARD_INIT, late);
+        assert_eq!(
+            events,
+            vec![WatchdogEvent::HeartbeatRestored {
+                outage: Duration::from_secs(6)
+            }]
+        );
+        assert!(!watchdog.is_heartbeat_missed());
+    }
+
+    #[test]
+    fn failsafe_transitions() {
+        let start = Instant::now();
+        let mut watchdog = Watchdog::new(WatchdogConfig::default(), start);
+        assert_eq!(
+            watchdog.record_heartbeat(TecStatus::FAILSAFE_ACTIVE, start),
+            vec![WatchdogEvent::FailsafeActivated]
+        );
+        assert!(watchdog
+            .record_heartbeat(TecStatus::FAILSAFE_ACTIVE, start)
+            .is_empty());
+        let events = watchdog.record_heartbeat(TecStatus::empty(), start);
+        assert_eq!(events, vec![WatchdogEvent::FailsafeCleared]);
+        assert!(events[0].is_recovery());
+    }
+
+    #[test]
+    fn heartbeat_due() {
+        let start = Instant::now();
+        let mut watchdog = Watchdog::new(WatchdogConfig::default(), start);
+        assert!(watchdog.heartbeat_due(start));
+        watchdog.record_attempt(start);
+        assert!(!watchdog.heartbeat_due(start + Duration::from_millis(100)));
+        assert!(watchdog.heartbeat_due(start + Duration::from_millis(500)));
+    }
+}